@@ -0,0 +1,317 @@
+use crate::errors::CliError;
+use crate::files::{
+    read_bytes, read_hex, read_json, read_messages, read_used_nonces, used_nonces_path,
+    write_bytes, write_json, write_private_json, write_used_nonces, GroupFile, KeyFile,
+    PublicKeyFile, SignatureFile, StateFile,
+};
+use bellman::pairing::bn256::Bn256;
+use franklin_crypto::alt_babyjubjub::{fs::Fs, AltJubjubBn256};
+use franklin_crypto::eddsa::{PrivateKey, PublicKey};
+use franklin_crypto::jubjub::FixedGenerators;
+use franklin_crypto::rescue::bn256::Bn256RescueParams;
+use musig::aggregated_pubkey::KeyAggregationContext;
+use musig::hash_suite::DefaultHashSuite;
+use musig::serialization::{SerdePublicKey, SerdeScalar};
+use musig::signature::MusigSignature;
+use musig::signer::{MuSigSigner, UsedNonces};
+use musig::verifier::MuSigVerifier;
+use musig::wire::{
+    collect_commitments, collect_pre_commitments, collect_signature_shares, Payload, SessionId,
+    WireMessage, SESSION_ID_LENGTH,
+};
+use rand::{Rand, Rng};
+use std::path::{Path, PathBuf};
+
+/// Same generator as the default one of the WASM bindings
+pub const GENERATOR: FixedGenerators = FixedGenerators::SpendingKeyGenerator;
+
+/// Parameters shared by all parties, signers borrow them
+pub struct Params {
+    pub jubjub_params: AltJubjubBn256,
+    pub rescue_params: Bn256RescueParams,
+}
+
+impl Params {
+    pub fn new() -> Self {
+        Self {
+            jubjub_params: AltJubjubBn256::new(),
+            rescue_params: Bn256RescueParams::new_checked_2_into_1(),
+        }
+    }
+
+    pub fn hash_suite(&self) -> DefaultHashSuite<Bn256> {
+        DefaultHashSuite::new(&self.rescue_params)
+    }
+
+    fn signer(
+        &self,
+        state: &[u8],
+        used_nonces: &UsedNonces,
+    ) -> Result<MuSigSigner<Bn256>, CliError> {
+        let signer = MuSigSigner::import_state(
+            state,
+            &self.jubjub_params,
+            GENERATOR,
+            self.hash_suite(),
+            used_nonces,
+        )?;
+
+        Ok(signer)
+    }
+}
+
+/// Generates a key pair, writes it to `out` and
+/// the public key alone to `public_out`.
+pub fn keygen(rng: &mut impl Rng, out: &Path, public_out: &Path) -> Result<(), CliError> {
+    let params = Params::new();
+
+    let private_key = PrivateKey::<Bn256>(Fs::rand(rng));
+    let public_key = PublicKey::from_private(&private_key, GENERATOR, &params.jubjub_params);
+
+    write_private_json(
+        out,
+        &KeyFile {
+            private_key: SerdeScalar::new(private_key.0),
+            public_key: SerdePublicKey(public_key.clone()),
+        },
+    )?;
+    write_json(
+        public_out,
+        &PublicKeyFile {
+            public_key: SerdePublicKey(public_key),
+        },
+    )
+}
+
+/// Computes aggregated public key of the parties, positions
+/// follow the order of public key files.
+pub fn aggregate(pubkey_files: &[PathBuf], out: &Path) -> Result<(), CliError> {
+    let params = Params::new();
+
+    let mut pubkeys = vec![];
+    for path in pubkey_files {
+        let file: PublicKeyFile = read_json(path)?;
+        pubkeys.push(file.public_key.0);
+    }
+
+    let context =
+        KeyAggregationContext::new(&pubkeys, &params.jubjub_params, &params.hash_suite())?;
+
+    write_json(
+        out,
+        &GroupFile {
+            pubkeys: pubkeys.into_iter().map(SerdePublicKey).collect(),
+            aggregated_pubkey: SerdePublicKey(context.aggregated_pubkey),
+        },
+    )
+}
+
+/// First round. Starts the session, writes signer state to `state` and
+/// pre-commitment of this party to `out`.
+pub fn precommit(
+    rng: &mut impl Rng,
+    group: &Path,
+    key: &Path,
+    session_id: &str,
+    state: &Path,
+    out: &Path,
+) -> Result<(), CliError> {
+    let params = Params::new();
+    let session_id = parse_session_id(session_id)?;
+
+    let pubkeys = read_group(group, &params)?;
+    let key: KeyFile = read_json(key)?;
+    let position = pubkeys
+        .iter()
+        .position(|pubkey| pubkey.0 == key.public_key.0 .0)
+        .ok_or(CliError::KeyNotInGroup)?;
+
+    let mut signer = MuSigSigner::new(
+        &pubkeys,
+        position,
+        &params.jubjub_params,
+        GENERATOR,
+        params.hash_suite(),
+    )?;
+    signer.set_session_id(session_id)?;
+    let pre_commitment = signer.compute_precommitment(rng)?;
+
+    write_state(state, &signer)?;
+    write_message(
+        out,
+        session_id,
+        position,
+        Payload::PreCommitment(pre_commitment),
+    )
+}
+
+/// Second round. Reads pre-commitments of all parties
+/// and writes commitment of this party to `out`.
+pub fn commit(state: &Path, inputs: &[PathBuf], out: &Path) -> Result<(), CliError> {
+    let params = Params::new();
+    let mut signer = read_state(state, &params, &UsedNonces::new())?;
+    let session_id = signer_session_id(&signer)?;
+
+    let messages = read_messages(inputs, &params.jubjub_params)?;
+    let pre_commitments = collect_pre_commitments(&messages, &session_id, signer.pubkeys().len())?;
+    let commitment = signer.receive_precommitments(&pre_commitments)?;
+
+    write_state(state, &signer)?;
+    write_message(
+        out,
+        session_id,
+        signer.position(),
+        Payload::Commitment(commitment),
+    )
+}
+
+/// Third round. Reads commitments of all parties and writes signature
+/// share of this party for the message to `out`. The nonce is erased from
+/// the state and its commitment is added to the used nonces file of the
+/// key before the share is written. A copy of the state from before
+/// signing is refused afterwards, as long as the used nonces file is kept.
+pub fn sign(
+    state: &Path,
+    key: &Path,
+    message: &Path,
+    inputs: &[PathBuf],
+    out: &Path,
+) -> Result<(), CliError> {
+    let params = Params::new();
+    let used_nonces_path = used_nonces_path(key);
+    let mut used_nonces = read_used_nonces(&used_nonces_path)?;
+    let mut signer = read_state(state, &params, &used_nonces)?;
+    let session_id = signer_session_id(&signer)?;
+    let key: KeyFile = read_json(key)?;
+    let message = read_bytes(message)?;
+
+    let messages = read_messages(inputs, &params.jubjub_params)?;
+    let commitments = collect_commitments(&messages, &session_id, signer.pubkeys().len())?;
+    signer.receive_commitments(&commitments)?;
+    let signature_share = signer.sign(&PrivateKey(key.private_key.0), &message)?;
+
+    if let Some(nonce_commitment) = signer.nonce_commitment() {
+        used_nonces.insert(nonce_commitment);
+    }
+    write_used_nonces(&used_nonces_path, &used_nonces)?;
+    write_state(state, &signer)?;
+    write_message(
+        out,
+        session_id,
+        signer.position(),
+        Payload::SignatureShare(signature_share),
+    )
+}
+
+/// Reads signature shares of all parties, verifies them
+/// and writes aggregated signature to `out`.
+pub fn finalize(state: &Path, inputs: &[PathBuf], out: &Path) -> Result<(), CliError> {
+    let params = Params::new();
+    let signer = read_state(state, &params, &UsedNonces::new())?;
+    let session_id = signer_session_id(&signer)?;
+
+    let messages = read_messages(inputs, &params.jubjub_params)?;
+    let signature_shares =
+        collect_signature_shares(&messages, &session_id, signer.pubkeys().len())?;
+    let signature = signer.receive_signatures(&signature_shares)?;
+
+    write_json(
+        out,
+        &SignatureFile {
+            signature: hex::encode(MusigSignature::from(signature).to_bytes()),
+            session_id: hex::encode(session_id),
+        },
+    )
+}
+
+/// Verifies aggregated signature of the group for the message
+/// in the session recorded in the signature file
+pub fn verify(group: &Path, message: &Path, signature: &Path) -> Result<(), CliError> {
+    let params = Params::new();
+
+    let pubkeys = read_group(group, &params)?;
+    let message = read_bytes(message)?;
+    let file: SignatureFile = read_json(signature)?;
+    let signature = MusigSignature::from_bytes(
+        &read_hex(signature, &file.signature)?,
+        &params.jubjub_params,
+    )?;
+    let session_id = parse_session_id(&file.session_id)?;
+
+    let is_valid = MuSigVerifier::verify_in_session(
+        &message,
+        &session_id,
+        &pubkeys,
+        &signature.into(),
+        &params.jubjub_params,
+        GENERATOR,
+        &params.hash_suite(),
+    )?;
+
+    if !is_valid {
+        return Err(CliError::InvalidSignature);
+    }
+
+    Ok(())
+}
+
+fn parse_session_id(encoded: &str) -> Result<SessionId, CliError> {
+    let decoded = hex::decode(encoded).map_err(|_| CliError::InvalidSessionId)?;
+    if decoded.len() != SESSION_ID_LENGTH {
+        return Err(CliError::InvalidSessionId);
+    }
+
+    let mut session_id = [0u8; SESSION_ID_LENGTH];
+    session_id.copy_from_slice(&decoded);
+
+    Ok(session_id)
+}
+
+// reads public keys of the group and checks its aggregated key
+fn read_group(path: &Path, params: &Params) -> Result<Vec<PublicKey<Bn256>>, CliError> {
+    let group: GroupFile = read_json(path)?;
+    let pubkeys: Vec<_> = group.pubkeys.into_iter().map(|pubkey| pubkey.0).collect();
+
+    let context =
+        KeyAggregationContext::new(&pubkeys, &params.jubjub_params, &params.hash_suite())?;
+    if context.aggregated_pubkey.0 != group.aggregated_pubkey.0 .0 {
+        return Err(CliError::InvalidGroupFile);
+    }
+
+    Ok(pubkeys)
+}
+
+// nonce of the state is dropped if it is in used nonces
+fn read_state<'a>(
+    path: &Path,
+    params: &'a Params,
+    used_nonces: &UsedNonces,
+) -> Result<MuSigSigner<'a, Bn256>, CliError> {
+    let file: StateFile = read_json(path)?;
+
+    params.signer(&read_hex(path, &file.state)?, used_nonces)
+}
+
+fn write_state(path: &Path, signer: &MuSigSigner<Bn256>) -> Result<(), CliError> {
+    write_private_json(
+        path,
+        &StateFile {
+            state: hex::encode(signer.export_state()),
+        },
+    )
+}
+
+fn signer_session_id(signer: &MuSigSigner<Bn256>) -> Result<SessionId, CliError> {
+    signer.session_id().copied().ok_or(CliError::SessionNotSet)
+}
+
+fn write_message(
+    path: &Path,
+    session_id: SessionId,
+    position: usize,
+    payload: Payload<Bn256>,
+) -> Result<(), CliError> {
+    let message = WireMessage::new(session_id, position, payload)?;
+
+    write_bytes(path, &message.to_bytes())
+}
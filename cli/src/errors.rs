@@ -0,0 +1,31 @@
+use musig::errors::MusigError;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("{0}")]
+    Musig(#[from] MusigError),
+    #[error("Can't access {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("File {path} is malformed: {source}")]
+    MalformedFile {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    #[error("File {0} has malformed hex")]
+    MalformedHex(PathBuf),
+    #[error("Session id must be 32 bytes in hex")]
+    InvalidSessionId,
+    #[error("Public key of the key file is not in the group")]
+    KeyNotInGroup,
+    #[error("Aggregated public key of the group file does not match its public keys")]
+    InvalidGroupFile,
+    #[error("Signer state is not bound to a session")]
+    SessionNotSet,
+    #[error("Signature is not valid")]
+    InvalidSignature,
+    #[error("Can't access system randomness: {0}")]
+    Randomness(io::Error),
+}
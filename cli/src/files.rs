@@ -0,0 +1,167 @@
+//! Files read and written by the tool.
+//!
+//! Keys, groups, signer states and signatures are JSON files, points and
+//! scalars in them are hex strings encoded as in the WASM bindings. Round
+//! files are binary `musig::wire` messages, so they carry the session id,
+//! the round and the position of the sender. Key, state and used nonces
+//! files hold secrets or guard them, they are written readable by the
+//! owner only.
+use crate::errors::CliError;
+use bellman::pairing::bn256::Bn256;
+use franklin_crypto::alt_babyjubjub::AltJubjubBn256;
+use musig::serialization::{SerdePublicKey, SerdeScalar};
+use musig::signer::UsedNonces;
+use musig::wire::WireMessage;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Key pair of a party, the private key must stay on its machine
+#[derive(Serialize, Deserialize)]
+pub struct KeyFile {
+    pub private_key: SerdeScalar<Bn256>,
+    pub public_key: SerdePublicKey<Bn256>,
+}
+
+/// Public key of a party, shared with the others
+#[derive(Serialize, Deserialize)]
+pub struct PublicKeyFile {
+    pub public_key: SerdePublicKey<Bn256>,
+}
+
+/// Public keys of a signing group in order of positions
+/// and their aggregated public key
+#[derive(Serialize, Deserialize)]
+pub struct GroupFile {
+    pub pubkeys: Vec<SerdePublicKey<Bn256>>,
+    pub aggregated_pubkey: SerdePublicKey<Bn256>,
+}
+
+/// State of a party between rounds, exported with
+/// `MuSigSigner::export_state`. It holds the secret nonce.
+#[derive(Serialize, Deserialize)]
+pub struct StateFile {
+    pub state: String,
+}
+
+/// Nonce commitments a key has signed with, `UsedNonces::to_bytes` in hex.
+/// It is kept next to the key file, see `used_nonces_path`.
+#[derive(Serialize, Deserialize)]
+pub struct UsedNoncesFile {
+    pub used_nonces: String,
+}
+
+/// Aggregated signature, `MusigSignature::to_bytes` in hex, and the
+/// session it is bound to in hex
+#[derive(Serialize, Deserialize)]
+pub struct SignatureFile {
+    pub signature: String,
+    pub session_id: String,
+}
+
+pub fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, CliError> {
+    let contents = read_bytes(path)?;
+
+    serde_json::from_slice(&contents).map_err(|source| CliError::MalformedFile {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+pub fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), CliError> {
+    let contents = serde_json::to_vec_pretty(value).expect("has serialized file");
+
+    write_bytes(path, &contents)
+}
+
+/// Same as `write_json` but the file is readable by the owner only
+pub fn write_private_json<T: Serialize>(path: &Path, value: &T) -> Result<(), CliError> {
+    let contents = serde_json::to_vec_pretty(value).expect("has serialized file");
+
+    write_private_bytes(path, &contents)
+}
+
+pub fn read_bytes(path: &Path) -> Result<Vec<u8>, CliError> {
+    fs::read(path).map_err(|source| CliError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+pub fn write_bytes(path: &Path, contents: &[u8]) -> Result<(), CliError> {
+    fs::write(path, contents).map_err(|source| CliError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+pub fn write_private_bytes(path: &Path, contents: &[u8]) -> Result<(), CliError> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    options
+        .open(path)
+        .and_then(|mut file| {
+            // mode is only applied to new files
+            #[cfg(unix)]
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+            file.write_all(contents)
+        })
+        .map_err(|source| CliError::Io {
+            path: path.to_path_buf(),
+            source,
+        })
+}
+
+/// Used nonces file of a key, `<key file>.used`
+pub fn used_nonces_path(key: &Path) -> PathBuf {
+    let mut path = key.as_os_str().to_owned();
+    path.push(".used");
+
+    PathBuf::from(path)
+}
+
+/// Reads used nonces of a key, none are used before its first signature
+pub fn read_used_nonces(path: &Path) -> Result<UsedNonces, CliError> {
+    if !path.exists() {
+        return Ok(UsedNonces::new());
+    }
+
+    let file: UsedNoncesFile = read_json(path)?;
+    let used_nonces = UsedNonces::from_bytes(&read_hex(path, &file.used_nonces)?)?;
+
+    Ok(used_nonces)
+}
+
+pub fn write_used_nonces(path: &Path, used_nonces: &UsedNonces) -> Result<(), CliError> {
+    write_private_json(
+        path,
+        &UsedNoncesFile {
+            used_nonces: hex::encode(used_nonces.to_bytes()),
+        },
+    )
+}
+
+pub fn read_hex(path: &Path, encoded: &str) -> Result<Vec<u8>, CliError> {
+    hex::decode(encoded).map_err(|_| CliError::MalformedHex(path.to_path_buf()))
+}
+
+/// Reads round files of all parties
+pub fn read_messages(
+    paths: &[PathBuf],
+    jubjub_params: &AltJubjubBn256,
+) -> Result<Vec<WireMessage<Bn256>>, CliError> {
+    let mut messages = vec![];
+    for path in paths {
+        let message = WireMessage::from_bytes(&read_bytes(path)?, jubjub_params)?;
+        messages.push(message);
+    }
+
+    Ok(messages)
+}
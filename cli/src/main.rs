@@ -0,0 +1,163 @@
+mod commands;
+mod errors;
+mod files;
+#[cfg(test)]
+mod tests;
+
+use errors::CliError;
+use rand::OsRng;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Runs a MuSig signing ceremony offline. Each party runs the rounds on
+/// its own machine, round files of all parties are carried between
+/// machines after each round.
+#[derive(StructOpt)]
+#[structopt(name = "musig-cli")]
+enum Command {
+    /// Generates a key pair
+    Keygen {
+        /// Key file, it holds the private key
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+        /// Public key file shared with the other parties
+        #[structopt(long, parse(from_os_str))]
+        public_out: PathBuf,
+    },
+    /// Computes aggregated public key of the group
+    Aggregate {
+        /// Public key files of all parties in order of positions
+        #[structopt(
+            long = "pubkey",
+            required = true,
+            number_of_values = 1,
+            parse(from_os_str)
+        )]
+        pubkeys: Vec<PathBuf>,
+        /// Group file
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// First round, writes pre-commitment of this party
+    Precommit {
+        #[structopt(long, parse(from_os_str))]
+        group: PathBuf,
+        #[structopt(long, parse(from_os_str))]
+        key: PathBuf,
+        /// Session id, 32 bytes in hex. All parties must use the same one
+        /// and it must never be reused.
+        #[structopt(long)]
+        session: String,
+        /// Signer state file, it holds the secret nonce until signing
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// Second round, reads pre-commitments and writes commitment of this party
+    Commit {
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        /// Pre-commitment files of all parties
+        #[structopt(
+            long = "input",
+            required = true,
+            number_of_values = 1,
+            parse(from_os_str)
+        )]
+        inputs: Vec<PathBuf>,
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// Third round, reads commitments and writes signature share of this party
+    Sign {
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        /// Key file, nonces it has signed with are kept in `<key file>.used`
+        #[structopt(long, parse(from_os_str))]
+        key: PathBuf,
+        /// Message file of any length
+        #[structopt(long, parse(from_os_str))]
+        message: PathBuf,
+        /// Commitment files of all parties
+        #[structopt(
+            long = "input",
+            required = true,
+            number_of_values = 1,
+            parse(from_os_str)
+        )]
+        inputs: Vec<PathBuf>,
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// Verifies signature shares and writes aggregated signature
+    Finalize {
+        #[structopt(long, parse(from_os_str))]
+        state: PathBuf,
+        /// Signature share files of all parties
+        #[structopt(
+            long = "input",
+            required = true,
+            number_of_values = 1,
+            parse(from_os_str)
+        )]
+        inputs: Vec<PathBuf>,
+        /// Signature file
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+    },
+    /// Verifies aggregated signature of the group
+    Verify {
+        #[structopt(long, parse(from_os_str))]
+        group: PathBuf,
+        #[structopt(long, parse(from_os_str))]
+        message: PathBuf,
+        #[structopt(long, parse(from_os_str))]
+        signature: PathBuf,
+    },
+}
+
+fn run(command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Keygen { out, public_out } => {
+            let mut rng = OsRng::new().map_err(CliError::Randomness)?;
+            commands::keygen(&mut rng, &out, &public_out)
+        }
+        Command::Aggregate { pubkeys, out } => commands::aggregate(&pubkeys, &out),
+        Command::Precommit {
+            group,
+            key,
+            session,
+            state,
+            out,
+        } => {
+            let mut rng = OsRng::new().map_err(CliError::Randomness)?;
+            commands::precommit(&mut rng, &group, &key, &session, &state, &out)
+        }
+        Command::Commit { state, inputs, out } => commands::commit(&state, &inputs, &out),
+        Command::Sign {
+            state,
+            key,
+            message,
+            inputs,
+            out,
+        } => commands::sign(&state, &key, &message, &inputs, &out),
+        Command::Finalize { state, inputs, out } => commands::finalize(&state, &inputs, &out),
+        Command::Verify {
+            group,
+            message,
+            signature,
+        } => {
+            commands::verify(&group, &message, &signature)?;
+            println!("Signature is valid");
+            Ok(())
+        }
+    }
+}
+
+fn main() {
+    if let Err(err) = run(Command::from_args()) {
+        eprintln!("Error: {}", err);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::commands;
+use crate::errors::CliError;
+use musig::errors::MusigError;
+use rand::{SeedableRng, XorShiftRng};
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("musig-cli-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    dir
+}
+
+fn party_files(dir: &Path, name: &str, number_of_parties: usize) -> Vec<PathBuf> {
+    (0..number_of_parties)
+        .map(|position| dir.join(format!("{}-{}", name, position)))
+        .collect()
+}
+
+#[test]
+fn test_offline_ceremony() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let dir = test_dir("ceremony");
+    let number_of_parties = 3;
+    let session = hex::encode([5u8; 32]);
+
+    let keys = party_files(&dir, "key.json", number_of_parties);
+    let pubkeys = party_files(&dir, "pubkey.json", number_of_parties);
+    let states = party_files(&dir, "state.json", number_of_parties);
+    let pre_commitments = party_files(&dir, "precommitment.msg", number_of_parties);
+    let commitments = party_files(&dir, "commitment.msg", number_of_parties);
+    let signature_shares = party_files(&dir, "share.msg", number_of_parties);
+    let group = dir.join("group.json");
+    let message = dir.join("message");
+    let signature = dir.join("signature.json");
+
+    // longer than 32 bytes, runs bound to a session sign its hash
+    fs::write(&message, [7u8; 100]).unwrap();
+
+    for position in 0..number_of_parties {
+        commands::keygen(rng, &keys[position], &pubkeys[position]).unwrap();
+    }
+    commands::aggregate(&pubkeys, &group).unwrap();
+
+    for position in 0..number_of_parties {
+        commands::precommit(
+            rng,
+            &group,
+            &keys[position],
+            &session,
+            &states[position],
+            &pre_commitments[position],
+        )
+        .unwrap();
+    }
+
+    for position in 0..number_of_parties {
+        commands::commit(&states[position], &pre_commitments, &commitments[position]).unwrap();
+    }
+
+    let stale_state = dir.join("stale-state.json");
+    fs::copy(&states[1], &stale_state).unwrap();
+
+    for position in 0..number_of_parties {
+        commands::sign(
+            &states[position],
+            &keys[position],
+            &message,
+            &commitments,
+            &signature_shares[position],
+        )
+        .unwrap();
+    }
+
+    // the nonce is gone from the state after signing
+    let result = commands::sign(
+        &states[0],
+        &keys[0],
+        &message,
+        &commitments,
+        &dir.join("second-share.msg"),
+    );
+    match result {
+        Err(CliError::Musig(MusigError::NonceAlreadyUsed)) => {}
+        _ => panic!("second signature share is produced"),
+    }
+
+    // a copy of the state from before signing can't sign another message
+    let other_message = dir.join("other-message");
+    fs::write(&other_message, [8u8; 32]).unwrap();
+    let result = commands::sign(
+        &stale_state,
+        &keys[1],
+        &other_message,
+        &commitments,
+        &dir.join("stale-share.msg"),
+    );
+    match result {
+        Err(CliError::Musig(MusigError::NonceAlreadyUsed)) => {}
+        _ => panic!("stale state produces a signature share"),
+    }
+
+    // secrets are readable by the owner only
+    #[cfg(unix)]
+    for path in [&keys[0], &states[0], &dir.join("key.json-0.used")] {
+        let mode = fs::metadata(path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    commands::finalize(&states[0], &signature_shares, &signature).unwrap();
+    commands::verify(&group, &message, &signature).unwrap();
+
+    // signature of another message
+    match commands::verify(&group, &other_message, &signature) {
+        Err(CliError::InvalidSignature) => {}
+        _ => panic!("signature is valid for another message"),
+    }
+
+    // signature of another session
+    let other_session = dir.join("other-session.json");
+    let contents = fs::read_to_string(&signature).unwrap();
+    fs::write(&other_session, contents.replace(&session, &"ab".repeat(32))).unwrap();
+    match commands::verify(&group, &message, &other_session) {
+        Err(CliError::InvalidSignature) => {}
+        _ => panic!("signature is valid in another session"),
+    }
+
+    // round files of a party are missing
+    match commands::finalize(&states[0], &signature_shares[1..], &signature) {
+        Err(CliError::Musig(MusigError::InvalidParticipantPosition)) => {}
+        _ => panic!("signature is aggregated without all shares"),
+    }
+
+    // the whole message is signed, not its first 32 bytes
+    fs::write(&other_message, [7u8; 32]).unwrap();
+    match commands::verify(&group, &other_message, &signature) {
+        Err(CliError::InvalidSignature) => {}
+        _ => panic!("signature is valid for a prefix of the message"),
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,114 @@
+use crate::errors::MusigError;
+use crate::hash_suite::HashSuite;
+use bellman::{Field, PrimeField};
+use franklin_crypto::eddsa::{PublicKey, Signature};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, JubjubParams, Unknown};
+
+/// Aggregated pre-signature `(R, s')` for the adaptor point `T = t * G`.
+/// Challenge is computed over `R + T`, so it becomes a valid signature
+/// `(R + T, s' + t)` only when secret `t` is known, and publishing that
+/// signature reveals `t` to anyone holding the pre-signature.
+#[derive(Clone)]
+pub struct PreSignature<E: JubjubEngine> {
+    pub r: Point<E, Unknown>,
+    pub s: E::Fs,
+    pub adaptor_point: Point<E, Unknown>,
+}
+
+impl<E: JubjubEngine> PreSignature<E> {
+    /// Verifies the pre-signature against aggregated public key:
+    /// s' * G == R + c * X' where c = H_sig(X', R + T, m).
+    /// Hash suite must be the same one signers used.
+    pub fn verify<H: HashSuite<E>>(
+        &self,
+        message: &[u8],
+        aggregated_pubkey: &PublicKey<E>,
+        jubjub_params: &<E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: &H,
+    ) -> bool {
+        if !is_in_correct_subgroup(&aggregated_pubkey.0, jubjub_params)
+            || !is_in_correct_subgroup(&self.r, jubjub_params)
+            || !is_in_correct_subgroup(&self.adaptor_point, jubjub_params)
+        {
+            return false;
+        }
+
+        // c = H_sig(X', R + T, m)
+        let c = hash_suite.hash_signature_data(
+            aggregated_pubkey,
+            &self.adapted_commitment(jubjub_params),
+            message,
+        );
+
+        // s' * G
+        let lhs: Point<E, Unknown> = jubjub_params
+            .generator(generator)
+            .mul(self.s, jubjub_params)
+            .into();
+        // R + c * X'
+        let rhs = aggregated_pubkey
+            .0
+            .mul(c, jubjub_params)
+            .add(&self.r, jubjub_params);
+
+        rhs.eq(&lhs)
+    }
+
+    /// Completes pre-signature with the secret of the adaptor point:
+    /// σ = (R + T, s' + t). Secret is not checked against the adaptor
+    /// point, resulting signature is valid only if T = t * G.
+    pub fn complete(
+        &self,
+        adaptor_secret: &E::Fs,
+        jubjub_params: &<E as JubjubEngine>::Params,
+    ) -> Signature<E> {
+        let mut s = self.s;
+        s.add_assign(adaptor_secret);
+
+        Signature {
+            r: self.adapted_commitment(jubjub_params),
+            s,
+        }
+    }
+
+    /// Extracts secret of the adaptor point from the signature which
+    /// completes this pre-signature: t = s - s'.
+    pub fn extract_secret(
+        &self,
+        signature: &Signature<E>,
+        jubjub_params: &<E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+    ) -> Result<E::Fs, MusigError> {
+        if signature.r != self.adapted_commitment(jubjub_params) {
+            return Err(MusigError::AdaptorSecretMismatch);
+        }
+
+        let mut t = signature.s;
+        t.sub_assign(&self.s);
+
+        // T = t * G
+        let adaptor_point: Point<E, Unknown> = jubjub_params
+            .generator(generator)
+            .mul(t, jubjub_params)
+            .into();
+        if adaptor_point != self.adaptor_point {
+            return Err(MusigError::AdaptorSecretMismatch);
+        }
+
+        Ok(t)
+    }
+
+    // R + T
+    fn adapted_commitment(&self, jubjub_params: &<E as JubjubEngine>::Params) -> Point<E, Unknown> {
+        self.r.add(&self.adaptor_point, jubjub_params)
+    }
+}
+
+fn is_in_correct_subgroup<E: JubjubEngine>(
+    point: &Point<E, Unknown>,
+    jubjub_params: &<E as JubjubEngine>::Params,
+) -> bool {
+    point.mul(E::Fs::char(), jubjub_params) == Point::zero()
+}
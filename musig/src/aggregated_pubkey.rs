@@ -1,45 +1,114 @@
 use crate::errors::MusigError;
-use crate::hasher::Hasher;
+use crate::hash_suite::HashSuite;
 use bellman::{Field, PrimeField};
 use franklin_crypto::eddsa::PublicKey;
 use franklin_crypto::jubjub::edwards::Point;
-use franklin_crypto::jubjub::{JubjubEngine, ToUniform};
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, JubjubParams, Unknown};
 
 pub struct AggregatedPublicKey;
 
-impl AggregatedPublicKey {
-    pub(crate) fn compute_from_pubkeys<E: JubjubEngine>(
+/// Result of key aggregation. It depends only on public keys, so it can be
+/// computed once and shared by any number of signing sessions of a group.
+#[derive(Clone)]
+pub struct KeyAggregationContext<E: JubjubEngine> {
+    pub pubkeys: Vec<PublicKey<E>>,
+    pub aggregated_pubkey: PublicKey<E>,
+    pub a_values: Vec<E::Fs>,
+    /// Sum of tweaks applied to the aggregated key, zero if it isn't tweaked
+    pub tweak: E::Fs,
+}
+
+impl<E: JubjubEngine> KeyAggregationContext<E> {
+    pub fn new<H: HashSuite<E>>(
         pubkeys: &[PublicKey<E>],
         jubjub_params: &<E as JubjubEngine>::Params,
-    ) -> Result<(PublicKey<E>, Vec<E::Fs>), MusigError> {
-        if pubkeys.is_empty() {
-            return Err(MusigError::InvalidPubkeyLength);
-        }
+        hash_suite: &H,
+    ) -> Result<Self, MusigError> {
+        let (aggregated_pubkey, a_values) =
+            AggregatedPublicKey::compute_from_pubkeys(pubkeys, jubjub_params, hash_suite)?;
+
+        Ok(Self {
+            pubkeys: pubkeys.to_vec(),
+            aggregated_pubkey,
+            a_values,
+            tweak: E::Fs::zero(),
+        })
+    }
 
-        for pubkey in pubkeys {
-            // check that pubkey is in correct subgroup
-            if pubkey.0.mul(E::Fs::char(), jubjub_params) != Point::zero() {
-                return Err(MusigError::InvalidPublicKey);
-            }
+    /// H_keyagg(L, X') of public keys and the aggregated key, computed
+    /// with the hash suite of the group, a tweaked context has a different
+    /// one. Signers bind it into session pre-commitments.
+    pub fn key_aggregation_hash<H: HashSuite<E>>(&self, hash_suite: &H) -> Vec<u8> {
+        hash_suite.hash_key_aggregation(&self.pubkeys, &self.aggregated_pubkey)
+    }
+
+    /// Tweaks aggregated public key: X' = X' + t * G. Signers created
+    /// from the tweaked context produce signatures for the tweaked key,
+    /// tweaks applied one after another add up.
+    pub fn apply_tweak(
+        &mut self,
+        tweak: &E::Fs,
+        jubjub_params: &<E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+    ) -> Result<(), MusigError> {
+        let tweak_point: Point<E, Unknown> = jubjub_params
+            .generator(generator)
+            .mul(*tweak, jubjub_params)
+            .into();
+        let tweaked_pubkey = self.aggregated_pubkey.0.add(&tweak_point, jubjub_params);
+
+        if tweaked_pubkey == Point::zero() {
+            return Err(MusigError::InvalidTweak);
         }
 
-        // TODO: sort pubkeys
-        // L sorted lexicographical order
+        self.aggregated_pubkey = PublicKey(tweaked_pubkey);
+        self.tweak.add_assign(tweak);
 
-        // aggregated pubkey and pubkey needs to be equal
-        if pubkeys.len() == 1 {
-            return Ok((pubkeys[0].clone(), vec![E::Fs::one()]));
-        }
+        Ok(())
+    }
 
-        let (aggregated_pubkey, a_values) =
-            Self::compute_aggregated_key_and_a_values(pubkeys, jubjub_params);
+    /// Tweaks aggregated public key with t = H_tweak(X', data), Taproot
+    /// style, so the tweaked key commits to data, e.g. to a root of
+    /// alternative spending conditions. Returns the applied tweak.
+    pub fn apply_taproot_tweak<H: HashSuite<E>>(
+        &mut self,
+        data: &[u8],
+        jubjub_params: &<E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: &H,
+    ) -> Result<E::Fs, MusigError> {
+        let tweak = hash_suite.hash_tweak(&self.aggregated_pubkey, data);
+        self.apply_tweak(&tweak, jubjub_params, generator)?;
 
-        Ok((aggregated_pubkey, a_values))
+        Ok(tweak)
+    }
+}
+
+impl AggregatedPublicKey {
+    pub(crate) fn compute_from_pubkeys<E: JubjubEngine, H: HashSuite<E>>(
+        pubkeys: &[PublicKey<E>],
+        jubjub_params: &<E as JubjubEngine>::Params,
+        hash_suite: &H,
+    ) -> Result<(PublicKey<E>, Vec<E::Fs>), MusigError> {
+        Self::compute_with(pubkeys, jubjub_params, |position| {
+            hash_suite.hash_aggregated(pubkeys, position)
+        })
+    }
+
+    /// Computes aggregated public key and a_i = H_agg(L, X_i) of each party,
+    /// resulting key depends on the hash suite.
+    pub fn compute_for_each_party<E: JubjubEngine, H: HashSuite<E>>(
+        pubkeys: &[PublicKey<E>],
+        jubjub_params: &<E as JubjubEngine>::Params,
+        hash_suite: &H,
+    ) -> Result<(PublicKey<E>, Vec<E::Fs>), MusigError> {
+        Self::compute_from_pubkeys(pubkeys, jubjub_params, hash_suite)
     }
 
-    pub fn compute_for_each_party<E: JubjubEngine>(
+    fn compute_with<E: JubjubEngine, F: Fn(usize) -> E::Fs>(
         pubkeys: &[PublicKey<E>],
         jubjub_params: &<E as JubjubEngine>::Params,
+        hash_aggregated: F,
     ) -> Result<(PublicKey<E>, Vec<E::Fs>), MusigError> {
         if pubkeys.is_empty() {
             return Err(MusigError::InvalidPubkeyLength);
@@ -61,14 +130,15 @@ impl AggregatedPublicKey {
         }
 
         let (aggregated_pubkey, a_values) =
-            Self::compute_aggregated_key_and_a_values(pubkeys, jubjub_params);
+            Self::compute_aggregated_key_and_a_values(pubkeys, jubjub_params, hash_aggregated);
 
         Ok((aggregated_pubkey, a_values))
     }
 
-    fn compute_aggregated_key_and_a_values<E: JubjubEngine>(
+    fn compute_aggregated_key_and_a_values<E: JubjubEngine, F: Fn(usize) -> E::Fs>(
         pubkeys: &[PublicKey<E>],
         jubjub_params: &<E as JubjubEngine>::Params,
+        hash_aggregated: F,
     ) -> (PublicKey<E>, Vec<E::Fs>) {
         // L = {X_1, X_2, .. X_n}
         let mut a_values = vec![];
@@ -76,7 +146,7 @@ impl AggregatedPublicKey {
 
         // X' = \sum{1<=i<=n}{ a_i * X_i}
         for (i, pubkey) in pubkeys.iter().enumerate() {
-            let a_i = E::Fs::to_uniform(&Hasher::hash_aggregated(&pubkeys, i));
+            let a_i = hash_aggregated(i);
             a_values.push(a_i);
 
             acc = acc.add(&pubkey.0.mul(a_i, jubjub_params), jubjub_params);
@@ -0,0 +1,427 @@
+use crate::ceremony::{Received, RoundMessages};
+use crate::errors::{CeremonyError, MusigError};
+use crate::hash_suite::{DefaultHashSuite, HashSuite};
+use crate::signer::MuSigSigner;
+use crate::wire::{
+    collect_commitments, collect_pre_commitments, collect_signature_shares, Payload, Round,
+    SessionId, WireMessage,
+};
+use franklin_crypto::eddsa::{PrivateKey, Signature};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{JubjubEngine, Unknown};
+use franklin_crypto::rescue::RescueEngine;
+use futures::stream::{Stream, StreamExt};
+use rand::Rng;
+use std::time::Duration;
+use tokio::time::{timeout_at, Instant};
+
+/// `MuSigSigner` for async services. Each party computes own wire message
+/// of a round and sends it to the other parties, then awaits messages of
+/// the other parties from a stream of incoming messages. A round completes
+/// as soon as valid messages of all parties have arrived, or fails with
+/// the positions of missing parties when the round timeout expires.
+/// Messages of other sessions and messages of earlier rounds are ignored,
+/// messages of later rounds are kept until their round. Sending own
+/// messages, and sending them again, is up to the caller.
+pub struct AsyncMuSigSigner<
+    'a,
+    E: JubjubEngine + RescueEngine,
+    H: HashSuite<E> = DefaultHashSuite<'a, E>,
+> {
+    signer: MuSigSigner<'a, E, H>,
+    session_id: SessionId,
+    round_timeout: Duration,
+    // own message of the current round
+    own_message: Option<WireMessage<E>>,
+    // messages of later rounds which arrived ahead of time
+    pending: Vec<WireMessage<E>>,
+}
+
+impl<'a, E: JubjubEngine + RescueEngine, H: HashSuite<E>> AsyncMuSigSigner<'a, E, H> {
+    /// Binds the signer to the session, all parties must use
+    /// the same session id and never reuse it.
+    pub fn new(
+        mut signer: MuSigSigner<'a, E, H>,
+        session_id: SessionId,
+        round_timeout: Duration,
+    ) -> Result<Self, MusigError> {
+        signer.set_session_id(session_id)?;
+
+        Ok(Self {
+            signer,
+            session_id,
+            round_timeout,
+            own_message: None,
+            pending: vec![],
+        })
+    }
+
+    pub fn signer(&self) -> &MuSigSigner<'a, E, H> {
+        &self.signer
+    }
+
+    /// Computes pre-commitment of this party, the returned message
+    /// must be sent to the other parties.
+    pub fn compute_precommitment(
+        &mut self,
+        rng: &mut impl Rng,
+    ) -> Result<WireMessage<E>, CeremonyError> {
+        let pre_commitment = self.signer.compute_precommitment(rng)?;
+
+        self.own_message(Payload::PreCommitment(pre_commitment))
+    }
+
+    /// Awaits pre-commitments of the other parties and returns commitment
+    /// of this party, the returned message must be sent to the other parties.
+    pub async fn receive_precommitments<S>(
+        &mut self,
+        incoming: &mut S,
+    ) -> Result<WireMessage<E>, CeremonyError>
+    where
+        S: Stream<Item = WireMessage<E>> + Unpin,
+    {
+        let messages = self.collect(Round::PreCommitment, incoming).await?;
+        let pre_commitments = collect_pre_commitments(&messages, &self.session_id, messages.len())?;
+        let commitment = self.signer.receive_precommitments(&pre_commitments)?;
+
+        self.own_message(Payload::Commitment(commitment))
+    }
+
+    /// Awaits commitments of the other parties and returns
+    /// the aggregated commitment.
+    pub async fn receive_commitments<S>(
+        &mut self,
+        incoming: &mut S,
+    ) -> Result<Point<E, Unknown>, CeremonyError>
+    where
+        S: Stream<Item = WireMessage<E>> + Unpin,
+    {
+        let messages = self.collect(Round::Commitment, incoming).await?;
+        let commitments = collect_commitments(&messages, &self.session_id, messages.len())?;
+
+        Ok(self.signer.receive_commitments(&commitments)?)
+    }
+
+    /// Computes signature share of this party for the message, the returned
+    /// message must be sent to the other parties.
+    pub fn sign(
+        &mut self,
+        private_key: &PrivateKey<E>,
+        message: &[u8],
+    ) -> Result<WireMessage<E>, CeremonyError> {
+        let signature_share = self.signer.sign(private_key, message)?;
+
+        self.own_message(Payload::SignatureShare(signature_share))
+    }
+
+    /// Awaits signature shares of the other parties, verifies them
+    /// and returns the aggregated signature.
+    pub async fn receive_signatures<S>(
+        &mut self,
+        incoming: &mut S,
+    ) -> Result<Signature<E>, CeremonyError>
+    where
+        S: Stream<Item = WireMessage<E>> + Unpin,
+    {
+        let messages = self.collect(Round::SignatureShare, incoming).await?;
+        let signature_shares =
+            collect_signature_shares(&messages, &self.session_id, messages.len())?;
+
+        Ok(self.signer.receive_signatures(&signature_shares)?)
+    }
+
+    /// Abort message to send to the other parties if this party gives up
+    /// the session, they fail instead of waiting for the timeout.
+    pub fn abort(&self) -> Result<WireMessage<E>, CeremonyError> {
+        Ok(WireMessage::new(
+            self.session_id,
+            self.signer.position(),
+            Payload::Abort,
+        )?)
+    }
+
+    fn own_message(&mut self, payload: Payload<E>) -> Result<WireMessage<E>, CeremonyError> {
+        let message = WireMessage::new(self.session_id, self.signer.position(), payload)?;
+        self.own_message = Some(message.clone());
+
+        Ok(message)
+    }
+
+    // awaits messages of all parties for the round
+    async fn collect<S>(
+        &mut self,
+        round: Round,
+        incoming: &mut S,
+    ) -> Result<Vec<WireMessage<E>>, CeremonyError>
+    where
+        S: Stream<Item = WireMessage<E>> + Unpin,
+    {
+        let own_message = match &self.own_message {
+            Some(message) if message.round() == round => message.clone(),
+            // previous step of this party hasn't been done
+            _ => {
+                return Err(match round {
+                    Round::PreCommitment => MusigError::NonceCommitmentNotGenerated,
+                    Round::Commitment => MusigError::NoncePreCommitmentsNotReceived,
+                    _ => MusigError::ChallengeNotGenerated,
+                }
+                .into())
+            }
+        };
+
+        let mut messages =
+            RoundMessages::new(own_message, self.session_id, self.signer.pubkeys().len());
+
+        for message in std::mem::take(&mut self.pending) {
+            self.accept(&mut messages, message)?;
+        }
+
+        let deadline = Instant::now() + self.round_timeout;
+        while !messages.is_complete() {
+            match timeout_at(deadline, incoming.next()).await {
+                Ok(Some(message)) => self.accept(&mut messages, message)?,
+                Ok(None) => {
+                    return Err(CeremonyError::Disconnected {
+                        round,
+                        missing: messages.missing(),
+                    })
+                }
+                Err(_) => {
+                    return Err(CeremonyError::Timeout {
+                        round,
+                        missing: messages.missing(),
+                    })
+                }
+            }
+        }
+
+        Ok(messages.into_messages())
+    }
+
+    fn accept(
+        &mut self,
+        messages: &mut RoundMessages<E>,
+        message: WireMessage<E>,
+    ) -> Result<(), CeremonyError> {
+        match messages.receive(message)? {
+            Received::Accepted | Received::Earlier(_) => {}
+            Received::Later(message) => self.pending.push(message),
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AsyncMuSigSigner;
+    use crate::errors::CeremonyError;
+    use crate::hash_suite::DefaultHashSuite;
+    use crate::tests::{musig_test_bn256_setup, random_message_hash};
+    use crate::verifier::MuSigVerifier;
+    use crate::wire::{Payload, Round, WireMessage};
+    use bellman::pairing::bn256::Bn256;
+    use franklin_crypto::alt_babyjubjub::AltJubjubBn256;
+    use franklin_crypto::eddsa::{PrivateKey, Signature};
+    use franklin_crypto::jubjub::FixedGenerators;
+    use franklin_crypto::rescue::bn256::Bn256RescueParams;
+    use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+    use futures::future::join_all;
+    use rand::{SeedableRng, XorShiftRng};
+    use std::time::Duration;
+
+    type Peers = Vec<UnboundedSender<WireMessage<Bn256>>>;
+
+    fn broadcast(peers: &Peers, position: usize, message: &WireMessage<Bn256>) {
+        for (peer, sender) in peers.iter().enumerate() {
+            if peer != position {
+                // the party may have failed already
+                let _ = sender.unbounded_send(message.clone());
+            }
+        }
+    }
+
+    async fn run_party(
+        mut signer: AsyncMuSigSigner<'_, Bn256>,
+        private_key: PrivateKey<Bn256>,
+        mut inbox: UnboundedReceiver<WireMessage<Bn256>>,
+        peers: Peers,
+        message: Vec<u8>,
+    ) -> Result<Signature<Bn256>, CeremonyError> {
+        let position = signer.signer().position();
+        let rng =
+            &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, position as u32]);
+
+        let pre_commitment = signer.compute_precommitment(rng)?;
+        broadcast(&peers, position, &pre_commitment);
+
+        let commitment = signer.receive_precommitments(&mut inbox).await?;
+        broadcast(&peers, position, &commitment);
+
+        signer.receive_commitments(&mut inbox).await?;
+        let signature_share = signer.sign(&private_key, &message)?;
+        broadcast(&peers, position, &signature_share);
+
+        signer.receive_signatures(&mut inbox).await
+    }
+
+    #[tokio::test]
+    async fn test_async_signer() {
+        let jubjub_params = AltJubjubBn256::new();
+        let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+        let generator = FixedGenerators::SpendingKeyGenerator;
+        let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+        let message = random_message_hash(rng);
+
+        let number_of_parties = 3;
+        let session_id = [4u8; 32];
+        let timeout = Duration::from_secs(5);
+
+        let (privkeys, pubkeys, signers) =
+            musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+        let (senders, inboxes): (Vec<_>, Vec<_>) =
+            (0..number_of_parties).map(|_| unbounded()).unzip();
+
+        let parties = signers
+            .into_iter()
+            .zip(privkeys.into_iter())
+            .zip(inboxes.into_iter())
+            .map(|((signer, private_key), inbox)| {
+                let signer = AsyncMuSigSigner::new(signer, session_id, timeout).unwrap();
+                run_party(signer, private_key, inbox, senders.clone(), message.clone())
+            });
+        let signatures = join_all(parties).await;
+
+        for signature in signatures.iter() {
+            let signature = signature.as_ref().unwrap();
+            assert_eq!(signature.r, signatures[0].as_ref().unwrap().r);
+            assert_eq!(signature.s, signatures[0].as_ref().unwrap().s);
+
+            assert!(MuSigVerifier::verify_in_session(
+                &message,
+                &session_id,
+                &pubkeys,
+                signature,
+                &jubjub_params,
+                generator,
+                &hash_suite,
+            )
+            .unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_signer_failures() {
+        let jubjub_params = AltJubjubBn256::new();
+        let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+        let hash_suite = DefaultHashSuite::new(&rescue_params);
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let number_of_parties = 3;
+        let session_id = [4u8; 32];
+
+        // party 2 never sends anything
+        let (_, _, signers) =
+            musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+        let mut parties: Vec<_> = signers
+            .into_iter()
+            .map(|signer| {
+                AsyncMuSigSigner::new(signer, session_id, Duration::from_millis(200)).unwrap()
+            })
+            .collect();
+        let (sender, mut inbox) = unbounded();
+        sender
+            .unbounded_send(parties[1].compute_precommitment(rng).unwrap())
+            .unwrap();
+        parties[0].compute_precommitment(rng).unwrap();
+
+        match parties[0].receive_precommitments(&mut inbox).await {
+            Err(CeremonyError::Timeout { round, missing }) => {
+                assert_eq!(round, Round::PreCommitment);
+                assert_eq!(missing, vec![2]);
+            }
+            _ => panic!("round completes without messages of all parties"),
+        }
+
+        // incoming messages end before all parties have sent theirs
+        let (_, _, signers) =
+            musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+        let mut parties: Vec<_> = signers
+            .into_iter()
+            .map(|signer| {
+                AsyncMuSigSigner::new(signer, session_id, Duration::from_secs(5)).unwrap()
+            })
+            .collect();
+        let (sender, mut inbox) = unbounded();
+        sender
+            .unbounded_send(parties[2].compute_precommitment(rng).unwrap())
+            .unwrap();
+        drop(sender);
+        parties[0].compute_precommitment(rng).unwrap();
+
+        match parties[0].receive_precommitments(&mut inbox).await {
+            Err(CeremonyError::Disconnected { round, missing }) => {
+                assert_eq!(round, Round::PreCommitment);
+                assert_eq!(missing, vec![1]);
+            }
+            _ => panic!("round completes after incoming messages ended"),
+        }
+
+        // conflicting pre-commitments of a party, messages of another
+        // session and retries are ignored
+        let (_, _, signers) =
+            musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+        let mut parties: Vec<_> = signers
+            .into_iter()
+            .map(|signer| {
+                AsyncMuSigSigner::new(signer, session_id, Duration::from_secs(5)).unwrap()
+            })
+            .collect();
+        let (sender, mut inbox) = unbounded();
+        let pre_commitment = parties[1].compute_precommitment(rng).unwrap();
+        let other_session =
+            WireMessage::new([5u8; 32], 1, Payload::PreCommitment(vec![1u8; 32])).unwrap();
+        let conflicting =
+            WireMessage::new(session_id, 1, Payload::PreCommitment(vec![1u8; 32])).unwrap();
+        for message in [
+            other_session,
+            pre_commitment.clone(),
+            pre_commitment,
+            conflicting,
+        ] {
+            sender.unbounded_send(message).unwrap();
+        }
+        parties[0].compute_precommitment(rng).unwrap();
+
+        match parties[0].receive_precommitments(&mut inbox).await {
+            Err(CeremonyError::Equivocation(1)) => {}
+            _ => panic!("conflicting messages are accepted"),
+        }
+
+        // abort of a party
+        let (_, _, signers) =
+            musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+        let mut parties: Vec<_> = signers
+            .into_iter()
+            .map(|signer| {
+                AsyncMuSigSigner::new(signer, session_id, Duration::from_secs(5)).unwrap()
+            })
+            .collect();
+        let (sender, mut inbox) = unbounded();
+        sender.unbounded_send(parties[2].abort().unwrap()).unwrap();
+        parties[0].compute_precommitment(rng).unwrap();
+
+        match parties[0].receive_precommitments(&mut inbox).await {
+            Err(CeremonyError::Aborted(2)) => {}
+            _ => panic!("round continues after abort"),
+        }
+
+        // rounds must be run in order
+        match parties[1].receive_commitments(&mut inbox).await {
+            Err(CeremonyError::Musig(_)) => {}
+            _ => panic!("commitments are received before pre-commitments"),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use crate::aggregated_pubkey::KeyAggregationContext;
+use crate::errors::MusigError;
+use crate::hash_suite::{DefaultHashSuite, HashSuite};
+use crate::signer::MuSigSigner;
+use franklin_crypto::eddsa::{PrivateKey, PublicKey, Signature};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, Unknown};
+use franklin_crypto::rescue::RescueEngine;
+use rand::Rng;
+
+/// Signs a batch of messages in a single protocol run. Each message gets
+/// its own nonce and signing session, but all of them go through each
+/// round together, so the number of round trips doesn't depend on the
+/// size of the batch.
+///
+/// Data received from other parties is indexed by party first and by
+/// message second, e.g. `pre_commitments[i][k]` is the pre-commitment of
+/// the i-th party for the k-th message.
+pub struct MuSigBatchSigner<
+    'a,
+    E: JubjubEngine + RescueEngine,
+    H: HashSuite<E> + Clone = DefaultHashSuite<'a, E>,
+> {
+    signers: Vec<MuSigSigner<'a, E, H>>,
+}
+
+impl<'a, E: JubjubEngine + RescueEngine, H: HashSuite<E> + Clone> MuSigBatchSigner<'a, E, H> {
+    /// Initializes new party for a batch of given size. Key aggregation
+    /// is computed once for the whole batch.
+    pub fn new(
+        pubkeys: &[PublicKey<E>],
+        position: usize,
+        batch_size: usize,
+        params: &'a <E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: H,
+    ) -> Result<Self, MusigError> {
+        let context = KeyAggregationContext::new(pubkeys, params, &hash_suite)?;
+
+        Self::new_with_context(
+            &context, position, batch_size, params, generator, hash_suite,
+        )
+    }
+
+    /// Initializes new party for a batch of given size from already
+    /// computed key aggregation.
+    pub fn new_with_context(
+        context: &KeyAggregationContext<E>,
+        position: usize,
+        batch_size: usize,
+        params: &'a <E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: H,
+    ) -> Result<Self, MusigError> {
+        if batch_size == 0 {
+            return Err(MusigError::InvalidBatchSize);
+        }
+
+        let mut signers = vec![];
+        for _ in 0..batch_size {
+            signers.push(MuSigSigner::new_with_context(
+                context,
+                position,
+                params,
+                generator,
+                hash_suite.clone(),
+            )?);
+        }
+
+        Ok(Self { signers })
+    }
+
+    /// Number of messages signed in this batch
+    pub fn batch_size(&self) -> usize {
+        self.signers.len()
+    }
+
+    /// Position of this party in the list of public keys
+    pub fn position(&self) -> usize {
+        self.signers[0].position()
+    }
+
+    /// Computes pre-commitment of an independent nonce for each message.
+    /// rng must be a cryptographically secure one.
+    pub fn compute_precommitments(
+        &mut self,
+        rng: &mut impl Rng,
+    ) -> Result<Vec<Vec<u8>>, MusigError> {
+        self.signers
+            .iter_mut()
+            .map(|signer| signer.compute_precommitment(rng))
+            .collect()
+    }
+
+    /// Receives pre-commitments of all parties and returns revealed
+    /// commitments of this party, one for each message.
+    pub fn receive_precommitments(
+        &mut self,
+        pre_commitments: &[Vec<Vec<u8>>],
+    ) -> Result<Vec<Point<E, Unknown>>, MusigError> {
+        let pre_commitments = split_by_message(pre_commitments, self.signers.len())?;
+
+        self.signers
+            .iter_mut()
+            .zip(pre_commitments.iter())
+            .map(|(signer, pre_commitments)| signer.receive_precommitments(pre_commitments))
+            .collect()
+    }
+
+    /// Receives revealed commitments of all parties, verifies them against
+    /// pre-commitments and returns aggregated commitment of each message.
+    pub fn receive_commitments(
+        &mut self,
+        commitments: &[Vec<Point<E, Unknown>>],
+    ) -> Result<Vec<Point<E, Unknown>>, MusigError> {
+        let commitments = split_by_message(commitments, self.signers.len())?;
+
+        self.signers
+            .iter_mut()
+            .zip(commitments.iter())
+            .map(|(signer, commitments)| signer.receive_commitments(commitments))
+            .collect()
+    }
+
+    /// Computes signature share for each message, messages must be
+    /// in the same order for all parties.
+    pub fn sign<M: AsRef<[u8]>>(
+        &mut self,
+        private_key: &PrivateKey<E>,
+        messages: &[M],
+    ) -> Result<Vec<E::Fs>, MusigError> {
+        if messages.len() != self.signers.len() {
+            return Err(MusigError::InvalidBatchSize);
+        }
+
+        self.signers
+            .iter_mut()
+            .zip(messages.iter())
+            .map(|(signer, message)| signer.sign(private_key, message.as_ref()))
+            .collect()
+    }
+
+    /// Receives signature shares of all parties and verifies them. If all
+    /// shares are valid then returns an aggregated signature for each message.
+    pub fn receive_signatures(
+        &self,
+        signature_shares: &[Vec<E::Fs>],
+    ) -> Result<Vec<Signature<E>>, MusigError> {
+        let signature_shares = split_by_message(signature_shares, self.signers.len())?;
+
+        self.signers
+            .iter()
+            .zip(signature_shares.iter())
+            .map(|(signer, signature_shares)| signer.receive_signatures(signature_shares))
+            .collect()
+    }
+}
+
+// turns items of each party for all messages into
+// items of all parties for each message
+pub(crate) fn split_by_message<T: Clone>(
+    items: &[Vec<T>],
+    batch_size: usize,
+) -> Result<Vec<Vec<T>>, MusigError> {
+    let mut by_message = vec![Vec::with_capacity(items.len()); batch_size];
+    for party_items in items {
+        if party_items.len() != batch_size {
+            return Err(MusigError::InvalidBatchSize);
+        }
+        for (message_items, item) in by_message.iter_mut().zip(party_items.iter()) {
+            message_items.push(item.clone());
+        }
+    }
+
+    Ok(by_message)
+}
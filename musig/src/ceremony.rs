@@ -0,0 +1,334 @@
+use crate::errors::CeremonyError;
+use crate::hash_suite::{DefaultHashSuite, HashSuite};
+use crate::signer::MuSigSigner;
+use crate::transport::Transport;
+use crate::wire::{
+    collect_commitments, collect_pre_commitments, collect_signature_shares, Payload, Round,
+    SessionId, WireMessage,
+};
+use franklin_crypto::eddsa::{PrivateKey, Signature};
+use franklin_crypto::jubjub::JubjubEngine;
+use franklin_crypto::rescue::RescueEngine;
+use rand::Rng;
+use std::cmp::{min, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Timeouts and retries of a ceremony
+#[derive(Clone, Debug)]
+pub struct CeremonyConfig {
+    /// Time to wait for messages of all parties in a single round
+    pub round_timeout: Duration,
+    /// Own message of the round is sent again after this much time
+    /// without incoming messages, in case other parties have missed it
+    pub retry_interval: Duration,
+    /// Number of attempts to send a message before the ceremony fails
+    pub send_attempts: usize,
+}
+
+impl Default for CeremonyConfig {
+    fn default() -> Self {
+        Self {
+            round_timeout: Duration::from_secs(30),
+            retry_interval: Duration::from_secs(1),
+            send_attempts: 3,
+        }
+    }
+}
+
+/// Runs all rounds of `MuSigSigner` for a party, exchanging wire messages
+/// with the other parties over a transport. Messages of other sessions
+/// are ignored, messages of later rounds are kept until their round.
+/// If the ceremony fails the party sends abort, so the other parties
+/// stop without waiting for the timeout.
+pub struct Ceremony<
+    'a,
+    E: JubjubEngine + RescueEngine,
+    T: Transport,
+    H: HashSuite<E> = DefaultHashSuite<'a, E>,
+> {
+    signer: MuSigSigner<'a, E, H>,
+    transport: T,
+    session_id: SessionId,
+    jubjub_params: &'a <E as JubjubEngine>::Params,
+    config: CeremonyConfig,
+    // own encoded message of each finished round, sent again
+    // to parties which are still in that round
+    sent: Vec<(Round, Vec<u8>)>,
+    // messages of later rounds which arrived ahead of time
+    pending: Vec<WireMessage<E>>,
+}
+
+impl<'a, E: JubjubEngine + RescueEngine, T: Transport, H: HashSuite<E>> Ceremony<'a, E, T, H> {
+    /// Binds the signer to the session, all parties must use
+    /// the same session id and never reuse it.
+    pub fn new(
+        mut signer: MuSigSigner<'a, E, H>,
+        transport: T,
+        session_id: SessionId,
+        jubjub_params: &'a <E as JubjubEngine>::Params,
+        config: CeremonyConfig,
+    ) -> Result<Self, CeremonyError> {
+        signer.set_session_id(session_id)?;
+
+        Ok(Self {
+            signer,
+            transport,
+            session_id,
+            jubjub_params,
+            config,
+            sent: vec![],
+            pending: vec![],
+        })
+    }
+
+    /// Runs the ceremony and returns an aggregated signature. The run is
+    /// bound to the session, so the signature is over m' = H_msg(sid, m),
+    /// not over the message itself, and must be checked with
+    /// `MuSigVerifier::verify_in_session`.
+    /// rng must be a cryptographically secure one.
+    pub fn run(
+        &mut self,
+        rng: &mut impl Rng,
+        private_key: &PrivateKey<E>,
+        message: &[u8],
+    ) -> Result<Signature<E>, CeremonyError> {
+        let result = self.run_rounds(rng, private_key, message);
+
+        match result {
+            Err(CeremonyError::Aborted(_)) | Ok(_) => {}
+            Err(_) => self.abort(),
+        }
+
+        result
+    }
+
+    fn run_rounds(
+        &mut self,
+        rng: &mut impl Rng,
+        private_key: &PrivateKey<E>,
+        message: &[u8],
+    ) -> Result<Signature<E>, CeremonyError> {
+        let number_of_parties = self.signer.pubkeys().len();
+
+        let pre_commitment = self.signer.compute_precommitment(rng)?;
+        let messages = self.exchange(Payload::PreCommitment(pre_commitment))?;
+        let pre_commitments =
+            collect_pre_commitments(&messages, &self.session_id, number_of_parties)?;
+
+        let commitment = self.signer.receive_precommitments(&pre_commitments)?;
+        let messages = self.exchange(Payload::Commitment(commitment))?;
+        let commitments = collect_commitments(&messages, &self.session_id, number_of_parties)?;
+
+        self.signer.receive_commitments(&commitments)?;
+        let signature_share = self.signer.sign(private_key, message)?;
+        let messages = self.exchange(Payload::SignatureShare(signature_share))?;
+        let signature_shares =
+            collect_signature_shares(&messages, &self.session_id, number_of_parties)?;
+
+        Ok(self.signer.receive_signatures(&signature_shares)?)
+    }
+
+    // sends own message of the round and waits for messages of all parties
+    fn exchange(&mut self, payload: Payload<E>) -> Result<Vec<WireMessage<E>>, CeremonyError> {
+        let position = self.signer.position();
+        let round = payload.round();
+
+        let own_message = WireMessage::new(self.session_id, position, payload)?;
+        let encoded = own_message.to_bytes();
+        self.send(&encoded)?;
+        self.sent.push((round, encoded.clone()));
+
+        let mut messages =
+            RoundMessages::new(own_message, self.session_id, self.signer.pubkeys().len());
+
+        for message in std::mem::take(&mut self.pending) {
+            self.accept(&mut messages, message)?;
+        }
+
+        let deadline = Instant::now() + self.config.round_timeout;
+        while !messages.is_complete() {
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(CeremonyError::Timeout {
+                    round,
+                    missing: messages.missing(),
+                });
+            }
+
+            let timeout = min(deadline - now, self.config.retry_interval);
+            match self.transport.receive(timeout)? {
+                Some((from, bytes)) => {
+                    // malformed messages can't be attributed to a party,
+                    // so they are dropped
+                    if let Ok(message) = WireMessage::from_bytes(&bytes, self.jubjub_params) {
+                        messages.check_sender(from, &message)?;
+                        self.accept(&mut messages, message)?;
+                    }
+                }
+                // best effort, the message has already been sent once
+                None => {
+                    let _ = self.transport.broadcast(&encoded);
+                }
+            }
+        }
+
+        Ok(messages.into_messages())
+    }
+
+    fn accept(
+        &mut self,
+        messages: &mut RoundMessages<E>,
+        message: WireMessage<E>,
+    ) -> Result<(), CeremonyError> {
+        match messages.receive(message)? {
+            Received::Accepted => {}
+            // sender is still in an earlier round, so it has
+            // missed our message of that round
+            Received::Earlier(round) => {
+                if let Some((_, encoded)) = self
+                    .sent
+                    .iter()
+                    .find(|(sent_round, _)| *sent_round == round)
+                {
+                    let _ = self.transport.broadcast(encoded);
+                }
+            }
+            Received::Later(message) => self.pending.push(message),
+        }
+
+        Ok(())
+    }
+
+    fn send(&mut self, message: &[u8]) -> Result<(), CeremonyError> {
+        let mut attempt = 1;
+        loop {
+            match self.transport.broadcast(message) {
+                Ok(()) => return Ok(()),
+                Err(err) if attempt >= self.config.send_attempts => return Err(err.into()),
+                Err(_) => {
+                    attempt += 1;
+                    thread::sleep(self.config.retry_interval);
+                }
+            }
+        }
+    }
+
+    // best effort, the ceremony has already failed
+    fn abort(&mut self) {
+        if let Ok(message) =
+            WireMessage::new(self.session_id, self.signer.position(), Payload::Abort)
+        {
+            let _ = self.transport.broadcast(&message.to_bytes());
+        }
+    }
+}
+
+/// What happened to a received message
+pub(crate) enum Received<E: JubjubEngine> {
+    /// Message of the current round is accepted, or ignored as a retry
+    /// or as a message of another session
+    Accepted,
+    /// Message of an earlier round
+    Earlier(Round),
+    /// Message of a later round, it must be kept until that round
+    Later(WireMessage<E>),
+}
+
+/// Messages of all parties for a round of the session
+pub(crate) struct RoundMessages<E: JubjubEngine> {
+    session_id: SessionId,
+    round: Round,
+    position: usize,
+    received: Vec<Option<WireMessage<E>>>,
+}
+
+impl<E: JubjubEngine> RoundMessages<E> {
+    /// Starts the round with own message of the party
+    pub(crate) fn new(
+        own_message: WireMessage<E>,
+        session_id: SessionId,
+        number_of_parties: usize,
+    ) -> Self {
+        let round = own_message.round();
+        let position = own_message.sender();
+        let mut received = vec![None; number_of_parties];
+        received[position] = Some(own_message);
+
+        Self {
+            session_id,
+            round,
+            position,
+            received,
+        }
+    }
+
+    /// Checks that a message received from the party at position `from`
+    /// is sent by that party, so a party can't send messages in the name
+    /// of another one.
+    pub(crate) fn check_sender(
+        &self,
+        from: usize,
+        message: &WireMessage<E>,
+    ) -> Result<(), CeremonyError> {
+        if message.session_id() == &self.session_id && message.sender() != from {
+            return Err(CeremonyError::ForgedSender {
+                party: from,
+                sender: message.sender(),
+            });
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn receive(
+        &mut self,
+        message: WireMessage<E>,
+    ) -> Result<Received<E>, CeremonyError> {
+        let sender = message.sender();
+
+        // the transport may be shared with other ceremonies
+        if message.session_id() != &self.session_id
+            || sender >= self.received.len()
+            || sender == self.position
+        {
+            return Ok(Received::Accepted);
+        }
+
+        if message.round() == Round::Abort {
+            return Err(CeremonyError::Aborted(sender));
+        }
+
+        match (message.round() as u8).cmp(&(self.round as u8)) {
+            Ordering::Less => Ok(Received::Earlier(message.round())),
+            Ordering::Greater => Ok(Received::Later(message)),
+            Ordering::Equal => match &self.received[sender] {
+                None => {
+                    self.received[sender] = Some(message);
+                    Ok(Received::Accepted)
+                }
+                // retry of the same message
+                Some(existing) if *existing == message => Ok(Received::Accepted),
+                Some(_) => Err(CeremonyError::Equivocation(sender)),
+            },
+        }
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        self.received.iter().all(Option::is_some)
+    }
+
+    /// Positions of parties whose messages haven't arrived yet
+    pub(crate) fn missing(&self) -> Vec<usize> {
+        self.received
+            .iter()
+            .enumerate()
+            .filter(|(_, message)| message.is_none())
+            .map(|(position, _)| position)
+            .collect()
+    }
+
+    pub(crate) fn into_messages(self) -> Vec<WireMessage<E>> {
+        self.received.into_iter().map(Option::unwrap).collect()
+    }
+}
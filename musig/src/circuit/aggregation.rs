@@ -0,0 +1,305 @@
+use crate::circuit::signature::{
+    enforce_points_equal, point_x_into_bits, rescue_hash_to_scalar_bits, tag_into_bits,
+};
+use bellman::{ConstraintSystem, SynthesisError};
+use franklin_crypto::circuit::ecc::EdwardsPoint;
+use franklin_crypto::jubjub::JubjubEngine;
+use franklin_crypto::rescue::RescueEngine;
+
+/// Computes X' = \sum{1<=i<=n}{ a_i * X_i} where a_i = H_agg(L, X_i) is
+/// Rescue-based, see `RescueHashSuite`.
+pub fn compute_aggregated_pubkey<E, CS>(
+    mut cs: CS,
+    pubkeys: &[EdwardsPoint<E>],
+    tag: &[u8],
+    jubjub_params: &<E as JubjubEngine>::Params,
+    rescue_params: &<E as RescueEngine>::Params,
+) -> Result<EdwardsPoint<E>, SynthesisError>
+where
+    E: JubjubEngine + RescueEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert!(!pubkeys.is_empty());
+
+    for (i, pubkey) in pubkeys.iter().enumerate() {
+        pubkey.assert_not_small_order(
+            cs.namespace(|| format!("pubkey {} is not small order", i)),
+            jubjub_params,
+        )?;
+    }
+
+    // aggregated pubkey and pubkey needs to be equal
+    if pubkeys.len() == 1 {
+        return Ok(pubkeys[0].clone());
+    }
+
+    // L = {X_1, X_2, .. X_n}
+    let mut pubkeys_bits = vec![];
+    for (i, pubkey) in pubkeys.iter().enumerate() {
+        pubkeys_bits.push(point_x_into_bits(
+            cs.namespace(|| format!("pubkey {} bits", i)),
+            pubkey,
+        )?);
+    }
+    let list_bits: Vec<_> = pubkeys_bits.iter().flatten().cloned().collect();
+
+    let mut acc: Option<EdwardsPoint<E>> = None;
+    for (i, pubkey) in pubkeys.iter().enumerate() {
+        let mut cs = cs.namespace(|| format!("a_{} * X_{}", i, i));
+
+        // a_i = H_agg(L, X_i)
+        let mut preimage = tag_into_bits(tag);
+        preimage.extend_from_slice(&list_bits);
+        preimage.extend_from_slice(&pubkeys_bits[i]);
+        let a_bits = rescue_hash_to_scalar_bits(cs.namespace(|| "a_i"), &preimage, rescue_params)?;
+
+        let term = pubkey.mul(cs.namespace(|| "multiplication"), &a_bits, jubjub_params)?;
+
+        acc = Some(match acc {
+            Some(acc) => acc.add(cs.namespace(|| "accumulate"), &term, jubjub_params)?,
+            None => term,
+        });
+    }
+
+    Ok(acc.expect("at least two pubkeys"))
+}
+
+/// Enforces that the aggregated public key is the MuSig aggregation
+/// of the given public keys.
+pub fn enforce_aggregated_pubkey<E, CS>(
+    mut cs: CS,
+    pubkeys: &[EdwardsPoint<E>],
+    aggregated_pubkey: &EdwardsPoint<E>,
+    tag: &[u8],
+    jubjub_params: &<E as JubjubEngine>::Params,
+    rescue_params: &<E as RescueEngine>::Params,
+) -> Result<(), SynthesisError>
+where
+    E: JubjubEngine + RescueEngine,
+    CS: ConstraintSystem<E>,
+{
+    let computed = compute_aggregated_pubkey(
+        cs.namespace(|| "compute aggregated pubkey"),
+        pubkeys,
+        tag,
+        jubjub_params,
+        rescue_params,
+    )?;
+
+    enforce_points_equal(
+        cs.namespace(|| "aggregated pubkeys are equal"),
+        &computed,
+        aggregated_pubkey,
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::enforce_aggregated_pubkey;
+    use crate::aggregated_pubkey::{AggregatedPublicKey, KeyAggregationContext};
+    use crate::circuit::signature::{
+        message_into_bits, verify_musig_signature, AllocatedSignature,
+    };
+    use crate::hash_suite::{DefaultHashSuite, RescueHashSuite};
+    use crate::signer::MuSigSigner;
+    use crate::tests::random_message_hash;
+    use crate::verifier::MuSigVerifier;
+    use bellman::pairing::bn256::Bn256;
+    use bellman::ConstraintSystem;
+    use franklin_crypto::alt_babyjubjub::{fs::Fs, AltJubjubBn256};
+    use franklin_crypto::circuit::ecc::EdwardsPoint;
+    use franklin_crypto::circuit::test::TestConstraintSystem;
+    use franklin_crypto::eddsa::{PrivateKey, PublicKey, Signature};
+    use franklin_crypto::jubjub::edwards::Point;
+    use franklin_crypto::jubjub::{FixedGenerators, Unknown};
+    use franklin_crypto::rescue::bn256::Bn256RescueParams;
+    use rand::{Rand, Rng, SeedableRng, XorShiftRng};
+
+    fn is_satisfied(
+        pubkeys: &[PublicKey<Bn256>],
+        aggregated_pubkey: &Point<Bn256, Unknown>,
+        signature: Option<(&Signature<Bn256>, &[u8])>,
+        tag: &[u8],
+        jubjub_params: &AltJubjubBn256,
+        rescue_params: &Bn256RescueParams,
+    ) -> bool {
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let mut allocated_pubkeys = vec![];
+        for (i, pubkey) in pubkeys.iter().enumerate() {
+            let allocated_pubkey = EdwardsPoint::witness(
+                cs.namespace(|| format!("pubkey {}", i)),
+                Some(pubkey.0),
+                jubjub_params,
+            )
+            .unwrap();
+            allocated_pubkeys.push(allocated_pubkey);
+        }
+        let allocated_aggregated_pubkey = EdwardsPoint::witness(
+            cs.namespace(|| "aggregated pubkey"),
+            Some(*aggregated_pubkey),
+            jubjub_params,
+        )
+        .unwrap();
+
+        enforce_aggregated_pubkey(
+            cs.namespace(|| "key aggregation"),
+            &allocated_pubkeys,
+            &allocated_aggregated_pubkey,
+            tag,
+            jubjub_params,
+            rescue_params,
+        )
+        .unwrap();
+
+        if let Some((signature, message)) = signature {
+            let signature = AllocatedSignature::alloc(
+                cs.namespace(|| "signature"),
+                Some(signature),
+                jubjub_params,
+            )
+            .unwrap();
+            let message_bits =
+                message_into_bits(cs.namespace(|| "message"), Some(message)).unwrap();
+
+            verify_musig_signature(
+                cs.namespace(|| "verify signature"),
+                &allocated_aggregated_pubkey,
+                &signature,
+                &message_bits,
+                tag,
+                FixedGenerators::SpendingKeyGenerator,
+                jubjub_params,
+                rescue_params,
+            )
+            .unwrap();
+        }
+
+        cs.is_satisfied()
+    }
+
+    #[test]
+    fn test_key_aggregation_gadget() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let jubjub_params = AltJubjubBn256::new();
+        let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+        let generator = FixedGenerators::SpendingKeyGenerator;
+
+        let number_of_parties = 3;
+        let message = random_message_hash(rng);
+
+        let privkeys: Vec<_> = (0..number_of_parties)
+            .map(|_| PrivateKey::<Bn256>(Fs::rand(rng)))
+            .collect();
+        let pubkeys: Vec<_> = privkeys
+            .iter()
+            .map(|privkey| PublicKey::from_private(privkey, generator, &jubjub_params))
+            .collect();
+
+        let tag = b"musig circuit test";
+        let hash_suite = RescueHashSuite::new_with_tag(&rescue_params, tag);
+        let context = KeyAggregationContext::new(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+
+        // full run with rescue-based coefficients
+        let mut signers: Vec<_> = (0..number_of_parties)
+            .map(|position| {
+                MuSigSigner::new_with_context(
+                    &context,
+                    position,
+                    &jubjub_params,
+                    generator,
+                    hash_suite,
+                )
+                .unwrap()
+            })
+            .collect();
+        let pre_commitments: Vec<_> = signers
+            .iter_mut()
+            .map(|signer| signer.compute_precommitment(rng).unwrap())
+            .collect();
+        let commitments: Vec<_> = signers
+            .iter_mut()
+            .map(|signer| signer.receive_precommitments(&pre_commitments).unwrap())
+            .collect();
+        for signer in signers.iter_mut() {
+            signer.receive_commitments(&commitments).unwrap();
+        }
+        let signature_shares: Vec<_> = signers
+            .iter_mut()
+            .zip(privkeys.iter())
+            .map(|(signer, privkey)| signer.sign(privkey, &message).unwrap())
+            .collect();
+        let signature = signers[0].receive_signatures(&signature_shares).unwrap();
+
+        assert!(MuSigVerifier::verify_with_context(
+            &message,
+            &context,
+            &signature,
+            &jubjub_params,
+            generator,
+            &hash_suite
+        ));
+
+        assert!(is_satisfied(
+            &pubkeys,
+            &context.aggregated_pubkey.0,
+            Some((&signature, &message)),
+            tag,
+            &jubjub_params,
+            &rescue_params
+        ));
+
+        // tag is bound into aggregation
+        assert!(!is_satisfied(
+            &pubkeys,
+            &context.aggregated_pubkey.0,
+            None,
+            b"another application",
+            &jubjub_params,
+            &rescue_params
+        ));
+
+        // blake2b-based aggregation doesn't match
+        let (blake2b_aggregated_pubkey, _) = AggregatedPublicKey::compute_for_each_party(
+            &pubkeys,
+            &jubjub_params,
+            &DefaultHashSuite::new(&rescue_params),
+        )
+        .unwrap();
+        assert!(!is_satisfied(
+            &pubkeys,
+            &blake2b_aggregated_pubkey.0,
+            None,
+            tag,
+            &jubjub_params,
+            &rescue_params
+        ));
+
+        // order of pubkeys matters
+        let mut shuffled_pubkeys = pubkeys.clone();
+        shuffled_pubkeys.swap(0, 1);
+        assert!(!is_satisfied(
+            &shuffled_pubkeys,
+            &context.aggregated_pubkey.0,
+            None,
+            tag,
+            &jubjub_params,
+            &rescue_params
+        ));
+
+        // single pubkey is aggregated into itself
+        let privkey = PrivateKey::<Bn256>(rng.gen());
+        let pubkey = PublicKey::from_private(&privkey, generator, &jubjub_params);
+        assert!(is_satisfied(
+            &[pubkey.clone()],
+            &pubkey.0,
+            None,
+            tag,
+            &jubjub_params,
+            &rescue_params
+        ));
+    }
+}
@@ -0,0 +1,5 @@
+//! Circuit gadgets which check MuSig relations inside of a constraint
+//! system. Hashes and encodings used here must produce exactly the same
+//! values as their native counterparts in `hasher` and `encoder`.
+pub mod aggregation;
+pub mod signature;
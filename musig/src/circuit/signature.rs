@@ -0,0 +1,355 @@
+use crate::encoder::STANDARD_ENCODING_LENGTH;
+use crate::hasher::tag_prefix;
+use bellman::{ConstraintSystem, PrimeField, SynthesisError};
+use franklin_crypto::circuit::boolean::{field_into_allocated_bits_le, AllocatedBit, Boolean};
+use franklin_crypto::circuit::ecc::{fixed_base_multiplication, EdwardsPoint};
+use franklin_crypto::circuit::multipack;
+use franklin_crypto::circuit::rescue::rescue_hash;
+use franklin_crypto::eddsa::Signature;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine};
+use franklin_crypto::rescue::RescueEngine;
+
+/// Aggregated signature `(R, s)` allocated in the constraint system.
+pub struct AllocatedSignature<E: JubjubEngine> {
+    pub r: EdwardsPoint<E>,
+    pub s_bits: Vec<Boolean>,
+}
+
+impl<E: JubjubEngine> AllocatedSignature<E> {
+    pub fn alloc<CS: ConstraintSystem<E>>(
+        mut cs: CS,
+        signature: Option<&Signature<E>>,
+        params: &<E as JubjubEngine>::Params,
+    ) -> Result<Self, SynthesisError> {
+        let r = EdwardsPoint::witness(
+            cs.namespace(|| "signature r"),
+            signature.map(|signature| signature.r),
+            params,
+        )?;
+
+        let s_bits = field_into_allocated_bits_le(
+            cs.namespace(|| "signature s"),
+            signature.map(|signature| signature.s),
+        )?
+        .into_iter()
+        .map(Boolean::from)
+        .collect();
+
+        Ok(Self { r, s_bits })
+    }
+}
+
+/// Allocates bits of the message padded the same way as
+/// `Encoder::encode_signature_data` does it.
+pub fn message_into_bits<E: JubjubEngine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    message: Option<&[u8]>,
+) -> Result<Vec<Boolean>, SynthesisError> {
+    let msg_padded = message.map(|message| {
+        let mut msg_padded = message.to_vec();
+        msg_padded.resize(STANDARD_ENCODING_LENGTH, 0u8);
+        msg_padded
+    });
+
+    let mut bits = vec![];
+    for i in 0..STANDARD_ENCODING_LENGTH * 8 {
+        let value = msg_padded
+            .as_ref()
+            .map(|msg_padded| (msg_padded[i / 8] >> (i % 8)) & 1 == 1);
+        let bit = AllocatedBit::alloc(cs.namespace(|| format!("message bit {}", i)), value)?;
+        bits.push(Boolean::from(bit));
+    }
+
+    Ok(bits)
+}
+
+/// Little-endian bits of x coordinate, padded to the size of its
+/// native encoding, see `encoder::write_point`.
+pub(crate) fn point_x_into_bits<E: JubjubEngine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    point: &EdwardsPoint<E>,
+) -> Result<Vec<Boolean>, SynthesisError> {
+    let mut bits = point
+        .get_x()
+        .into_bits_le_strict(cs.namespace(|| "x into bits"))?;
+    bits.resize(STANDARD_ENCODING_LENGTH * 8, Boolean::constant(false));
+
+    Ok(bits)
+}
+
+/// Prefix of the domain tag as constant little-endian bits of each byte,
+/// the same way as native hashes prepend it to the preimage.
+pub(crate) fn tag_into_bits(tag: &[u8]) -> Vec<Boolean> {
+    tag_prefix(tag)
+        .into_iter()
+        .flat_map(|byte| (0..8).map(move |i| Boolean::constant((byte >> i) & 1 == 1)))
+        .collect()
+}
+
+/// In-circuit version of `rescue_hash_to_scalar`. Preimage is packed into
+/// field elements the same way as the native hash does it, lowest
+/// `E::Fs::CAPACITY` bits of the first output are the resulting scalar.
+pub(crate) fn rescue_hash_to_scalar_bits<E, CS>(
+    mut cs: CS,
+    preimage: &[Boolean],
+    rescue_params: &<E as RescueEngine>::Params,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: JubjubEngine + RescueEngine,
+    CS: ConstraintSystem<E>,
+{
+    let packed = multipack::pack_into_witness(cs.namespace(|| "pack preimage"), preimage)?;
+
+    let hash = rescue_hash(cs.namespace(|| "rescue hash"), &packed, rescue_params)?;
+
+    let mut hash_bits = hash[0].into_bits_le_strict(cs.namespace(|| "hash into bits"))?;
+    hash_bits.truncate(E::Fs::CAPACITY as usize);
+
+    Ok(hash_bits)
+}
+
+/// c = H_sig(X', R, m), see `Hasher::hash_signature_data`
+pub fn compute_challenge_bits<E, CS>(
+    mut cs: CS,
+    aggregated_pubkey: &EdwardsPoint<E>,
+    aggregated_commitment: &EdwardsPoint<E>,
+    message_bits: &[Boolean],
+    tag: &[u8],
+    rescue_params: &<E as RescueEngine>::Params,
+) -> Result<Vec<Boolean>, SynthesisError>
+where
+    E: JubjubEngine + RescueEngine,
+    CS: ConstraintSystem<E>,
+{
+    assert_eq!(message_bits.len(), STANDARD_ENCODING_LENGTH * 8);
+
+    let mut preimage = tag_into_bits(tag);
+    preimage.extend(point_x_into_bits(
+        cs.namespace(|| "aggregated pubkey bits"),
+        aggregated_pubkey,
+    )?);
+    preimage.extend(point_x_into_bits(
+        cs.namespace(|| "aggregated commitment bits"),
+        aggregated_commitment,
+    )?);
+    preimage.extend_from_slice(message_bits);
+
+    rescue_hash_to_scalar_bits(cs.namespace(|| "challenge"), &preimage, rescue_params)
+}
+
+/// Enforces that the signature is valid for the message under the
+/// aggregated public key: s * G == R + c * X'. Domain tag must be the
+/// one of the hash suite signature was produced with.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_musig_signature<E, CS>(
+    mut cs: CS,
+    aggregated_pubkey: &EdwardsPoint<E>,
+    signature: &AllocatedSignature<E>,
+    message_bits: &[Boolean],
+    tag: &[u8],
+    generator: FixedGenerators,
+    jubjub_params: &<E as JubjubEngine>::Params,
+    rescue_params: &<E as RescueEngine>::Params,
+) -> Result<(), SynthesisError>
+where
+    E: JubjubEngine + RescueEngine,
+    CS: ConstraintSystem<E>,
+{
+    aggregated_pubkey.assert_not_small_order(
+        cs.namespace(|| "aggregated pubkey is not small order"),
+        jubjub_params,
+    )?;
+    signature.r.assert_not_small_order(
+        cs.namespace(|| "aggregated commitment is not small order"),
+        jubjub_params,
+    )?;
+
+    let c_bits = compute_challenge_bits(
+        cs.namespace(|| "compute challenge"),
+        aggregated_pubkey,
+        &signature.r,
+        message_bits,
+        tag,
+        rescue_params,
+    )?;
+
+    // s * G
+    let lhs = fixed_base_multiplication(
+        cs.namespace(|| "s * G"),
+        generator,
+        &signature.s_bits,
+        jubjub_params,
+    )?;
+
+    // R + c * X'
+    let rhs = aggregated_pubkey
+        .mul(cs.namespace(|| "c * X'"), &c_bits, jubjub_params)?
+        .add(cs.namespace(|| "R + c * X'"), &signature.r, jubjub_params)?;
+
+    enforce_points_equal(cs.namespace(|| "s * G == R + c * X'"), &lhs, &rhs);
+
+    Ok(())
+}
+
+pub(crate) fn enforce_points_equal<E: JubjubEngine, CS: ConstraintSystem<E>>(
+    mut cs: CS,
+    p: &EdwardsPoint<E>,
+    q: &EdwardsPoint<E>,
+) {
+    cs.enforce(
+        || "x coordinates are equal",
+        |lc| lc + p.get_x().get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + q.get_x().get_variable(),
+    );
+    cs.enforce(
+        || "y coordinates are equal",
+        |lc| lc + p.get_y().get_variable(),
+        |lc| lc + CS::one(),
+        |lc| lc + q.get_y().get_variable(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{message_into_bits, verify_musig_signature, AllocatedSignature};
+    use crate::aggregated_pubkey::AggregatedPublicKey;
+    use crate::hash_suite::DefaultHashSuite;
+    use crate::tests::{
+        musig_multi_party_test_runner, musig_test_bn256_setup, random_message_hash,
+    };
+    use bellman::pairing::bn256::Bn256;
+    use bellman::{ConstraintSystem, Field};
+    use franklin_crypto::alt_babyjubjub::{fs::Fs, AltJubjubBn256};
+    use franklin_crypto::circuit::ecc::EdwardsPoint;
+    use franklin_crypto::circuit::test::TestConstraintSystem;
+    use franklin_crypto::eddsa::{PublicKey, Signature};
+    use franklin_crypto::jubjub::FixedGenerators;
+    use franklin_crypto::rescue::bn256::Bn256RescueParams;
+    use rand::{SeedableRng, XorShiftRng};
+
+    fn is_satisfied(
+        pubkeys: &[PublicKey<Bn256>],
+        signature: &Signature<Bn256>,
+        message: &[u8],
+        tag: &[u8],
+        jubjub_params: &AltJubjubBn256,
+        rescue_params: &Bn256RescueParams,
+    ) -> bool {
+        let generator = FixedGenerators::SpendingKeyGenerator;
+        let mut cs = TestConstraintSystem::<Bn256>::new();
+
+        let (aggregated_pubkey, _) = AggregatedPublicKey::compute_for_each_party(
+            pubkeys,
+            jubjub_params,
+            &DefaultHashSuite::new(rescue_params),
+        )
+        .unwrap();
+
+        let aggregated_pubkey = EdwardsPoint::witness(
+            cs.namespace(|| "aggregated pubkey"),
+            Some(aggregated_pubkey.0),
+            jubjub_params,
+        )
+        .unwrap();
+        let signature =
+            AllocatedSignature::alloc(cs.namespace(|| "signature"), Some(signature), jubjub_params)
+                .unwrap();
+        let message_bits = message_into_bits(cs.namespace(|| "message"), Some(message)).unwrap();
+
+        verify_musig_signature(
+            cs.namespace(|| "verify signature"),
+            &aggregated_pubkey,
+            &signature,
+            &message_bits,
+            tag,
+            generator,
+            jubjub_params,
+            rescue_params,
+        )
+        .unwrap();
+
+        cs.is_satisfied()
+    }
+
+    #[test]
+    fn test_musig_signature_gadget() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let jubjub_params = AltJubjubBn256::new();
+        let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+        let generator = FixedGenerators::SpendingKeyGenerator;
+
+        let message = random_message_hash(rng);
+
+        let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+        let (privkeys, pubkeys, mut signers) =
+            musig_test_bn256_setup(3, &jubjub_params, hash_suite).unwrap();
+
+        let signature = musig_multi_party_test_runner(
+            rng,
+            &message,
+            &pubkeys,
+            &privkeys,
+            &mut signers,
+            &jubjub_params,
+            &hash_suite,
+            generator,
+        )
+        .unwrap();
+
+        assert!(is_satisfied(
+            &pubkeys,
+            &signature,
+            &message,
+            &[],
+            &jubjub_params,
+            &rescue_params
+        ));
+
+        // tampered s
+        let mut tampered_signature = signature.clone();
+        tampered_signature.s.add_assign(&Fs::one());
+        assert!(!is_satisfied(
+            &pubkeys,
+            &tampered_signature,
+            &message,
+            &[],
+            &jubjub_params,
+            &rescue_params
+        ));
+
+        // tampered message
+        let mut tampered_message = message.clone();
+        tampered_message[0] ^= 1;
+        assert!(!is_satisfied(
+            &pubkeys,
+            &signature,
+            &tampered_message,
+            &[],
+            &jubjub_params,
+            &rescue_params
+        ));
+
+        // signature of another application
+        assert!(!is_satisfied(
+            &pubkeys,
+            &signature,
+            &message,
+            b"another application",
+            &jubjub_params,
+            &rescue_params
+        ));
+
+        // signature of different signer set
+        assert!(!is_satisfied(
+            &pubkeys[1..],
+            &signature,
+            &message,
+            &[],
+            &jubjub_params,
+            &rescue_params
+        ));
+    }
+}
@@ -17,6 +17,24 @@ pub fn write_point<E: JubjubEngine>(point: &Point<E, Unknown>, dest: &mut Vec<u8
     dest.extend_from_slice(&x_bytes);
 }
 
+/// Reads a compressed point and accepts only its canonical encoding.
+/// Points with x = 0 are decoded with either sign bit, only the encoding
+/// that is written back is canonical.
+pub(crate) fn read_canonical_point<E: JubjubEngine>(
+    bytes: &[u8],
+    params: &<E as JubjubEngine>::Params,
+) -> Option<Point<E, Unknown>> {
+    let point = Point::read(bytes, params).ok()?;
+
+    let mut canonical = vec![];
+    point.write(&mut canonical).expect("has serialized point");
+    if canonical[..] != bytes[..] {
+        return None;
+    }
+
+    Some(point)
+}
+
 pub struct Encoder<E: JubjubEngine> {
     marker: PhantomData<E>,
 }
@@ -34,6 +52,23 @@ impl<E: JubjubEngine> Encoder<E> {
         buf
     }
 
+    // H_agg(L, X_i) with rescue, it takes list of pubkeys and
+    // pubkey of actual signer separately
+    pub(crate) fn encode_aggregated_data_for_rescue(
+        pubkeys: &[PublicKey<E>],
+        position: usize,
+    ) -> (Vec<u8>, Vec<u8>) {
+        let mut buf = vec![];
+        for pubkey in pubkeys {
+            write_point(&pubkey.0, &mut buf);
+        }
+
+        let mut signer_buf = vec![];
+        write_point(&pubkeys[position].0, &mut signer_buf);
+
+        (buf, signer_buf)
+    }
+
     // H_comm(R_i)
     pub(crate) fn encode_commitment_data(commitment: &Point<E, Unknown>) -> Vec<u8> {
         let mut buf = vec![];
@@ -42,6 +77,55 @@ impl<E: JubjubEngine> Encoder<E> {
         buf
     }
 
+    // H_comm(R_i, T)
+    pub(crate) fn encode_adaptor_commitment_data(
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Vec<u8> {
+        let mut buf = vec![];
+        write_point(&commitment, &mut buf);
+        write_point(&adaptor_point, &mut buf);
+
+        buf
+    }
+
+    // H_keyagg(L, X')
+    pub(crate) fn encode_key_aggregation_data(
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+    ) -> Vec<u8> {
+        let mut buf = vec![];
+        for pubkey in pubkeys {
+            write_point(&pubkey.0, &mut buf);
+        }
+        write_point(&aggregated_pubkey.0, &mut buf);
+
+        buf
+    }
+
+    // H_sess(sid, i, H_keyagg(L, X'), H_comm(R_i))
+    pub(crate) fn encode_session_commitment_data(
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+    ) -> Vec<u8> {
+        let mut buf = session_id.to_vec();
+        buf.extend_from_slice(&(position as u32).to_be_bytes());
+        buf.extend_from_slice(key_aggregation_hash);
+        buf.extend_from_slice(commitment_hash);
+
+        buf
+    }
+
+    // H_msg(sid, m)
+    pub(crate) fn encode_session_message_data(session_id: &[u8], message: &[u8]) -> Vec<u8> {
+        let mut buf = session_id.to_vec();
+        buf.extend_from_slice(message);
+
+        buf
+    }
+
     // H_sig(X', R, m)
     pub(crate) fn encode_signature_data(
         aggregated_pubkey: &PublicKey<E>,
@@ -60,4 +144,33 @@ impl<E: JubjubEngine> Encoder<E> {
 
         (buf, msg_padded)
     }
+
+    // H_non(X', R_1, R_2, m)
+    pub(crate) fn encode_nonce_coefficient_data(
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitments: &(Point<E, Unknown>, Point<E, Unknown>),
+        message: &[u8],
+    ) -> (Vec<u8>, Vec<u8>) {
+        let mut buf = vec![];
+
+        write_point(&aggregated_pubkey.0, &mut buf);
+        write_point(&aggregated_commitments.0, &mut buf);
+        write_point(&aggregated_commitments.1, &mut buf);
+
+        let mut msg_padded: Vec<u8> = message.to_vec();
+        msg_padded.resize(STANDARD_ENCODING_LENGTH, 0u8);
+
+        (buf, msg_padded)
+    }
+
+    // H_tweak(X', data)
+    pub(crate) fn encode_tweak_data(
+        aggregated_pubkey: &PublicKey<E>,
+        data: &[u8],
+    ) -> (Vec<u8>, Vec<u8>) {
+        let mut buf = vec![];
+        write_point(&aggregated_pubkey.0, &mut buf);
+
+        (buf, data.to_vec())
+    }
 }
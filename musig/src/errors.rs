@@ -1,3 +1,4 @@
+use crate::wire::Round;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -32,4 +33,64 @@ pub enum MusigError {
     InvalidSignatureShare,
     #[error("Seed length must be 128 bytes")]
     InvalidSeed,
+    #[error("Nonce has already been used for a signature share")]
+    NonceAlreadyUsed,
+    #[error("Signer state is malformed")]
+    InvalidSignerState,
+    #[error("Adaptor point is not in a correct subgroup")]
+    AdaptorPointIsNotInCorrectSubgroup,
+    #[error("Signature shares are produced without adaptor point")]
+    AdaptorPointNotSet,
+    #[error("Adaptor point differs from the one bound before pre-commitments")]
+    AdaptorPointMismatch,
+    #[error("Signature shares are produced with adaptor point, they form a pre-signature")]
+    PreSignatureExpected,
+    #[error("Signature does not complete pre-signature with secret of its adaptor point")]
+    AdaptorSecretMismatch,
+    #[error("Tweaked public key is the identity point")]
+    InvalidTweak,
+    #[error("Batch must be non-empty and each party must send an item for each message")]
+    InvalidBatchSize,
+    #[error("Nonce pool has no entry with such index")]
+    InvalidPoolEntry,
+    #[error("Encoded signature must be 64 bytes")]
+    InvalidSignatureLength,
+    #[error("Signature point is not canonically encoded or not in a correct subgroup")]
+    InvalidSignaturePoint,
+    #[error("Signature scalar is not less than the group order")]
+    InvalidSignatureScalar,
+    #[error("Wire message is malformed")]
+    InvalidWireMessage,
+    #[error("Wire message has unsupported protocol version")]
+    UnsupportedWireVersion,
+    #[error("Wire message belongs to another session")]
+    WireSessionMismatch,
+    #[error("Wire message belongs to another round")]
+    UnexpectedWireRound,
+    #[error("Session id must be set before pre-commitment is computed")]
+    SessionAlreadyStarted,
+    #[error("Commitment does not match its pre-commitment for this session, position and group")]
+    SessionCommitmentMismatch,
+    #[error("Commitment is the identity point")]
+    IdentityCommitment,
+}
+
+#[derive(Error, Debug)]
+pub enum CeremonyError {
+    #[error("{0}")]
+    Musig(#[from] MusigError),
+    #[error("Transport failed: {0}")]
+    Transport(#[from] std::io::Error),
+    #[error("Round {round:?} timed out, messages of parties {missing:?} are missing")]
+    Timeout { round: Round, missing: Vec<usize> },
+    #[error(
+        "Incoming messages ended in round {round:?}, messages of parties {missing:?} are missing"
+    )]
+    Disconnected { round: Round, missing: Vec<usize> },
+    #[error("Party {0} aborted the ceremony")]
+    Aborted(usize),
+    #[error("Party {0} sent conflicting messages in the same round")]
+    Equivocation(usize),
+    #[error("Party {party} sent a message in the name of party {sender}")]
+    ForgedSender { party: usize, sender: usize },
 }
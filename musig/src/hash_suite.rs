@@ -0,0 +1,588 @@
+use crate::encoder::Encoder;
+use crate::hasher::{
+    adaptor_commitment_persona, key_aggregation_persona, poseidon_hash_to_field,
+    poseidon_hash_to_scalar, session_message_persona, session_persona, sha256_hash_to_scalar,
+    tag_prefix, tweak_persona, Hasher,
+};
+use bellman::{PrimeField, PrimeFieldRepr};
+use franklin_crypto::eddsa::PublicKey;
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{JubjubEngine, ToUniform, Unknown};
+use franklin_crypto::poseidon::PoseidonEngine;
+use franklin_crypto::rescue::RescueEngine;
+use std::marker::PhantomData;
+
+/// Set of hash functions the protocol is instantiated with. Signers and
+/// verifiers of the same group must use the same suite and the same domain
+/// tag, otherwise they compute different aggregated keys and challenges.
+///
+/// Domain tag identifies an application and is bound into all of its hashes,
+/// so signatures of one application can't be replayed in another one which
+/// uses the same keys. The tag is prepended as its fixed-length
+/// `tag_prefix`. Suites created without a tag hash their inputs as is.
+pub trait HashSuite<E: JubjubEngine> {
+    /// a_i = H_agg(L, X_i)
+    fn hash_aggregated(&self, pubkeys: &[PublicKey<E>], position: usize) -> E::Fs;
+
+    /// t_i = H_comm(R_i)
+    fn hash_commitment(&self, commitment: &Point<E, Unknown>) -> Vec<u8>;
+
+    /// t_i = H_comm(R_i, T), pre-commitment of a run bound to the adaptor
+    /// point T, see `MuSigSigner::set_adaptor_point`
+    fn hash_adaptor_commitment(
+        &self,
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Vec<u8>;
+
+    /// c = H_sig(X', R, m)
+    fn hash_signature_data(
+        &self,
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitment: &Point<E, Unknown>,
+        message: &[u8],
+    ) -> E::Fs;
+
+    /// t = H_tweak(X', data), tweak which commits aggregated key to data,
+    /// see `KeyAggregationContext::apply_taproot_tweak`
+    fn hash_tweak(&self, aggregated_pubkey: &PublicKey<E>, data: &[u8]) -> E::Fs;
+
+    /// H_keyagg(L, X'), identifies the group and its aggregated key,
+    /// see `KeyAggregationContext::key_aggregation_hash`
+    fn hash_key_aggregation(
+        &self,
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+    ) -> Vec<u8>;
+
+    /// t_i = H_sess(sid, i, H_keyagg(L, X'), H_comm(R_i)), pre-commitment
+    /// of a run bound to a session, see `MuSigSigner::set_session_id`
+    fn hash_session_commitment(
+        &self,
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+    ) -> Vec<u8>;
+
+    /// m' = H_msg(sid, m), message which a run bound to a session signs
+    /// instead of m, so its signature depends on the session id
+    fn hash_session_message(&self, session_id: &[u8], message: &[u8]) -> Vec<u8>;
+}
+
+/// Blake2b for H_agg, SHA-256 for H_comm and Rescue for H_sig.
+/// Signatures are verifiable with `PublicKey::verify_musig_rescue`.
+pub struct DefaultHashSuite<'a, E: JubjubEngine + RescueEngine> {
+    rescue_params: &'a <E as RescueEngine>::Params,
+    tag: &'a [u8],
+}
+
+impl<'a, E: JubjubEngine + RescueEngine> DefaultHashSuite<'a, E> {
+    pub fn new(rescue_params: &'a <E as RescueEngine>::Params) -> Self {
+        Self::new_with_tag(rescue_params, &[])
+    }
+
+    pub fn new_with_tag(rescue_params: &'a <E as RescueEngine>::Params, tag: &'a [u8]) -> Self {
+        Self { rescue_params, tag }
+    }
+}
+
+impl<'a, E: JubjubEngine + RescueEngine> Clone for DefaultHashSuite<'a, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E: JubjubEngine + RescueEngine> Copy for DefaultHashSuite<'a, E> {}
+
+impl<'a, E: JubjubEngine + RescueEngine> HashSuite<E> for DefaultHashSuite<'a, E> {
+    fn hash_aggregated(&self, pubkeys: &[PublicKey<E>], position: usize) -> E::Fs {
+        E::Fs::to_uniform(&Hasher::hash_aggregated(pubkeys, position, self.tag))
+    }
+
+    fn hash_commitment(&self, commitment: &Point<E, Unknown>) -> Vec<u8> {
+        Hasher::hash_commitment(commitment, self.tag)
+    }
+
+    fn hash_adaptor_commitment(
+        &self,
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Vec<u8> {
+        Hasher::hash_adaptor_commitment(commitment, adaptor_point, self.tag)
+    }
+
+    fn hash_signature_data(
+        &self,
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitment: &Point<E, Unknown>,
+        message: &[u8],
+    ) -> E::Fs {
+        Hasher::hash_signature_data::<E>(
+            aggregated_pubkey,
+            aggregated_commitment,
+            message,
+            self.tag,
+            self.rescue_params,
+        )
+    }
+
+    fn hash_tweak(&self, aggregated_pubkey: &PublicKey<E>, data: &[u8]) -> E::Fs {
+        Hasher::hash_tweak::<E>(aggregated_pubkey, data, self.tag, self.rescue_params)
+    }
+
+    fn hash_key_aggregation(
+        &self,
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+    ) -> Vec<u8> {
+        Hasher::hash_key_aggregation(pubkeys, aggregated_pubkey, self.tag)
+    }
+
+    fn hash_session_commitment(
+        &self,
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+    ) -> Vec<u8> {
+        Hasher::<E>::hash_session_commitment(
+            session_id,
+            position,
+            key_aggregation_hash,
+            commitment_hash,
+            self.tag,
+        )
+    }
+
+    fn hash_session_message(&self, session_id: &[u8], message: &[u8]) -> Vec<u8> {
+        Hasher::<E>::hash_session_message(session_id, message, self.tag)
+    }
+}
+
+/// Rescue for H_agg and H_sig, SHA-256 for H_comm. Both key aggregation
+/// and signature verification are cheap to check in circuit, see
+/// `circuit::aggregation` and `circuit::signature`.
+pub struct RescueHashSuite<'a, E: JubjubEngine + RescueEngine> {
+    rescue_params: &'a <E as RescueEngine>::Params,
+    tag: &'a [u8],
+}
+
+impl<'a, E: JubjubEngine + RescueEngine> RescueHashSuite<'a, E> {
+    pub fn new(rescue_params: &'a <E as RescueEngine>::Params) -> Self {
+        Self::new_with_tag(rescue_params, &[])
+    }
+
+    pub fn new_with_tag(rescue_params: &'a <E as RescueEngine>::Params, tag: &'a [u8]) -> Self {
+        Self { rescue_params, tag }
+    }
+}
+
+impl<'a, E: JubjubEngine + RescueEngine> Clone for RescueHashSuite<'a, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E: JubjubEngine + RescueEngine> Copy for RescueHashSuite<'a, E> {}
+
+impl<'a, E: JubjubEngine + RescueEngine> HashSuite<E> for RescueHashSuite<'a, E> {
+    fn hash_aggregated(&self, pubkeys: &[PublicKey<E>], position: usize) -> E::Fs {
+        Hasher::hash_aggregated_rescue::<E>(pubkeys, position, self.tag, self.rescue_params)
+    }
+
+    fn hash_commitment(&self, commitment: &Point<E, Unknown>) -> Vec<u8> {
+        Hasher::hash_commitment(commitment, self.tag)
+    }
+
+    fn hash_adaptor_commitment(
+        &self,
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Vec<u8> {
+        Hasher::hash_adaptor_commitment(commitment, adaptor_point, self.tag)
+    }
+
+    fn hash_signature_data(
+        &self,
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitment: &Point<E, Unknown>,
+        message: &[u8],
+    ) -> E::Fs {
+        Hasher::hash_signature_data::<E>(
+            aggregated_pubkey,
+            aggregated_commitment,
+            message,
+            self.tag,
+            self.rescue_params,
+        )
+    }
+
+    fn hash_tweak(&self, aggregated_pubkey: &PublicKey<E>, data: &[u8]) -> E::Fs {
+        Hasher::hash_tweak::<E>(aggregated_pubkey, data, self.tag, self.rescue_params)
+    }
+
+    fn hash_key_aggregation(
+        &self,
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+    ) -> Vec<u8> {
+        Hasher::hash_key_aggregation(pubkeys, aggregated_pubkey, self.tag)
+    }
+
+    fn hash_session_commitment(
+        &self,
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+    ) -> Vec<u8> {
+        Hasher::<E>::hash_session_commitment(
+            session_id,
+            position,
+            key_aggregation_hash,
+            commitment_hash,
+            self.tag,
+        )
+    }
+
+    fn hash_session_message(&self, session_id: &[u8], message: &[u8]) -> Vec<u8> {
+        Hasher::<E>::hash_session_message(session_id, message, self.tag)
+    }
+}
+
+/// Poseidon for all of H_agg, H_comm, H_sig, H_keyagg and the session hashes.
+/// Pre-commitment is the little-endian encoding of the hash output.
+pub struct PoseidonHashSuite<'a, E: JubjubEngine + PoseidonEngine> {
+    poseidon_params: &'a <E as PoseidonEngine>::Params,
+    tag: &'a [u8],
+}
+
+impl<'a, E: JubjubEngine + PoseidonEngine> PoseidonHashSuite<'a, E> {
+    pub fn new(poseidon_params: &'a <E as PoseidonEngine>::Params) -> Self {
+        Self::new_with_tag(poseidon_params, &[])
+    }
+
+    pub fn new_with_tag(poseidon_params: &'a <E as PoseidonEngine>::Params, tag: &'a [u8]) -> Self {
+        Self {
+            poseidon_params,
+            tag,
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine + PoseidonEngine> Clone for PoseidonHashSuite<'a, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E: JubjubEngine + PoseidonEngine> Copy for PoseidonHashSuite<'a, E> {}
+
+impl<'a, E: JubjubEngine + PoseidonEngine> HashSuite<E> for PoseidonHashSuite<'a, E> {
+    fn hash_aggregated(&self, pubkeys: &[PublicKey<E>], position: usize) -> E::Fs {
+        let (a, b) = Encoder::encode_aggregated_data_for_rescue(pubkeys, position);
+
+        poseidon_hash_to_scalar::<E>(&tag_prefix(self.tag), &a, &b, self.poseidon_params)
+    }
+
+    fn hash_commitment(&self, commitment: &Point<E, Unknown>) -> Vec<u8> {
+        let encoded_data = Encoder::encode_commitment_data(commitment);
+
+        poseidon_digest::<E>(&tag_prefix(self.tag), &encoded_data, self.poseidon_params)
+    }
+
+    fn hash_adaptor_commitment(
+        &self,
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Vec<u8> {
+        let encoded_data = Encoder::encode_adaptor_commitment_data(commitment, adaptor_point);
+
+        poseidon_digest::<E>(
+            &adaptor_commitment_persona(self.tag),
+            &encoded_data,
+            self.poseidon_params,
+        )
+    }
+
+    fn hash_signature_data(
+        &self,
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitment: &Point<E, Unknown>,
+        message: &[u8],
+    ) -> E::Fs {
+        let (a, b) =
+            Encoder::encode_signature_data(aggregated_pubkey, aggregated_commitment, message);
+
+        poseidon_hash_to_scalar::<E>(&tag_prefix(self.tag), &a, &b, self.poseidon_params)
+    }
+
+    fn hash_tweak(&self, aggregated_pubkey: &PublicKey<E>, data: &[u8]) -> E::Fs {
+        let (a, b) = Encoder::encode_tweak_data(aggregated_pubkey, data);
+
+        poseidon_hash_to_scalar::<E>(&tweak_persona(self.tag), &a, &b, self.poseidon_params)
+    }
+
+    fn hash_key_aggregation(
+        &self,
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+    ) -> Vec<u8> {
+        let encoded_data = Encoder::encode_key_aggregation_data(pubkeys, aggregated_pubkey);
+
+        poseidon_digest::<E>(
+            &key_aggregation_persona(self.tag),
+            &encoded_data,
+            self.poseidon_params,
+        )
+    }
+
+    fn hash_session_commitment(
+        &self,
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+    ) -> Vec<u8> {
+        let encoded_data = Encoder::<E>::encode_session_commitment_data(
+            session_id,
+            position,
+            key_aggregation_hash,
+            commitment_hash,
+        );
+
+        poseidon_digest::<E>(
+            &session_persona(self.tag),
+            &encoded_data,
+            self.poseidon_params,
+        )
+    }
+
+    fn hash_session_message(&self, session_id: &[u8], message: &[u8]) -> Vec<u8> {
+        let encoded_data = Encoder::<E>::encode_session_message_data(session_id, message);
+
+        poseidon_digest::<E>(
+            &session_message_persona(self.tag),
+            &encoded_data,
+            self.poseidon_params,
+        )
+    }
+}
+
+// little-endian encoding of the Poseidon hash of data
+fn poseidon_digest<E: JubjubEngine + PoseidonEngine>(
+    persona: &[u8],
+    data: &[u8],
+    params: &<E as PoseidonEngine>::Params,
+) -> Vec<u8> {
+    let hash = poseidon_hash_to_field::<E>(persona, data, &[], params);
+
+    let mut buf = vec![];
+    hash.into_repr()
+        .write_le(&mut buf)
+        .expect("has serialized hash");
+
+    buf
+}
+
+/// SHA-256 for all of H_agg, H_comm and H_sig. Scalars are digests
+/// reduced modulo the order of the scalar field, see `sha256_hash_to_scalar`.
+pub struct Sha256HashSuite<'a, E: JubjubEngine> {
+    tag: &'a [u8],
+    marker: PhantomData<E>,
+}
+
+impl<'a, E: JubjubEngine> Sha256HashSuite<'a, E> {
+    pub fn new() -> Self {
+        Self::new_with_tag(&[])
+    }
+
+    pub fn new_with_tag(tag: &'a [u8]) -> Self {
+        Self {
+            tag,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine> Default for Sha256HashSuite<'a, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, E: JubjubEngine> Clone for Sha256HashSuite<'a, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E: JubjubEngine> Copy for Sha256HashSuite<'a, E> {}
+
+impl<'a, E: JubjubEngine> HashSuite<E> for Sha256HashSuite<'a, E> {
+    fn hash_aggregated(&self, pubkeys: &[PublicKey<E>], position: usize) -> E::Fs {
+        let (a, b) = Encoder::encode_aggregated_data_for_rescue(pubkeys, position);
+
+        sha256_hash_to_scalar::<E>(&tag_prefix(self.tag), &a, &b)
+    }
+
+    fn hash_commitment(&self, commitment: &Point<E, Unknown>) -> Vec<u8> {
+        Hasher::hash_commitment(commitment, self.tag)
+    }
+
+    fn hash_adaptor_commitment(
+        &self,
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Vec<u8> {
+        Hasher::hash_adaptor_commitment(commitment, adaptor_point, self.tag)
+    }
+
+    fn hash_signature_data(
+        &self,
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitment: &Point<E, Unknown>,
+        message: &[u8],
+    ) -> E::Fs {
+        let (a, b) =
+            Encoder::encode_signature_data(aggregated_pubkey, aggregated_commitment, message);
+
+        sha256_hash_to_scalar::<E>(&tag_prefix(self.tag), &a, &b)
+    }
+
+    fn hash_tweak(&self, aggregated_pubkey: &PublicKey<E>, data: &[u8]) -> E::Fs {
+        let (a, b) = Encoder::encode_tweak_data(aggregated_pubkey, data);
+
+        sha256_hash_to_scalar::<E>(&tweak_persona(self.tag), &a, &b)
+    }
+
+    fn hash_key_aggregation(
+        &self,
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+    ) -> Vec<u8> {
+        Hasher::hash_key_aggregation(pubkeys, aggregated_pubkey, self.tag)
+    }
+
+    fn hash_session_commitment(
+        &self,
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+    ) -> Vec<u8> {
+        Hasher::<E>::hash_session_commitment(
+            session_id,
+            position,
+            key_aggregation_hash,
+            commitment_hash,
+            self.tag,
+        )
+    }
+
+    fn hash_session_message(&self, session_id: &[u8], message: &[u8]) -> Vec<u8> {
+        Hasher::<E>::hash_session_message(session_id, message, self.tag)
+    }
+}
+
+/// Blake2b for H_agg, SHA-256 for H_comm and H_sig. Aggregated keys are the
+/// same as with `DefaultHashSuite`, but the challenge is computed as
+/// `uint256(sha256(prefix || x(X') || x(R) || m)) % order`, where
+/// prefix is `tag_prefix(tag)`, so signatures are cheap to verify
+/// where Rescue is not available, e.g. in EVM.
+pub struct Sha256ChallengeHashSuite<'a, E: JubjubEngine> {
+    tag: &'a [u8],
+    marker: PhantomData<E>,
+}
+
+impl<'a, E: JubjubEngine> Sha256ChallengeHashSuite<'a, E> {
+    pub fn new() -> Self {
+        Self::new_with_tag(&[])
+    }
+
+    pub fn new_with_tag(tag: &'a [u8]) -> Self {
+        Self {
+            tag,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, E: JubjubEngine> Default for Sha256ChallengeHashSuite<'a, E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, E: JubjubEngine> Clone for Sha256ChallengeHashSuite<'a, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E: JubjubEngine> Copy for Sha256ChallengeHashSuite<'a, E> {}
+
+impl<'a, E: JubjubEngine> HashSuite<E> for Sha256ChallengeHashSuite<'a, E> {
+    fn hash_aggregated(&self, pubkeys: &[PublicKey<E>], position: usize) -> E::Fs {
+        E::Fs::to_uniform(&Hasher::hash_aggregated(pubkeys, position, self.tag))
+    }
+
+    fn hash_commitment(&self, commitment: &Point<E, Unknown>) -> Vec<u8> {
+        Hasher::hash_commitment(commitment, self.tag)
+    }
+
+    fn hash_adaptor_commitment(
+        &self,
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Vec<u8> {
+        Hasher::hash_adaptor_commitment(commitment, adaptor_point, self.tag)
+    }
+
+    fn hash_signature_data(
+        &self,
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitment: &Point<E, Unknown>,
+        message: &[u8],
+    ) -> E::Fs {
+        let (a, b) =
+            Encoder::encode_signature_data(aggregated_pubkey, aggregated_commitment, message);
+
+        sha256_hash_to_scalar::<E>(&tag_prefix(self.tag), &a, &b)
+    }
+
+    fn hash_tweak(&self, aggregated_pubkey: &PublicKey<E>, data: &[u8]) -> E::Fs {
+        let (a, b) = Encoder::encode_tweak_data(aggregated_pubkey, data);
+
+        sha256_hash_to_scalar::<E>(&tweak_persona(self.tag), &a, &b)
+    }
+
+    fn hash_key_aggregation(
+        &self,
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+    ) -> Vec<u8> {
+        Hasher::hash_key_aggregation(pubkeys, aggregated_pubkey, self.tag)
+    }
+
+    fn hash_session_commitment(
+        &self,
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+    ) -> Vec<u8> {
+        Hasher::<E>::hash_session_commitment(
+            session_id,
+            position,
+            key_aggregation_hash,
+            commitment_hash,
+            self.tag,
+        )
+    }
+
+    fn hash_session_message(&self, session_id: &[u8], message: &[u8]) -> Vec<u8> {
+        Hasher::<E>::hash_session_message(session_id, message, self.tag)
+    }
+}
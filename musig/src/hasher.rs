@@ -1,23 +1,39 @@
 use crate::encoder::Encoder;
+use bellman::{PrimeField, PrimeFieldRepr};
 use blake2::{Blake2b, Digest as Blake2Digest};
+use franklin_crypto::circuit::multipack;
 use franklin_crypto::eddsa::PublicKey;
 use franklin_crypto::jubjub::edwards::Point;
-use franklin_crypto::jubjub::{JubjubEngine, Unknown};
+use franklin_crypto::jubjub::{JubjubEngine, ToUniform, Unknown};
+use franklin_crypto::poseidon::{poseidon_hash, PoseidonEngine};
 use franklin_crypto::rescue::RescueEngine;
 use franklin_crypto::util::rescue_hash_to_scalar;
 use sha2::{Digest, Sha256};
 use std::marker::PhantomData;
+
+// separates key tweaks from the other hashes of the same suite
+const TWEAK_DOMAIN: &[u8] = b"MuSig/tweak";
+const NONCE_COEFFICIENT_DOMAIN: &[u8] = b"MuSig/noncecoef";
+const KEY_AGGREGATION_DOMAIN: &[u8] = b"MuSig/keyagg";
+const ADAPTOR_COMMITMENT_DOMAIN: &[u8] = b"MuSig/adaptor";
+const SESSION_DOMAIN: &[u8] = b"MuSig/session";
+const SESSION_MESSAGE_DOMAIN: &[u8] = b"MuSig/message";
+
 pub struct Hasher<E: JubjubEngine> {
     marker: PhantomData<E>,
 }
 
+// Each hash takes a domain tag whose `tag_prefix` is prepended to its
+// preimage, so signatures of one application are not valid in another one.
+// Empty tag keeps the preimage as is.
 impl<E: JubjubEngine> Hasher<E> {
     // H_agg(L, X_i)
-    pub fn hash_aggregated(pubkeys: &[PublicKey<E>], position: usize) -> Vec<u8> {
+    pub fn hash_aggregated(pubkeys: &[PublicKey<E>], position: usize, tag: &[u8]) -> Vec<u8> {
         // sha256 produces 32bytes output we use blake2b instead
         let encoded_data = Encoder::encode_aggregated_data(pubkeys, position);
 
         let mut blake2b = Blake2b::new();
+        blake2b.update(tag_prefix(tag));
         blake2b.update(encoded_data);
         let result = blake2b.finalize();
 
@@ -26,9 +42,83 @@ impl<E: JubjubEngine> Hasher<E> {
         result.to_vec()
     }
 
+    // H_agg(L, X_i) with rescue, which is cheap to recompute in circuit
+    pub fn hash_aggregated_rescue<R: JubjubEngine + RescueEngine>(
+        pubkeys: &[PublicKey<E>],
+        position: usize,
+        tag: &[u8],
+        params: &<R as RescueEngine>::Params,
+    ) -> R::Fs {
+        let (a, b) = Encoder::encode_aggregated_data_for_rescue(pubkeys, position);
+
+        rescue_hash_to_scalar::<R>(&tag_prefix(tag), &a, &b, params)
+    }
+
     // H_comm(R_i)
-    pub fn hash_commitment(commitment: &Point<E, Unknown>) -> Vec<u8> {
-        Sha256::digest(&Encoder::encode_commitment_data(commitment)).to_vec()
+    pub fn hash_commitment(commitment: &Point<E, Unknown>, tag: &[u8]) -> Vec<u8> {
+        let mut preimage = tag_prefix(tag);
+        preimage.extend(Encoder::encode_commitment_data(commitment));
+
+        Sha256::digest(&preimage).to_vec()
+    }
+
+    // H_comm(R_i, T), commits to the nonce and to the adaptor point
+    pub fn hash_adaptor_commitment(
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+        tag: &[u8],
+    ) -> Vec<u8> {
+        let mut preimage = adaptor_commitment_persona(tag);
+        preimage.extend(Encoder::encode_adaptor_commitment_data(
+            commitment,
+            adaptor_point,
+        ));
+
+        Sha256::digest(&preimage).to_vec()
+    }
+
+    // H_keyagg(L, X'), identifies the group and its aggregated key
+    pub fn hash_key_aggregation(
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+        tag: &[u8],
+    ) -> Vec<u8> {
+        let mut preimage = key_aggregation_persona(tag);
+        preimage.extend(Encoder::encode_key_aggregation_data(
+            pubkeys,
+            aggregated_pubkey,
+        ));
+
+        Sha256::digest(&preimage).to_vec()
+    }
+
+    // H_sess(sid, i, H_keyagg(L, X'), H_comm(R_i))
+    pub fn hash_session_commitment(
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+        tag: &[u8],
+    ) -> Vec<u8> {
+        let mut preimage = session_persona(tag);
+        preimage.extend(Encoder::<E>::encode_session_commitment_data(
+            session_id,
+            position,
+            key_aggregation_hash,
+            commitment_hash,
+        ));
+
+        Sha256::digest(&preimage).to_vec()
+    }
+
+    // H_msg(sid, m)
+    pub fn hash_session_message(session_id: &[u8], message: &[u8], tag: &[u8]) -> Vec<u8> {
+        let mut preimage = session_message_persona(tag);
+        preimage.extend(Encoder::<E>::encode_session_message_data(
+            session_id, message,
+        ));
+
+        Sha256::digest(&preimage).to_vec()
     }
 
     // H_sig(X', R, m)
@@ -36,11 +126,168 @@ impl<E: JubjubEngine> Hasher<E> {
         aggregated_pubkey: &PublicKey<E>,
         aggregated_commitment: &Point<E, Unknown>,
         message: &[u8],
+        tag: &[u8],
         params: &<R as RescueEngine>::Params,
     ) -> R::Fs {
         let (a, b) =
             Encoder::encode_signature_data(&aggregated_pubkey, &aggregated_commitment, message);
 
-        rescue_hash_to_scalar::<R>(&[], &a, &b, params)
+        rescue_hash_to_scalar::<R>(&tag_prefix(tag), &a, &b, params)
+    }
+
+    // H_non(X', R_1, R_2, m), it is computed only by signers, so unlike
+    // the other hashes it doesn't depend on the hash suite
+    pub fn hash_nonce_coefficient(
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitments: &(Point<E, Unknown>, Point<E, Unknown>),
+        message: &[u8],
+    ) -> E::Fs {
+        let (a, b) = Encoder::encode_nonce_coefficient_data(
+            aggregated_pubkey,
+            aggregated_commitments,
+            message,
+        );
+
+        sha256_hash_to_scalar::<E>(NONCE_COEFFICIENT_DOMAIN, &a, &b)
+    }
+
+    // H_tweak(X', data)
+    pub fn hash_tweak<R: JubjubEngine + RescueEngine>(
+        aggregated_pubkey: &PublicKey<E>,
+        data: &[u8],
+        tag: &[u8],
+        params: &<R as RescueEngine>::Params,
+    ) -> R::Fs {
+        let (a, b) = Encoder::encode_tweak_data(aggregated_pubkey, data);
+
+        rescue_hash_to_scalar::<R>(&tweak_persona(tag), &a, &b, params)
+    }
+}
+
+/// Prefix a non-empty domain tag is turned into, the same as in BIP-340
+/// tagged hashes: SHA-256(tag) || SHA-256(tag). It has fixed length, so
+/// a tag followed by data never reads as another tag followed by other
+/// data. Empty tag has no prefix.
+pub fn tag_prefix(tag: &[u8]) -> Vec<u8> {
+    if tag.is_empty() {
+        return vec![];
     }
+
+    let tag_hash = Sha256::digest(tag);
+
+    [tag_hash.as_slice(), tag_hash.as_slice()].concat()
+}
+
+/// Persona of H_tweak, prefix of the domain tag of the suite followed by
+/// a constant which separates it from H_sig over the same data.
+pub(crate) fn tweak_persona(tag: &[u8]) -> Vec<u8> {
+    let mut persona = tag_prefix(tag);
+    persona.extend_from_slice(TWEAK_DOMAIN);
+
+    persona
+}
+
+/// Persona of H_comm(R_i, T), prefix of the domain tag of the suite
+/// followed by a constant which separates it from H_comm(R_i).
+pub(crate) fn adaptor_commitment_persona(tag: &[u8]) -> Vec<u8> {
+    let mut persona = tag_prefix(tag);
+    persona.extend_from_slice(ADAPTOR_COMMITMENT_DOMAIN);
+
+    persona
+}
+
+/// Persona of H_keyagg, prefix of the domain tag of the suite
+/// followed by a constant.
+pub(crate) fn key_aggregation_persona(tag: &[u8]) -> Vec<u8> {
+    let mut persona = tag_prefix(tag);
+    persona.extend_from_slice(KEY_AGGREGATION_DOMAIN);
+
+    persona
+}
+
+/// Persona of H_sess, prefix of the domain tag of the suite
+/// followed by a constant.
+pub(crate) fn session_persona(tag: &[u8]) -> Vec<u8> {
+    let mut persona = tag_prefix(tag);
+    persona.extend_from_slice(SESSION_DOMAIN);
+
+    persona
+}
+
+/// Persona of H_msg, prefix of the domain tag of the suite
+/// followed by a constant.
+pub(crate) fn session_message_persona(tag: &[u8]) -> Vec<u8> {
+    let mut persona = tag_prefix(tag);
+    persona.extend_from_slice(SESSION_MESSAGE_DOMAIN);
+
+    persona
+}
+
+/// Same as `rescue_hash_to_scalar` but with Poseidon sponge. Lowest
+/// `E::Fs::CAPACITY` bits of the first output are the resulting scalar.
+pub fn poseidon_hash_to_scalar<E: JubjubEngine + PoseidonEngine>(
+    persona: &[u8],
+    a: &[u8],
+    b: &[u8],
+    params: &<E as PoseidonEngine>::Params,
+) -> E::Fs {
+    let hash = poseidon_hash_to_field::<E>(persona, a, b, params);
+
+    let mut hash_bytes = vec![];
+    hash.into_repr()
+        .write_le(&mut hash_bytes)
+        .expect("has serialized hash");
+
+    // keep only bits which fit into the scalar field
+    let capacity = E::Fs::CAPACITY as usize;
+    for (i, byte) in hash_bytes.iter_mut().enumerate() {
+        for bit in 0..8 {
+            if i * 8 + bit >= capacity {
+                *byte &= !(1 << bit);
+            }
+        }
+    }
+
+    let mut repr = <E::Fs as PrimeField>::Repr::default();
+    repr.read_le(&hash_bytes[..])
+        .expect("has deserialized scalar");
+
+    E::Fs::from_repr(repr).expect("truncated hash is a valid scalar")
+}
+
+/// Hashes bytes with Poseidon sponge, preimage is packed into field
+/// elements the same way as `rescue_hash_to_scalar` does it.
+pub fn poseidon_hash_to_field<E: JubjubEngine + PoseidonEngine>(
+    persona: &[u8],
+    a: &[u8],
+    b: &[u8],
+    params: &<E as PoseidonEngine>::Params,
+) -> E::Fr {
+    let mut preimage = persona.to_vec();
+    preimage.extend_from_slice(a);
+    preimage.extend_from_slice(b);
+
+    let bits = multipack::bytes_to_bits_le(&preimage);
+    let packed = multipack::compute_multipacking::<E>(&bits);
+
+    poseidon_hash::<E>(params, &packed)[0]
+}
+
+/// SHA-256 digest, read as a big-endian integer and reduced modulo
+/// the order of the scalar field. It is cheap to recompute in EVM
+/// as `uint256(sha256(persona || a || b)) % order`.
+pub fn sha256_hash_to_scalar<E: JubjubEngine>(persona: &[u8], a: &[u8], b: &[u8]) -> E::Fs {
+    let mut preimage = persona.to_vec();
+    preimage.extend_from_slice(a);
+    preimage.extend_from_slice(b);
+
+    let digest = Sha256::digest(&preimage);
+
+    // to_uniform reduces a 512-bit little-endian integer
+    let mut wide = [0u8; 64];
+    for (dst, src) in wide.iter_mut().zip(digest.iter().rev()) {
+        *dst = *src;
+    }
+
+    E::Fs::to_uniform(&wide)
 }
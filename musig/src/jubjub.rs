@@ -2,13 +2,13 @@ use bellman::PrimeField;
 use franklin_crypto::jubjub::edwards::Point;
 use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, JubjubParams, Unknown};
 
-pub struct JubJubWrapper<E: JubjubEngine> {
-    pub params: <E as JubjubEngine>::Params,
+pub struct JubJubWrapper<'t, E: JubjubEngine> {
+    pub params: &'t <E as JubjubEngine>::Params,
     pub generator: FixedGenerators,
 }
 
-impl<'t, E: JubjubEngine> JubJubWrapper<E> {
-    pub fn new(params: <E as JubjubEngine>::Params, generator: FixedGenerators) -> Self {
+impl<'t, E: JubjubEngine> JubJubWrapper<'t, E> {
+    pub fn new(params: &'t <E as JubjubEngine>::Params, generator: FixedGenerators) -> Self {
         Self { params, generator }
     }
 
@@ -17,14 +17,14 @@ impl<'t, E: JubjubEngine> JubJubWrapper<E> {
         point: &Point<E, Unknown>,
         scalar: S,
     ) -> Point<E, Unknown> {
-        point.mul(scalar, &self.params)
+        point.mul(scalar, self.params)
     }
 
     pub fn mul_by_generator(&self, scalar: E::Fs) -> Point<E, Unknown> {
         Point::from(
             self.params
                 .generator(self.generator)
-                .mul(scalar, &self.params),
+                .mul(scalar, self.params),
         )
     }
 
@@ -32,15 +32,15 @@ impl<'t, E: JubjubEngine> JubJubWrapper<E> {
         Point::from(
             self.params
                 .generator(self.generator)
-                .mul_ct(scalar, &self.params),
+                .mul_ct(scalar, self.params),
         )
     }
 
     pub fn add(&self, p: &Point<E, Unknown>, q: &Point<E, Unknown>) -> Point<E, Unknown> {
-        p.add(&q, &self.params)
+        p.add(&q, self.params)
     }
 
     pub fn is_in_correct_subgroup(&self, point: &Point<E, Unknown>) -> bool {
-        point.mul(E::Fs::char(), &self.params) == Point::zero()
+        point.mul(E::Fs::char(), self.params) == Point::zero()
     }
 }
@@ -1,10 +1,30 @@
 #![allow(non_snake_case)]
+pub mod adaptor;
 pub mod aggregated_pubkey;
+#[cfg(feature = "async")]
+pub mod async_signer;
+pub mod batch;
+pub mod ceremony;
+pub mod circuit;
 pub mod encoder;
 pub mod errors;
+pub mod hash_suite;
 pub mod hasher;
 pub mod jubjub;
+pub mod nonce_pool;
+#[cfg(test)]
+mod properties;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod signature;
 pub mod signer;
 #[cfg(test)]
+mod simulator;
+#[cfg(test)]
 pub mod tests;
+pub mod transport;
+pub mod two_round;
+#[cfg(test)]
+mod vectors;
 pub mod verifier;
+pub mod wire;
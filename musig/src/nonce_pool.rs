@@ -0,0 +1,185 @@
+use crate::aggregated_pubkey::KeyAggregationContext;
+use crate::batch::split_by_message;
+use crate::errors::MusigError;
+use crate::hash_suite::HashSuite;
+use crate::two_round::{MuSigTwoRoundSigner, NonceCommitments};
+use franklin_crypto::eddsa::{PrivateKey, Signature};
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine};
+use franklin_crypto::rescue::RescueEngine;
+use rand::Rng;
+
+/// Signing session which is prepared before the message is known.
+/// Many of them have their nonces fixed at the same time, so it must stay
+/// secure in concurrent sessions, which `MuSigSigner` doesn't, see
+/// `NoncePool`.
+pub trait PoolEntry {
+    type Engine: JubjubEngine;
+
+    fn sign(
+        &mut self,
+        private_key: &PrivateKey<Self::Engine>,
+        message: &[u8],
+    ) -> Result<<Self::Engine as JubjubEngine>::Fs, MusigError>;
+
+    fn receive_signatures(
+        &self,
+        signature_shares: &[<Self::Engine as JubjubEngine>::Fs],
+    ) -> Result<Signature<Self::Engine>, MusigError>;
+}
+
+impl<'a, E: JubjubEngine + RescueEngine, H: HashSuite<E>> PoolEntry
+    for MuSigTwoRoundSigner<'a, E, H>
+{
+    type Engine = E;
+
+    fn sign(&mut self, private_key: &PrivateKey<E>, message: &[u8]) -> Result<E::Fs, MusigError> {
+        MuSigTwoRoundSigner::sign(self, private_key, message)
+    }
+
+    fn receive_signatures(&self, signature_shares: &[E::Fs]) -> Result<Signature<E>, MusigError> {
+        MuSigTwoRoundSigner::receive_signatures(self, signature_shares)
+    }
+}
+
+/// Pool of nonces whose commitments are exchanged ahead of time, so only
+/// the signing round is left when a message arrives. Each message consumes
+/// one entry, all parties must sign it with the entry of the same index.
+/// An entry which has produced a signature share is never used again.
+///
+/// Data received from other parties during pre-processing is indexed by
+/// party first and by entry second.
+///
+/// Only `MuSigTwoRoundSigner` sessions are pooled. Nonces of three-round
+/// `MuSigSigner` sessions would be fixed for all entries before any message
+/// is chosen, and an attacker who opens many such sessions at once can
+/// pick the messages so that their challenges combine into a signature of
+/// another message (Wagner's algorithm, the ROS attack). The nonce
+/// coefficient of the two-round protocol depends on the message, which
+/// prevents it.
+pub struct NoncePool<S> {
+    entries: Vec<S>,
+    used: Vec<bool>,
+}
+
+/// Pool of `MuSigTwoRoundSigner` sessions, pre-processing takes one round
+pub type TwoRoundNoncePool<'a, E, H> = NoncePool<MuSigTwoRoundSigner<'a, E, H>>;
+
+impl<S> NoncePool<S> {
+    fn from_entries(entries: Vec<S>) -> Result<Self, MusigError> {
+        if entries.is_empty() {
+            return Err(MusigError::InvalidBatchSize);
+        }
+
+        let used = vec![false; entries.len()];
+
+        Ok(Self { entries, used })
+    }
+
+    /// Number of entries in the pool, including used ones
+    pub fn size(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Number of entries which are not used yet
+    pub fn remaining(&self) -> usize {
+        self.used.iter().filter(|used| !**used).count()
+    }
+
+    /// Whether the entry of given index has produced a signature share
+    pub fn is_used(&self, index: usize) -> Result<bool, MusigError> {
+        self.used
+            .get(index)
+            .copied()
+            .ok_or(MusigError::InvalidPoolEntry)
+    }
+
+    /// Index of the first entry which is not used yet
+    pub fn next_unused(&self) -> Option<usize> {
+        self.used.iter().position(|used| !used)
+    }
+}
+
+impl<S: PoolEntry> NoncePool<S> {
+    /// Computes signature share for the message with the entry of given
+    /// index. The entry is marked as used once it produced the share.
+    pub fn sign(
+        &mut self,
+        index: usize,
+        private_key: &PrivateKey<S::Engine>,
+        message: &[u8],
+    ) -> Result<<S::Engine as JubjubEngine>::Fs, MusigError> {
+        if self.is_used(index)? {
+            return Err(MusigError::NonceAlreadyUsed);
+        }
+
+        let signature_share = self.entries[index].sign(private_key, message)?;
+        self.used[index] = true;
+
+        Ok(signature_share)
+    }
+
+    /// Receives signature shares produced with the entry of given index
+    /// and returns an aggregated signature.
+    pub fn receive_signatures(
+        &self,
+        index: usize,
+        signature_shares: &[<S::Engine as JubjubEngine>::Fs],
+    ) -> Result<Signature<S::Engine>, MusigError> {
+        self.entries
+            .get(index)
+            .ok_or(MusigError::InvalidPoolEntry)?
+            .receive_signatures(signature_shares)
+    }
+}
+
+impl<'a, E: JubjubEngine + RescueEngine, H: HashSuite<E> + Clone> TwoRoundNoncePool<'a, E, H> {
+    /// Initializes pool of given size for the party
+    pub fn new_with_context(
+        context: &KeyAggregationContext<E>,
+        position: usize,
+        size: usize,
+        params: &'a <E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: H,
+    ) -> Result<Self, MusigError> {
+        let mut entries = vec![];
+        for _ in 0..size {
+            entries.push(MuSigTwoRoundSigner::new_with_context(
+                context,
+                position,
+                params,
+                generator,
+                hash_suite.clone(),
+            )?);
+        }
+
+        Self::from_entries(entries)
+    }
+
+    /// Computes nonce commitments of each entry.
+    /// rng must be a cryptographically secure one.
+    pub fn compute_commitments(
+        &mut self,
+        rng: &mut impl Rng,
+    ) -> Result<Vec<NonceCommitments<E>>, MusigError> {
+        self.entries
+            .iter_mut()
+            .map(|signer| signer.compute_commitments(rng))
+            .collect()
+    }
+
+    /// Receives nonce commitments of all parties, after that
+    /// entries are ready for signing.
+    pub fn receive_commitments(
+        &mut self,
+        commitments: &[Vec<NonceCommitments<E>>],
+    ) -> Result<Vec<NonceCommitments<E>>, MusigError> {
+        let commitments = split_by_message(commitments, self.entries.len())?;
+
+        self.entries
+            .iter_mut()
+            .zip(commitments.iter())
+            .map(|(signer, commitments)| signer.receive_commitments(commitments))
+            .collect()
+    }
+}
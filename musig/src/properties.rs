@@ -0,0 +1,374 @@
+//! Properties of the protocol checked for random party counts, keys,
+//! messages and message orderings.
+
+use crate::aggregated_pubkey::KeyAggregationContext;
+use crate::hash_suite::DefaultHashSuite;
+#[cfg(feature = "serde")]
+use crate::serialization::{SerdePoint, SerdePublicKey, SerdeScalar, SerdeSignature};
+use crate::signature::MusigSignature;
+use crate::signer::MuSigSigner;
+use crate::verifier::MuSigVerifier;
+use crate::wire::{
+    collect_commitments, collect_pre_commitments, collect_signature_shares, Payload, Round,
+    WireMessage,
+};
+use bellman::pairing::bn256::Bn256;
+use franklin_crypto::alt_babyjubjub::{fs::Fs, AltJubjubBn256};
+use franklin_crypto::eddsa::{PrivateKey, PublicKey, Signature};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubParams, Unknown};
+use franklin_crypto::rescue::bn256::Bn256RescueParams;
+use proptest::prelude::*;
+use proptest::sample::Index;
+#[cfg(feature = "serde")]
+use proptest::test_runner::TestCaseError;
+use proptest::test_runner::{Config, TestRunner};
+use rand::{Rng, SeedableRng, XorShiftRng};
+#[cfg(feature = "serde")]
+use serde_crate::{de::DeserializeOwned, Serialize};
+
+const GENERATOR: FixedGenerators = FixedGenerators::SpendingKeyGenerator;
+const SESSION_ID: [u8; 32] = [9u8; 32];
+
+// signing runs are slow, so they get fewer cases than encodings
+const SIGNING_CASES: u32 = 16;
+const ENCODING_CASES: u32 = 256;
+
+fn seed() -> impl Strategy<Value = [u32; 4]> {
+    // xorshift can't be seeded with zeros
+    prop::array::uniform4(1u32..)
+}
+
+fn keys(
+    rng: &mut impl Rng,
+    number_of_parties: usize,
+    jubjub_params: &AltJubjubBn256,
+) -> (Vec<PrivateKey<Bn256>>, Vec<PublicKey<Bn256>>) {
+    (0..number_of_parties)
+        .map(|_| {
+            let private_key = PrivateKey::<Bn256>(rng.gen());
+            let public_key = PublicKey::from_private(&private_key, GENERATOR, jubjub_params);
+            (private_key, public_key)
+        })
+        .unzip()
+}
+
+fn random_point(rng: &mut impl Rng, jubjub_params: &AltJubjubBn256) -> Point<Bn256, Unknown> {
+    let scalar: Fs = rng.gen();
+
+    jubjub_params
+        .generator(GENERATOR)
+        .mul(scalar, jubjub_params)
+        .into()
+}
+
+fn shuffled(rng: &mut impl Rng, messages: &[WireMessage<Bn256>]) -> Vec<WireMessage<Bn256>> {
+    let mut messages = messages.to_vec();
+    rng.shuffle(&mut messages);
+
+    messages
+}
+
+/// Runs all rounds over wire messages, each party gets messages of a round
+/// in its own random order. Returns aggregated commitments and signatures
+/// of all parties.
+fn run_signing(
+    rng: &mut impl Rng,
+    message: &[u8],
+    privkeys: &[PrivateKey<Bn256>],
+    signers: &mut [MuSigSigner<Bn256>],
+) -> (Vec<Point<Bn256, Unknown>>, Vec<Signature<Bn256>>) {
+    let session_id = SESSION_ID;
+    let number_of_parties = signers.len();
+
+    let mut messages = vec![];
+    for (position, signer) in signers.iter_mut().enumerate() {
+        signer.set_session_id(session_id).unwrap();
+        let pre_commitment = signer.compute_precommitment(rng).unwrap();
+        messages.push(
+            WireMessage::new(session_id, position, Payload::PreCommitment(pre_commitment)).unwrap(),
+        );
+    }
+
+    let mut commitment_messages = vec![];
+    for (position, signer) in signers.iter_mut().enumerate() {
+        let received = shuffled(rng, &messages);
+        let pre_commitments =
+            collect_pre_commitments(&received, &session_id, number_of_parties).unwrap();
+        let commitment = signer.receive_precommitments(&pre_commitments).unwrap();
+        commitment_messages
+            .push(WireMessage::new(session_id, position, Payload::Commitment(commitment)).unwrap());
+    }
+
+    let mut aggregated_commitments = vec![];
+    let mut share_messages = vec![];
+    for ((position, signer), private_key) in signers.iter_mut().enumerate().zip(privkeys) {
+        let received = shuffled(rng, &commitment_messages);
+        let commitments = collect_commitments(&received, &session_id, number_of_parties).unwrap();
+        aggregated_commitments.push(signer.receive_commitments(&commitments).unwrap());
+        let signature_share = signer.sign(private_key, message).unwrap();
+        share_messages.push(
+            WireMessage::new(
+                session_id,
+                position,
+                Payload::SignatureShare(signature_share),
+            )
+            .unwrap(),
+        );
+    }
+
+    let signatures = signers
+        .iter()
+        .map(|signer| {
+            let received = shuffled(rng, &share_messages);
+            let signature_shares =
+                collect_signature_shares(&received, &session_id, number_of_parties).unwrap();
+            signer.receive_signatures(&signature_shares).unwrap()
+        })
+        .collect();
+
+    (aggregated_commitments, signatures)
+}
+
+#[test]
+fn prop_parties_agree_and_verifier_rejects_mutations() {
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let strategy = (
+        seed(),
+        1usize..=5,
+        prop::collection::vec(any::<u8>(), 1..=32),
+        0usize..8 * 64,
+        any::<Index>(),
+    );
+
+    let mut runner = TestRunner::new(Config::with_cases(SIGNING_CASES));
+    runner
+        .run(
+            &strategy,
+            |(seed, number_of_parties, message, signature_bit, message_bit)| {
+                let rng = &mut XorShiftRng::from_seed(seed);
+                let (privkeys, pubkeys) = keys(rng, number_of_parties, &jubjub_params);
+                let mut signers: Vec<_> = (0..number_of_parties)
+                    .map(|position| {
+                        MuSigSigner::new(&pubkeys, position, &jubjub_params, GENERATOR, hash_suite)
+                            .unwrap()
+                    })
+                    .collect();
+
+                let (aggregated_commitments, signatures) =
+                    run_signing(rng, &message, &privkeys, &mut signers);
+
+                for (aggregated_commitment, signature) in
+                    aggregated_commitments.iter().zip(signatures.iter())
+                {
+                    prop_assert!(*aggregated_commitment == aggregated_commitments[0]);
+                    prop_assert!(signature.r == signatures[0].r && signature.s == signatures[0].s);
+                    prop_assert!(signature.r == *aggregated_commitment);
+                }
+
+                let verify = |message: &[u8], signature: &Signature<Bn256>| {
+                    MuSigVerifier::verify_in_session(
+                        message,
+                        &SESSION_ID,
+                        &pubkeys,
+                        signature,
+                        &jubjub_params,
+                        GENERATOR,
+                        &hash_suite,
+                    )
+                    .unwrap()
+                };
+                prop_assert!(verify(&message, &signatures[0]));
+
+                // a flipped bit of the signature either breaks its
+                // encoding or the signature
+                let mut encoded = MusigSignature::<Bn256> {
+                    r: signatures[0].r,
+                    s: signatures[0].s,
+                }
+                .to_bytes();
+                encoded[signature_bit / 8] ^= 1 << (signature_bit % 8);
+                if let Ok(mutated) = MusigSignature::from_bytes(&encoded, &jubjub_params) {
+                    prop_assert!(!verify(&message, &mutated.into()));
+                }
+
+                let mut mutated_message = message.clone();
+                let bit = message_bit.index(8 * message.len());
+                mutated_message[bit / 8] ^= 1 << (bit % 8);
+                prop_assert!(!verify(&mutated_message, &signatures[0]));
+
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn prop_key_aggregation_follows_key_order() {
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let mut runner = TestRunner::new(Config::with_cases(SIGNING_CASES));
+    runner
+        .run(&(seed(), 1usize..=6), |(seed, number_of_parties)| {
+            let rng = &mut XorShiftRng::from_seed(seed);
+            let (_, pubkeys) = keys(rng, number_of_parties, &jubjub_params);
+
+            let aggregate = |pubkeys: &[PublicKey<Bn256>]| {
+                KeyAggregationContext::new(pubkeys, &jubjub_params, &hash_suite).unwrap()
+            };
+
+            // keys aren't sorted, L is the list in the given order, so
+            // the aggregated key and a_i depend on positions of parties
+            let mut sorted = pubkeys.clone();
+            let encoding = |pubkey: &PublicKey<Bn256>| {
+                let mut bytes = vec![];
+                pubkey.write(&mut bytes).unwrap();
+                bytes
+            };
+            sorted.sort_by_key(encoding);
+
+            let unsorted_context = aggregate(&pubkeys);
+            let sorted_context = aggregate(&sorted);
+
+            prop_assert!(
+                unsorted_context.aggregated_pubkey.0 == aggregate(&pubkeys).aggregated_pubkey.0
+            );
+            prop_assert_eq!(
+                unsorted_context.aggregated_pubkey.0 == sorted_context.aggregated_pubkey.0,
+                pubkeys.iter().map(encoding).eq(sorted.iter().map(encoding))
+            );
+
+            // a single key is its own aggregated key
+            if number_of_parties == 1 {
+                prop_assert!(unsorted_context.aggregated_pubkey.0 == pubkeys[0].0);
+            }
+
+            Ok(())
+        })
+        .unwrap();
+}
+
+fn wire_message(
+    rng: &mut impl Rng,
+    session_id: [u8; 32],
+    sender: usize,
+    round: Round,
+    jubjub_params: &AltJubjubBn256,
+) -> WireMessage<Bn256> {
+    let payload = match round {
+        Round::PreCommitment => {
+            let mut pre_commitment = vec![0u8; 32];
+            rng.fill_bytes(&mut pre_commitment);
+            Payload::PreCommitment(pre_commitment)
+        }
+        Round::Commitment => Payload::Commitment(random_point(rng, jubjub_params)),
+        Round::SignatureShare => Payload::SignatureShare(rng.gen()),
+        Round::Abort => Payload::Abort,
+    };
+
+    WireMessage::new(session_id, sender, payload).unwrap()
+}
+
+// same as for wire messages: values round trip through bincode and JSON,
+// and whatever decodes from a mutated encoding encodes back to it
+#[cfg(feature = "serde")]
+fn check_serde_encoding<T: Serialize + DeserializeOwned + PartialEq>(
+    value: &T,
+    bit_index: &Index,
+) -> Result<(), TestCaseError> {
+    let encoded = bincode::serialize(value).unwrap();
+    let decoded: T = bincode::deserialize(&encoded).unwrap();
+    prop_assert!(decoded == *value);
+    prop_assert_eq!(bincode::serialize(&decoded).unwrap(), encoded.clone());
+
+    let json = serde_json::to_string(value).unwrap();
+    prop_assert!(serde_json::from_str::<T>(&json).unwrap() == *value);
+
+    let mut mutated = encoded;
+    let bit = bit_index.index(8 * mutated.len());
+    mutated[bit / 8] ^= 1 << (bit % 8);
+    if let Ok(decoded) = bincode::deserialize::<T>(&mutated) {
+        prop_assert_eq!(bincode::serialize(&decoded).unwrap(), mutated);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn prop_decoding_then_encoding_is_identity() {
+    let jubjub_params = AltJubjubBn256::new();
+    let rounds = [
+        Round::PreCommitment,
+        Round::Commitment,
+        Round::SignatureShare,
+        Round::Abort,
+    ];
+
+    let strategy = (
+        seed(),
+        any::<[u8; 32]>(),
+        0usize..=u32::MAX as usize,
+        any::<Index>(),
+        any::<Index>(),
+    );
+
+    let mut runner = TestRunner::new(Config::with_cases(ENCODING_CASES));
+    runner
+        .run(&strategy, |(seed, session_id, sender, round, bit_index)| {
+            let rng = &mut XorShiftRng::from_seed(seed);
+
+            let message =
+                wire_message(rng, session_id, sender, *round.get(&rounds), &jubjub_params);
+            let encoded = message.to_bytes();
+            let decoded = WireMessage::from_bytes(&encoded, &jubjub_params).unwrap();
+            prop_assert!(decoded == message);
+            prop_assert_eq!(decoded.to_bytes(), encoded.clone());
+
+            // decoding is strict, whatever decodes encodes back to the input
+            let mut mutated = encoded;
+            let bit = bit_index.index(8 * mutated.len());
+            mutated[bit / 8] ^= 1 << (bit % 8);
+            if let Ok(decoded) = WireMessage::from_bytes(&mutated, &jubjub_params) {
+                prop_assert_eq!(decoded.to_bytes(), mutated);
+            }
+
+            let signature = MusigSignature::<Bn256> {
+                r: random_point(rng, &jubjub_params),
+                s: rng.gen(),
+            };
+            let encoded = signature.to_bytes();
+            let decoded = MusigSignature::from_bytes(&encoded, &jubjub_params).unwrap();
+            prop_assert!(decoded == signature);
+            prop_assert_eq!(decoded.to_bytes(), encoded.clone());
+
+            let mut mutated = encoded;
+            let bit = bit_index.index(8 * mutated.len());
+            mutated[bit / 8] ^= 1 << (bit % 8);
+            if let Ok(decoded) = MusigSignature::from_bytes(&mutated, &jubjub_params) {
+                prop_assert_eq!(decoded.to_bytes(), mutated);
+            }
+
+            #[cfg(feature = "serde")]
+            {
+                let point = random_point(rng, &jubjub_params);
+                check_serde_encoding(&SerdePoint::<Bn256>(point), &bit_index)?;
+                check_serde_encoding(&SerdePublicKey::<Bn256>(PublicKey(point)), &bit_index)?;
+                check_serde_encoding(&SerdeScalar::<Bn256>::new(rng.gen()), &bit_index)?;
+                check_serde_encoding(
+                    &SerdeSignature::<Bn256>(Signature {
+                        r: random_point(rng, &jubjub_params),
+                        s: rng.gen(),
+                    }),
+                    &bit_index,
+                )?;
+            }
+
+            Ok(())
+        })
+        .unwrap();
+}
@@ -0,0 +1,407 @@
+//! Serde wrappers for protocol values, enabled with the `serde` feature.
+//!
+//! Values are encoded the same way as the WASM bindings do it: points and
+//! public keys as 32-byte compressed points, scalars as 32-byte big-endian
+//! integers and signatures as compressed `R` followed by little-endian `s`.
+//! Human-readable formats get a hex string, binary ones raw bytes.
+//! Decoding is strict, points must be canonically encoded and in the prime
+//! order subgroup, and scalars must be less than the group order.
+//! Signatures are decoded with `MusigSignature::from_bytes`.
+use crate::encoder::{read_canonical_point, STANDARD_ENCODING_LENGTH};
+use crate::signature::{MusigSignature, SIGNATURE_ENCODING_LENGTH};
+use bellman::pairing::bn256::Bn256;
+use bellman::{PrimeField, PrimeFieldRepr};
+use franklin_crypto::alt_babyjubjub::AltJubjubBn256;
+use franklin_crypto::eddsa::{PublicKey, Signature};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{JubjubEngine, Unknown};
+use once_cell::sync::Lazy;
+use serde_crate::de::{self, Visitor};
+use serde_crate::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+static BN256_JUBJUB_PARAMS: Lazy<AltJubjubBn256> = Lazy::new(AltJubjubBn256::new);
+
+/// Engine whose Jubjub parameters are available without an instance,
+/// points can't be decoded without them.
+pub trait SerdeEngine: JubjubEngine {
+    fn jubjub_params() -> &'static <Self as JubjubEngine>::Params;
+}
+
+impl SerdeEngine for Bn256 {
+    fn jubjub_params() -> &'static AltJubjubBn256 {
+        &BN256_JUBJUB_PARAMS
+    }
+}
+
+/// Nonce commitment or any other point of the protocol
+#[derive(Clone)]
+pub struct SerdePoint<E: JubjubEngine>(pub Point<E, Unknown>);
+
+/// Public key of a party or an aggregated public key
+#[derive(Clone)]
+pub struct SerdePublicKey<E: JubjubEngine>(pub PublicKey<E>);
+
+/// Signature share or any other scalar of the protocol
+#[derive(Clone)]
+pub struct SerdeScalar<E: JubjubEngine>(pub E::Fs);
+
+/// Pre-commitment, 32-byte output of H_comm
+#[derive(Clone, Debug, PartialEq)]
+pub struct SerdePreCommitment(pub Vec<u8>);
+
+/// Aggregated signature `(R, s)`
+#[derive(Clone)]
+pub struct SerdeSignature<E: JubjubEngine>(pub Signature<E>);
+
+impl<E: JubjubEngine> From<Point<E, Unknown>> for SerdePoint<E> {
+    fn from(point: Point<E, Unknown>) -> Self {
+        Self(point)
+    }
+}
+
+impl<E: JubjubEngine> From<PublicKey<E>> for SerdePublicKey<E> {
+    fn from(pubkey: PublicKey<E>) -> Self {
+        Self(pubkey)
+    }
+}
+
+impl<E: JubjubEngine> From<Signature<E>> for SerdeSignature<E> {
+    fn from(signature: Signature<E>) -> Self {
+        Self(signature)
+    }
+}
+
+impl From<Vec<u8>> for SerdePreCommitment {
+    fn from(pre_commitment: Vec<u8>) -> Self {
+        Self(pre_commitment)
+    }
+}
+
+impl<E: JubjubEngine> SerdeScalar<E> {
+    pub fn new(scalar: E::Fs) -> Self {
+        Self(scalar)
+    }
+}
+
+impl<E: JubjubEngine> PartialEq for SerdePoint<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<E: JubjubEngine> PartialEq for SerdePublicKey<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 .0 == other.0 .0
+    }
+}
+
+impl<E: JubjubEngine> PartialEq for SerdeScalar<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<E: JubjubEngine> PartialEq for SerdeSignature<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.r == other.0.r && self.0.s == other.0.s
+    }
+}
+
+impl<E: JubjubEngine> Serialize for SerdePoint<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = vec![];
+        self.0.write(&mut buf).expect("has serialized point");
+
+        serialize_bytes(&buf, serializer)
+    }
+}
+
+impl<'de, E: SerdeEngine> Deserialize<'de> for SerdePoint<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_fixed_bytes(deserializer, STANDARD_ENCODING_LENGTH)?;
+
+        read_point(&bytes).map(Self)
+    }
+}
+
+impl<E: JubjubEngine> Serialize for SerdePublicKey<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerdePoint(self.0 .0).serialize(serializer)
+    }
+}
+
+impl<'de, E: SerdeEngine> Deserialize<'de> for SerdePublicKey<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let point = SerdePoint::<E>::deserialize(deserializer)?;
+
+        Ok(Self(PublicKey(point.0)))
+    }
+}
+
+impl<E: JubjubEngine> Serialize for SerdeScalar<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut buf = vec![];
+        self.0
+            .into_repr()
+            .write_be(&mut buf)
+            .expect("has serialized scalar");
+
+        serialize_bytes(&buf, serializer)
+    }
+}
+
+impl<'de, E: JubjubEngine> Deserialize<'de> for SerdeScalar<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_fixed_bytes(deserializer, STANDARD_ENCODING_LENGTH)?;
+
+        let mut repr = <E::Fs as PrimeField>::Repr::default();
+        repr.read_be(&bytes[..]).map_err(D::Error::custom)?;
+
+        E::Fs::from_repr(repr)
+            .map(Self)
+            .map_err(|_| de::Error::custom("scalar is not less than the group order"))
+    }
+}
+
+impl Serialize for SerdePreCommitment {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_bytes(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SerdePreCommitment {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_fixed_bytes(deserializer, STANDARD_ENCODING_LENGTH).map(Self)
+    }
+}
+
+impl<E: JubjubEngine> Serialize for SerdeSignature<E> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let encoded = MusigSignature::from(self.0.clone()).to_bytes();
+
+        serialize_bytes(&encoded, serializer)
+    }
+}
+
+impl<'de, E: SerdeEngine> Deserialize<'de> for SerdeSignature<E> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let bytes = deserialize_fixed_bytes(deserializer, SIGNATURE_ENCODING_LENGTH)?;
+
+        let signature =
+            MusigSignature::from_bytes(&bytes, E::jubjub_params()).map_err(D::Error::custom)?;
+
+        Ok(Self(signature.into()))
+    }
+}
+
+fn read_point<E: SerdeEngine, Err: de::Error>(bytes: &[u8]) -> Result<Point<E, Unknown>, Err> {
+    let params = E::jubjub_params();
+
+    let point = read_canonical_point(bytes, params)
+        .ok_or_else(|| Err::custom("point is not canonically encoded or not on the curve"))?;
+
+    // check that point is in correct subgroup
+    if point.mul(E::Fs::char(), params) != Point::zero() {
+        return Err(Err::custom("point is not in the prime order subgroup"));
+    }
+
+    Ok(point)
+}
+
+fn serialize_bytes<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.serialize_str(&hex::encode(bytes))
+    } else {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+fn deserialize_bytes<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    if deserializer.is_human_readable() {
+        let encoded = String::deserialize(deserializer)?;
+
+        hex::decode(&encoded).map_err(de::Error::custom)
+    } else {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+}
+
+fn deserialize_fixed_bytes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+    len: usize,
+) -> Result<Vec<u8>, D::Error> {
+    let bytes = deserialize_bytes(deserializer)?;
+    if bytes.len() != len {
+        let expected = format!("{} bytes", len);
+        return Err(de::Error::invalid_length(bytes.len(), &expected.as_str()));
+    }
+
+    Ok(bytes)
+}
+
+struct BytesVisitor;
+
+impl<'de> Visitor<'de> for BytesVisitor {
+    type Value = Vec<u8>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("byte array")
+    }
+
+    fn visit_bytes<Err: de::Error>(self, v: &[u8]) -> Result<Self::Value, Err> {
+        Ok(v.to_vec())
+    }
+
+    fn visit_byte_buf<Err: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, Err> {
+        Ok(v)
+    }
+
+    fn visit_seq<A: de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut bytes = vec![];
+        while let Some(byte) = seq.next_element()? {
+            bytes.push(byte);
+        }
+
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SerdePoint, SerdePreCommitment, SerdePublicKey, SerdeScalar, SerdeSignature};
+    use crate::encoder::STANDARD_ENCODING_LENGTH;
+    use crate::hash_suite::DefaultHashSuite;
+    use crate::signature::MusigSignature;
+    use crate::tests::{
+        musig_multi_party_test_runner, musig_test_bn256_setup, random_message_hash,
+    };
+    use bellman::pairing::bn256::Bn256;
+    use bellman::{Field, PrimeField, PrimeFieldRepr};
+    use franklin_crypto::alt_babyjubjub::{fs::Fs, AltJubjubBn256};
+    use franklin_crypto::jubjub::FixedGenerators;
+    use franklin_crypto::rescue::bn256::Bn256RescueParams;
+    use rand::{Rng, SeedableRng, XorShiftRng};
+
+    #[test]
+    fn test_serde_round_trip() {
+        let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+        let jubjub_params = AltJubjubBn256::new();
+        let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+        let generator = FixedGenerators::SpendingKeyGenerator;
+
+        let hash_suite = DefaultHashSuite::new(&rescue_params);
+        let message = random_message_hash(rng);
+
+        let (privkeys, pubkeys, mut signers) =
+            musig_test_bn256_setup(2, &jubjub_params, hash_suite).unwrap();
+        let signature = musig_multi_party_test_runner(
+            rng,
+            &message,
+            &pubkeys,
+            &privkeys,
+            &mut signers,
+            &jubjub_params,
+            &hash_suite,
+            generator,
+        )
+        .unwrap();
+
+        let pubkey = SerdePublicKey(pubkeys[0].clone());
+        let point = SerdePoint(signature.r);
+        let scalar = SerdeScalar::<Bn256>(rng.gen());
+        let pre_commitment = SerdePreCommitment(vec![7; STANDARD_ENCODING_LENGTH]);
+        let signature = SerdeSignature(signature);
+
+        // hex strings in human-readable formats
+        let json = serde_json::to_string(&pubkey).unwrap();
+        let mut expected_pubkey = vec![];
+        pubkeys[0].write(&mut expected_pubkey).unwrap();
+        assert_eq!(json, format!("\"{}\"", hex::encode(&expected_pubkey)));
+        assert!(serde_json::from_str::<SerdePublicKey<Bn256>>(&json).unwrap() == pubkey);
+
+        let json = serde_json::to_string(&point).unwrap();
+        assert!(serde_json::from_str::<SerdePoint<Bn256>>(&json).unwrap() == point);
+        let json = serde_json::to_string(&scalar).unwrap();
+        assert!(serde_json::from_str::<SerdeScalar<Bn256>>(&json).unwrap() == scalar);
+        let json = serde_json::to_string(&pre_commitment).unwrap();
+        assert_eq!(
+            json,
+            format!("\"{}\"", "07".repeat(STANDARD_ENCODING_LENGTH))
+        );
+        assert_eq!(
+            serde_json::from_str::<SerdePreCommitment>(&json).unwrap(),
+            pre_commitment
+        );
+        assert!(serde_json::from_str::<SerdePreCommitment>("\"010203\"").is_err());
+        let json = serde_json::to_string(&signature).unwrap();
+        assert!(serde_json::from_str::<SerdeSignature<Bn256>>(&json).unwrap() == signature);
+
+        // raw bytes in binary formats
+        let encoded = bincode::serialize(&signature).unwrap();
+        assert_eq!(encoded.len(), 8 + 64);
+        assert!(bincode::deserialize::<SerdeSignature<Bn256>>(&encoded).unwrap() == signature);
+        let encoded = bincode::serialize(&scalar).unwrap();
+        assert!(bincode::deserialize::<SerdeScalar<Bn256>>(&encoded).unwrap() == scalar);
+        let encoded = bincode::serialize(&point).unwrap();
+        assert!(bincode::deserialize::<SerdePoint<Bn256>>(&encoded).unwrap() == point);
+
+        // scalar equal to the group order is not canonical
+        let mut order = vec![];
+        Fs::char().write_be(&mut order).unwrap();
+        let json = format!("\"{}\"", hex::encode(&order));
+        assert!(serde_json::from_str::<SerdeScalar<Bn256>>(&json).is_err());
+
+        // wrong length
+        let json = format!("\"{}\"", hex::encode(&order[1..]));
+        assert!(serde_json::from_str::<SerdeScalar<Bn256>>(&json).is_err());
+
+        // y coordinate which has no point on the curve
+        let mut invalid_point = None;
+        for y in 2u64.. {
+            let mut encoded = vec![];
+            <Bn256 as bellman::pairing::Engine>::Fr::from_str(&y.to_string())
+                .unwrap()
+                .into_repr()
+                .write_le(&mut encoded)
+                .unwrap();
+            let json = format!("\"{}\"", hex::encode(&encoded));
+            if serde_json::from_str::<SerdePoint<Bn256>>(&json).is_err() {
+                invalid_point = Some(json);
+                break;
+            }
+        }
+        assert!(invalid_point.is_some());
+
+        // (0, -1) is on the curve but has order 2
+        let mut low_order_point = vec![];
+        let mut minus_one = <Bn256 as bellman::pairing::Engine>::Fr::one();
+        minus_one.negate();
+        minus_one
+            .into_repr()
+            .write_le(&mut low_order_point)
+            .unwrap();
+        let json = format!("\"{}\"", hex::encode(&low_order_point));
+        assert!(serde_json::from_str::<SerdePoint<Bn256>>(&json).is_err());
+        assert!(serde_json::from_str::<SerdePublicKey<Bn256>>(&json).is_err());
+
+        // identity point with the sign bit set decodes, but isn't canonical
+        let mut identity = vec![];
+        <Bn256 as bellman::pairing::Engine>::Fr::one()
+            .into_repr()
+            .write_le(&mut identity)
+            .unwrap();
+        let json = format!("\"{}\"", hex::encode(&identity));
+        assert!(serde_json::from_str::<SerdePoint<Bn256>>(&json).is_ok());
+        identity[STANDARD_ENCODING_LENGTH - 1] |= 0x80;
+        let json = format!("\"{}\"", hex::encode(&identity));
+        assert!(serde_json::from_str::<SerdePoint<Bn256>>(&json).is_err());
+
+        // signatures are decoded as strictly as `MusigSignature`
+        let mut encoded = MusigSignature::from(signature.0.clone()).to_bytes();
+        encoded[..STANDARD_ENCODING_LENGTH].copy_from_slice(&low_order_point);
+        let json = format!("\"{}\"", hex::encode(&encoded));
+        assert!(serde_json::from_str::<SerdeSignature<Bn256>>(&json).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use crate::encoder::{read_canonical_point, STANDARD_ENCODING_LENGTH};
+use crate::errors::MusigError;
+use bellman::{PrimeField, PrimeFieldRepr};
+use franklin_crypto::eddsa::Signature;
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{JubjubEngine, Unknown};
+
+/// Length of an encoded signature, R || s
+pub const SIGNATURE_ENCODING_LENGTH: usize = 2 * STANDARD_ENCODING_LENGTH;
+
+/// Aggregated signature σ = (R, s) with a canonical encoding: compressed R
+/// followed by little-endian s, 64 bytes in total. This is the layout the
+/// WASM bindings use.
+///
+/// Decoding is strict, each signature has exactly one encoding which is
+/// accepted. It rejects input of any other length, s which is not less
+/// than the group order, and R which is off the curve, not in the prime
+/// order subgroup or not encoded canonically.
+#[derive(Clone)]
+pub struct MusigSignature<E: JubjubEngine> {
+    pub r: Point<E, Unknown>,
+    pub s: E::Fs,
+}
+
+impl<E: JubjubEngine> MusigSignature<E> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut encoded = vec![0u8; SIGNATURE_ENCODING_LENGTH];
+        self.r
+            .write(&mut encoded[..STANDARD_ENCODING_LENGTH])
+            .expect("has serialized point");
+        self.s
+            .into_repr()
+            .write_le(&mut encoded[STANDARD_ENCODING_LENGTH..])
+            .expect("has serialized scalar");
+
+        encoded
+    }
+
+    pub fn from_bytes(
+        input: &[u8],
+        jubjub_params: &<E as JubjubEngine>::Params,
+    ) -> Result<Self, MusigError> {
+        if input.len() != SIGNATURE_ENCODING_LENGTH {
+            return Err(MusigError::InvalidSignatureLength);
+        }
+        let (r_bytes, s_bytes) = input.split_at(STANDARD_ENCODING_LENGTH);
+
+        let r: Point<E, Unknown> = read_canonical_point(r_bytes, jubjub_params)
+            .ok_or(MusigError::InvalidSignaturePoint)?;
+
+        // check that R is in correct subgroup
+        if r.mul(E::Fs::char(), jubjub_params) != Point::zero() {
+            return Err(MusigError::InvalidSignaturePoint);
+        }
+
+        let mut repr = <E::Fs as PrimeField>::Repr::default();
+        repr.read_le(s_bytes)
+            .map_err(|_| MusigError::InvalidSignatureScalar)?;
+        // rejects s >= order
+        let s = E::Fs::from_repr(repr).map_err(|_| MusigError::InvalidSignatureScalar)?;
+
+        Ok(Self { r, s })
+    }
+}
+
+impl<E: JubjubEngine> PartialEq for MusigSignature<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.r == other.r && self.s == other.s
+    }
+}
+
+impl<E: JubjubEngine> From<Signature<E>> for MusigSignature<E> {
+    fn from(signature: Signature<E>) -> Self {
+        Self {
+            r: signature.r,
+            s: signature.s,
+        }
+    }
+}
+
+impl<E: JubjubEngine> From<MusigSignature<E>> for Signature<E> {
+    fn from(signature: MusigSignature<E>) -> Self {
+        Signature {
+            r: signature.r,
+            s: signature.s,
+        }
+    }
+}
@@ -1,16 +1,103 @@
-use crate::aggregated_pubkey::AggregatedPublicKey;
+use crate::adaptor::PreSignature;
+use crate::aggregated_pubkey::KeyAggregationContext;
 use crate::errors::MusigError;
-use crate::hasher::Hasher;
+use crate::hash_suite::{DefaultHashSuite, HashSuite};
 use crate::jubjub::JubJubWrapper;
 use crate::verifier::MuSigVerifier;
-use bellman::pairing::ff::Field;
+use crate::wire::{SessionId, SESSION_ID_LENGTH};
+use bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
 use franklin_crypto::eddsa::{PrivateKey, PublicKey, Signature};
 use franklin_crypto::jubjub::edwards::Point;
 use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, Unknown};
 use franklin_crypto::rescue::RescueEngine;
 use rand::{Rand, Rng};
-/// MuSig signer party holds required data for protocol run
-pub struct MuSigSigner<E: JubjubEngine + RescueEngine> {
+use std::io::Read;
+
+const SIGNER_STATE_VERSION: u8 = 2;
+
+// flags of exported signer state, each one marks presence of
+// an optional part of the state
+const STATE_HAS_NONCE: u8 = 1;
+const STATE_HAS_NONCE_COMMITMENT: u8 = 1 << 1;
+const STATE_HAS_PRE_COMMITMENTS: u8 = 1 << 2;
+const STATE_HAS_COMMITMENTS: u8 = 1 << 3;
+const STATE_HAS_CHALLENGE: u8 = 1 << 4;
+const STATE_NONCE_USED: u8 = 1 << 5;
+const STATE_HAS_ADAPTOR_POINT: u8 = 1 << 6;
+const STATE_HAS_TWEAK: u8 = 1 << 7;
+// extended flags
+const STATE_HAS_SESSION_ID: u8 = 1;
+
+/// Nonce commitments R_i of a party which have produced signature shares.
+/// An exported state before signing still holds its nonce, so this list
+/// must be kept persistently next to exported states and passed to
+/// `import_state`, otherwise a restored signer could sign a second
+/// message with the same nonce and reveal the private key.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsedNonces {
+    commitments: Vec<Vec<u8>>,
+}
+
+impl UsedNonces {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records nonce commitment of a signer which has produced its share
+    pub fn insert<E: JubjubEngine>(&mut self, commitment: &Point<E, Unknown>) {
+        let encoded = encode_point(commitment);
+        if !self.commitments.contains(&encoded) {
+            self.commitments.push(encoded);
+        }
+    }
+
+    pub fn contains<E: JubjubEngine>(&self, commitment: &Point<E, Unknown>) -> bool {
+        self.commitments.contains(&encode_point(commitment))
+    }
+
+    /// Number of recorded nonce commitments
+    pub fn len(&self) -> usize {
+        self.commitments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commitments.is_empty()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        for commitment in self.commitments.iter() {
+            buf.extend_from_slice(&(commitment.len() as u32).to_be_bytes());
+            buf.extend_from_slice(commitment);
+        }
+
+        buf
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MusigError> {
+        let mut reader = bytes;
+        let mut commitments = vec![];
+        while !reader.is_empty() {
+            let len = read_u32(&mut reader)? as usize;
+            if reader.len() < len {
+                return Err(MusigError::InvalidSignerState);
+            }
+            commitments.push(reader[..len].to_vec());
+            reader = &reader[len..];
+        }
+
+        Ok(Self { commitments })
+    }
+}
+
+/// MuSig signer party holds required data for protocol run.
+/// A party shouldn't run many sessions with nonces fixed before the
+/// messages are chosen, see `nonce_pool::NoncePool`.
+pub struct MuSigSigner<
+    'a,
+    E: JubjubEngine + RescueEngine,
+    H: HashSuite<E> = DefaultHashSuite<'a, E>,
+> {
     position: usize,
     nonce: Option<E::Fs>,
     nonce_commitment: Option<Point<E, Unknown>>,
@@ -18,29 +105,52 @@ pub struct MuSigSigner<E: JubjubEngine + RescueEngine> {
     aggregated_commitment: Option<Point<E, Unknown>>,
     aggregated_pubkey: PublicKey<E>,
     a_values: Vec<E::Fs>,
+    tweak: E::Fs,
     pre_commitments: Option<Vec<Vec<u8>>>,
     signature: E::Fs,
     challenge: Option<E::Fs>,
+    adaptor_point: Option<Point<E, Unknown>>,
+    nonce_used: bool,
+    session_id: Option<SessionId>,
+    key_aggregation_hash: Vec<u8>,
     pubkeys: Vec<PublicKey<E>>,
-    jubjub_wrapper: JubJubWrapper<E>,
+    jubjub_wrapper: JubJubWrapper<'a, E>,
+    hash_suite: H,
 }
 
-impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
+impl<'a, E: JubjubEngine + RescueEngine, H: HashSuite<E>> MuSigSigner<'a, E, H> {
     /// Initializes new party
-    /// All pubkeys required for computation of aggregated public key
+    /// All pubkeys required for computation of aggregated public key.
+    /// Jubjub parameters are borrowed, so a single instance can be
+    /// shared between any number of signers.
+    /// All parties and verifiers must use the same hash suite.
     pub fn new(
         pubkeys: &[PublicKey<E>],
         position: usize,
-        params: <E as JubjubEngine>::Params,
+        params: &'a <E as JubjubEngine>::Params,
         generator: FixedGenerators,
+        hash_suite: H,
     ) -> Result<Self, MusigError> {
-        let jubjub_wrapper = JubJubWrapper::new(params, generator);
-
         // we need each a_i values for signature share verification
-        let (aggregated_pubkey, a_values) =
-            AggregatedPublicKey::compute_from_pubkeys(pubkeys, &jubjub_wrapper.params)?;
+        let context = KeyAggregationContext::new(pubkeys, params, &hash_suite)?;
 
-        if position > pubkeys.len() - 1 {
+        Self::new_with_context(&context, position, params, generator, hash_suite)
+    }
+
+    /// Initializes new party from already computed key aggregation,
+    /// so parallel sessions of the same group don't repeat it. Context
+    /// must be computed with the same hash suite. If the context is
+    /// tweaked, signatures are produced for the tweaked key.
+    pub fn new_with_context(
+        context: &KeyAggregationContext<E>,
+        position: usize,
+        params: &'a <E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: H,
+    ) -> Result<Self, MusigError> {
+        let jubjub_wrapper = JubJubWrapper::new(params, generator);
+
+        if position >= context.pubkeys.len() {
             return Err(MusigError::InvalidParticipantPosition);
         }
 
@@ -50,16 +160,85 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
             nonce_commitment: None,
             nonce_commitments: vec![],
             aggregated_commitment: None,
-            aggregated_pubkey,
-            a_values,
+            aggregated_pubkey: context.aggregated_pubkey.clone(),
+            a_values: context.a_values.clone(),
+            tweak: context.tweak,
             pre_commitments: None,
             signature: E::Fs::zero(),
             challenge: None,
-            pubkeys: pubkeys.to_vec(),
+            adaptor_point: None,
+            nonce_used: false,
+            session_id: None,
+            key_aggregation_hash: context.key_aggregation_hash(&hash_suite),
+            pubkeys: context.pubkeys.clone(),
             jubjub_wrapper,
+            hash_suite,
         })
     }
 
+    /// Position of this party in the list of public keys
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Public keys of all parties
+    pub fn pubkeys(&self) -> &[PublicKey<E>] {
+        &self.pubkeys
+    }
+
+    /// Commitment R_i of the nonce of this run, if it is generated.
+    /// Once the signer has produced its share the commitment must be
+    /// recorded in `UsedNonces`.
+    pub fn nonce_commitment(&self) -> Option<&Point<E, Unknown>> {
+        self.nonce_commitment.as_ref()
+    }
+
+    /// Jubjub parameters borrowed by this party
+    pub fn jubjub_params(&self) -> &'a <E as JubjubEngine>::Params {
+        self.jubjub_wrapper.params
+    }
+
+    /// Binds this run to a session. Pre-commitments are then computed as
+    /// t_i = H_sess(sid, i, H_keyagg(L, X'), H_comm(R_i)), so commitments
+    /// of another session, of another party or of another group don't
+    /// match them. All parties must set the same session id before
+    /// computing pre-commitments, and it must not be reused.
+    pub fn set_session_id(&mut self, session_id: SessionId) -> Result<(), MusigError> {
+        if self.nonce_commitment.is_some() {
+            return Err(MusigError::SessionAlreadyStarted);
+        }
+
+        self.session_id = Some(session_id);
+
+        Ok(())
+    }
+
+    /// Session this run is bound to, if any
+    pub fn session_id(&self) -> Option<&SessionId> {
+        self.session_id.as_ref()
+    }
+
+    /// Binds this run to the adaptor point T = t * G of an adaptor
+    /// signature. Pre-commitments are then computed over H_comm(R_i, T),
+    /// so all parties commit to the same T before nonces are revealed,
+    /// and `sign_with_adaptor` refuses any other point. All parties must
+    /// set it before computing pre-commitments.
+    pub fn set_adaptor_point(
+        &mut self,
+        adaptor_point: Point<E, Unknown>,
+    ) -> Result<(), MusigError> {
+        if self.nonce_commitment.is_some() {
+            return Err(MusigError::SessionAlreadyStarted);
+        }
+        if !self.jubjub_wrapper.is_in_correct_subgroup(&adaptor_point) {
+            return Err(MusigError::AdaptorPointIsNotInCorrectSubgroup);
+        }
+
+        self.adaptor_point = Some(adaptor_point);
+
+        Ok(())
+    }
+
     /// Pre-commitment is hash of serialized point which computed
     /// by multiplication of a randomly generated scalar with generator.
     /// rng must be a cryptographically secure one.
@@ -70,7 +249,7 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
         let R = self.jubjub_wrapper.mul_by_generator_ct(r);
 
         // t = H_comm(R)
-        let pre_commitment = Hasher::hash_commitment(&R);
+        let pre_commitment = self.hash_commitment(self.position, &R);
 
         self.nonce = Some(r);
         self.nonce_commitment = Some(R);
@@ -78,6 +257,32 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
         Ok(pre_commitment)
     }
 
+    /// Acts for a sub-group which is a party at this position, its aggregated
+    /// key is the public key of this party. Takes aggregated commitment of
+    /// the sub-group instead of generating a nonce and returns pre-commitment
+    /// to it. The signer doesn't hold a nonce, so `sign` is refused with
+    /// `NonceAlreadyUsed` and the share comes from the sub-group.
+    pub fn receive_nested_commitment(
+        &mut self,
+        commitment: &Point<E, Unknown>,
+    ) -> Result<Vec<u8>, MusigError> {
+        if !self.jubjub_wrapper.is_in_correct_subgroup(commitment) {
+            return Err(MusigError::CommitmentIsNotInCorrectSubgroup);
+        }
+        if *commitment == Point::zero() {
+            return Err(MusigError::IdentityCommitment);
+        }
+
+        // t = H_comm(R_i)
+        let pre_commitment = self.hash_commitment(self.position, commitment);
+
+        self.nonce = None;
+        self.nonce_commitment = Some(*commitment);
+        self.nonce_used = true;
+
+        Ok(pre_commitment)
+    }
+
     /// Receives pre-commitments of other parties and returns his revealed
     /// commitment which is a point in the group. These pre-commitments will
     /// be used to validate received revealed commitments in the next step.     
@@ -122,12 +327,21 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
         let pre_commitments = self.pre_commitments.clone().unwrap();
 
         // check that t_i == H_comm(R_i)
-        for (commitment, pre_commitment) in commitments.iter().zip(pre_commitments.iter()) {
-            let t_i = Hasher::hash_commitment(&commitment);
+        for (position, (commitment, pre_commitment)) in
+            commitments.iter().zip(pre_commitments.iter()).enumerate()
+        {
+            let t_i = self.hash_commitment(position, &commitment);
             if !self.jubjub_wrapper.is_in_correct_subgroup(&commitment) {
                 return Err(MusigError::CommitmentIsNotInCorrectSubgroup);
             }
+            // nonce of an honest party is never zero
+            if *commitment == Point::zero() {
+                return Err(MusigError::IdentityCommitment);
+            }
             if *pre_commitment != t_i {
+                if self.session_id.is_some() {
+                    return Err(MusigError::SessionCommitmentMismatch);
+                }
                 return Err(MusigError::InvalidCommitment);
             }
         }
@@ -143,36 +357,214 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
         Ok(acc)
     }
 
-    /// Computes signature share with a challenge 'c'
+    // t_i = H_comm(R_i), or H_sess(sid, i, H_keyagg(L, X'), H_comm(R_i))
+    // if the run is bound to a session. H_comm(R_i, T) takes the place
+    // of H_comm(R_i) if the run is bound to an adaptor point.
+    fn hash_commitment(&self, position: usize, commitment: &Point<E, Unknown>) -> Vec<u8> {
+        let commitment_hash = match self.adaptor_point.as_ref() {
+            Some(adaptor_point) => self
+                .hash_suite
+                .hash_adaptor_commitment(commitment, adaptor_point),
+            None => self.hash_suite.hash_commitment(commitment),
+        };
+
+        match self.session_id.as_ref() {
+            Some(session_id) => self.hash_suite.hash_session_commitment(
+                session_id,
+                position,
+                &self.key_aggregation_hash,
+                &commitment_hash,
+            ),
+            None => commitment_hash,
+        }
+    }
+
+    // m, or m' = H_msg(sid, m) if the run is bound to a session
+    fn challenge_message(&self, message: &[u8]) -> Vec<u8> {
+        match self.session_id.as_ref() {
+            Some(session_id) => self.hash_suite.hash_session_message(session_id, message),
+            None => message.to_vec(),
+        }
+    }
+
+    /// Computes signature share with a challenge 'c'. A run bound to a
+    /// session signs m' = H_msg(sid, m) instead of m, its signature is
+    /// verified with `MuSigVerifier::verify_in_session`.
     pub fn sign(
         &mut self,
         private_key: &PrivateKey<E>,
         message: &[u8],
-        rescue_params: &<E as RescueEngine>::Params,
     ) -> Result<E::Fs, MusigError> {
+        self.sign_with(private_key, message, None)
+    }
+
+    /// Computes adaptor signature share for the adaptor point T = t * G,
+    /// which is added to the aggregated commitment: c = H_sig(X', R + T, m).
+    /// T must be the one bound with `set_adaptor_point` before
+    /// pre-commitments, shares are combined with `receive_pre_signatures`.
+    /// A run bound to a session signs m' = H_msg(sid, m) as `sign` does.
+    pub fn sign_with_adaptor(
+        &mut self,
+        private_key: &PrivateKey<E>,
+        message: &[u8],
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Result<E::Fs, MusigError> {
+        self.sign_with(private_key, message, Some(*adaptor_point))
+    }
+
+    /// Computes challenge c = H_sig(X', R, m) of the parent signer acting
+    /// for a sub-group, so it can verify and combine the share of the
+    /// sub-group. Returns c * a_i of this party. Members of the sub-group
+    /// recompute it themselves in `sign_nested`.
+    pub fn compute_nested_challenge(&mut self, message: &[u8]) -> Result<E::Fs, MusigError> {
         // check that whether previous step passed or not
         if self.aggregated_commitment.is_none() {
             return Err(MusigError::NonceCommitmentsNotReceived);
         }
 
         let aggregated_commitment = self.aggregated_commitment.clone().unwrap();
-        // since aggregated commitment has already generated
-        // we can safely unwrap nonce
-        let r = self.nonce.unwrap();
+        let message = self.challenge_message(message);
+
+        // c = H_sig(X', R, m)
+        let c = self.hash_suite.hash_signature_data(
+            &self.aggregated_pubkey,
+            &aggregated_commitment,
+            &message,
+        );
+        self.challenge = Some(c);
+
+        let mut nested_challenge = c;
+        nested_challenge.mul_assign(&self.a_values[self.position]);
+
+        Ok(nested_challenge)
+    }
+
+    /// Computes signature share of a sub-group member, which is a party of
+    /// the parent signing group at `parent_position` as a whole. The member
+    /// doesn't sign a challenge it is given: it checks that the parent key
+    /// aggregation context has the aggregated key of the sub-group at that
+    /// position and the parent commitments have the aggregated commitment
+    /// of the sub-group there, then computes c = H_sig(X', R, m) and c * a_i
+    /// itself. The parent group must use the same hash suite, and
+    /// `parent_session_id` is the session the parent run is bound to, if
+    /// any. Shares are combined with `receive_nested_signatures`.
+    pub fn sign_nested(
+        &mut self,
+        private_key: &PrivateKey<E>,
+        parent_context: &KeyAggregationContext<E>,
+        parent_position: usize,
+        parent_commitments: &[Point<E, Unknown>],
+        parent_session_id: Option<&SessionId>,
+        message: &[u8],
+    ) -> Result<E::Fs, MusigError> {
+        let aggregated_commitment = self
+            .aggregated_commitment
+            .ok_or(MusigError::NonceCommitmentsNotReceived)?;
+
+        if parent_position >= parent_context.pubkeys.len() {
+            return Err(MusigError::InvalidParticipantPosition);
+        }
+        if parent_context.pubkeys[parent_position].0 != self.aggregated_pubkey.0 {
+            return Err(MusigError::InvalidPublicKey);
+        }
+        if parent_commitments.len() != parent_context.pubkeys.len() {
+            return Err(MusigError::NonceCommitmentsAndParticipantsNotMatch);
+        }
+        if parent_commitments[parent_position] != aggregated_commitment {
+            return Err(MusigError::InvalidCommitment);
+        }
+
+        // R = \sum{1<=i<=n}{R_i} of the parent group
+        let mut parent_aggregated_commitment = Point::zero();
+        for commitment in parent_commitments {
+            if !self.jubjub_wrapper.is_in_correct_subgroup(commitment) {
+                return Err(MusigError::CommitmentIsNotInCorrectSubgroup);
+            }
+            parent_aggregated_commitment = self
+                .jubjub_wrapper
+                .add(&parent_aggregated_commitment, commitment);
+        }
+
+        // m' = H_msg(sid, m) if the parent run is bound to a session
+        let message = match parent_session_id {
+            Some(session_id) => self.hash_suite.hash_session_message(session_id, message),
+            None => message.to_vec(),
+        };
+
+        // c = H_sig(X', R, m), signed as c * a_i of the sub-group
+        let mut nested_challenge = self.hash_suite.hash_signature_data(
+            &parent_context.aggregated_pubkey,
+            &parent_aggregated_commitment,
+            &message,
+        );
+        nested_challenge.mul_assign(&parent_context.a_values[parent_position]);
+
+        self.sign_with_challenge(private_key, nested_challenge)
+    }
+
+    fn sign_with(
+        &mut self,
+        private_key: &PrivateKey<E>,
+        message: &[u8],
+        adaptor_point: Option<Point<E, Unknown>>,
+    ) -> Result<E::Fs, MusigError> {
+        // check that whether previous step passed or not
+        if self.aggregated_commitment.is_none() {
+            return Err(MusigError::NonceCommitmentsNotReceived);
+        }
+
+        // pre-commitments of all parties are bound to this adaptor point
+        if adaptor_point != self.adaptor_point {
+            return Err(MusigError::AdaptorPointMismatch);
+        }
+
+        let aggregated_commitment = self.aggregated_commitment.clone().unwrap();
 
         let aggregated_pubkey = self.aggregated_pubkey.clone();
 
-        let a_i = self.a_values[self.position];
+        // R + T for adaptor signature
+        let challenge_commitment = match adaptor_point {
+            Some(adaptor_point) => self
+                .jubjub_wrapper
+                .add(&aggregated_commitment, &adaptor_point),
+            None => aggregated_commitment,
+        };
+
+        let message = self.challenge_message(message);
 
         // c = H_sig(X', R, m)
         // this computes fiat-shamir challenge
-        let c = Hasher::hash_signature_data::<E>(
+        let c = self.hash_suite.hash_signature_data(
             &aggregated_pubkey,
-            &aggregated_commitment,
-            message,
-            rescue_params,
+            &challenge_commitment,
+            &message,
         );
 
+        self.sign_with_challenge(private_key, c)
+    }
+
+    fn sign_with_challenge(
+        &mut self,
+        private_key: &PrivateKey<E>,
+        c: E::Fs,
+    ) -> Result<E::Fs, MusigError> {
+        // check that whether previous step passed or not
+        if self.aggregated_commitment.is_none() {
+            return Err(MusigError::NonceCommitmentsNotReceived);
+        }
+
+        // a second share with the same nonce and a different
+        // challenge would reveal the private key
+        if self.nonce_used {
+            return Err(MusigError::NonceAlreadyUsed);
+        }
+
+        // since aggregated commitment has already generated
+        // we can safely unwrap nonce
+        let r = self.nonce.unwrap();
+
+        let a_i = self.a_values[self.position];
+
         self.challenge = Some(c);
         // s = r + c * a_i * x_i
         let mut s = c;
@@ -180,6 +572,10 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
         s.mul_assign(&private_key.0);
         s.add_assign(&r);
 
+        // nonce is not needed anymore
+        self.nonce = None;
+        self.nonce_used = true;
+
         Ok(s)
     }
 
@@ -190,6 +586,53 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
         &self,
         signature_shares: &[E::Fs],
     ) -> Result<Signature<E>, MusigError> {
+        let aggregated_signature = self.aggregate_shares(signature_shares)?;
+
+        if self.adaptor_point.is_some() {
+            return Err(MusigError::PreSignatureExpected);
+        }
+
+        let aggregated_commitment = self.aggregated_commitment.clone().unwrap();
+
+        // σ = (R, s)
+        Ok(Signature {
+            r: aggregated_commitment,
+            s: aggregated_signature,
+        })
+    }
+
+    /// Receives signature shares of sub-group members produced with
+    /// `sign_nested` and verifies them. If all shares are valid then returns
+    /// the signature share of the sub-group in the parent signing group.
+    pub fn receive_nested_signatures(
+        &self,
+        signature_shares: &[E::Fs],
+    ) -> Result<E::Fs, MusigError> {
+        self.aggregate_shares(signature_shares)
+    }
+
+    /// Receives adaptor signature shares and verifies them. If all shares
+    /// are valid then returns an aggregated pre-signature, which is
+    /// completed into a signature with the secret of the adaptor point.
+    pub fn receive_pre_signatures(
+        &self,
+        signature_shares: &[E::Fs],
+    ) -> Result<PreSignature<E>, MusigError> {
+        let aggregated_signature = self.aggregate_shares(signature_shares)?;
+
+        let adaptor_point = self.adaptor_point.ok_or(MusigError::AdaptorPointNotSet)?;
+
+        let aggregated_commitment = self.aggregated_commitment.clone().unwrap();
+
+        // σ' = (R, s')
+        Ok(PreSignature {
+            r: aggregated_commitment,
+            s: aggregated_signature,
+            adaptor_point,
+        })
+    }
+
+    fn aggregate_shares(&self, signature_shares: &[E::Fs]) -> Result<E::Fs, MusigError> {
         // check that whether previous step passed or not
         if self.challenge.is_none() {
             return Err(MusigError::ChallengeNotGenerated);
@@ -208,13 +651,65 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
             aggregated_signature.add_assign(&signature);
         }
 
-        let aggregated_commitment = self.aggregated_commitment.clone().unwrap();
+        // s = s + c * t, so the signature is valid for X' + t * G
+        let mut tweak_term = self.challenge.unwrap();
+        tweak_term.mul_assign(&self.tweak);
+        aggregated_signature.add_assign(&tweak_term);
 
-        // σ = (R, s)
-        Ok(Signature {
-            r: aggregated_commitment,
-            s: aggregated_signature,
-        })
+        Ok(aggregated_signature)
+    }
+
+    /// Returns positions of parties whose commitments are the identity
+    /// point, not in a correct subgroup or don't match their pre-commitments,
+    /// so a failed `receive_commitments` can be attributed to them.
+    pub fn blame_commitments(
+        &self,
+        commitments: &[Point<E, Unknown>],
+    ) -> Result<Vec<usize>, MusigError> {
+        let pre_commitments = self
+            .pre_commitments
+            .as_ref()
+            .ok_or(MusigError::NoncePreCommitmentsNotReceived)?;
+
+        if commitments.len() != self.pubkeys.len() {
+            return Err(MusigError::NonceCommitmentsAndParticipantsNotMatch);
+        }
+
+        Ok(commitments
+            .iter()
+            .zip(pre_commitments.iter())
+            .enumerate()
+            .filter(|(position, (commitment, pre_commitment))| {
+                !self.jubjub_wrapper.is_in_correct_subgroup(commitment)
+                    || **commitment == Point::zero()
+                    || **pre_commitment != self.hash_commitment(*position, commitment)
+            })
+            .map(|(position, _)| position)
+            .collect())
+    }
+
+    /// Returns positions of parties whose signature shares are invalid,
+    /// so a failed `receive_signatures` can be attributed to them.
+    pub fn blame_signature_shares(
+        &self,
+        signature_shares: &[E::Fs],
+    ) -> Result<Vec<usize>, MusigError> {
+        if self.challenge.is_none() {
+            return Err(MusigError::ChallengeNotGenerated);
+        }
+
+        if signature_shares.len() != self.pubkeys.len() {
+            return Err(MusigError::SignatureShareAndParticipantsNotMatch);
+        }
+
+        Ok(signature_shares
+            .iter()
+            .enumerate()
+            .filter(|(position, signature_share)| {
+                self.verify_share(signature_share, *position).is_err()
+            })
+            .map(|(position, _)| position)
+            .collect())
     }
 
     /// Verifies asignature share of a single party.
@@ -233,4 +728,245 @@ impl<E: JubjubEngine + RescueEngine> MuSigSigner<E> {
         }
         Ok(())
     }
+
+    /// Serializes current state of the protocol run, so it can be
+    /// restored later with `import_state`. The state contains the secret
+    /// nonce, so it must be kept as secret as the private key. A state
+    /// exported before signing stays usable after the signer has signed,
+    /// so the nonce commitment must be recorded in `UsedNonces` once the
+    /// share is produced.
+    pub fn export_state(&self) -> Vec<u8> {
+        let mut flags = 0u8;
+        if self.nonce.is_some() {
+            flags |= STATE_HAS_NONCE;
+        }
+        if self.nonce_commitment.is_some() {
+            flags |= STATE_HAS_NONCE_COMMITMENT;
+        }
+        if self.pre_commitments.is_some() {
+            flags |= STATE_HAS_PRE_COMMITMENTS;
+        }
+        if self.aggregated_commitment.is_some() {
+            flags |= STATE_HAS_COMMITMENTS;
+        }
+        if self.challenge.is_some() {
+            flags |= STATE_HAS_CHALLENGE;
+        }
+        if self.nonce_used {
+            flags |= STATE_NONCE_USED;
+        }
+        if self.adaptor_point.is_some() {
+            flags |= STATE_HAS_ADAPTOR_POINT;
+        }
+        if !self.tweak.is_zero() {
+            flags |= STATE_HAS_TWEAK;
+        }
+
+        let mut extended_flags = 0u8;
+        if self.session_id.is_some() {
+            extended_flags |= STATE_HAS_SESSION_ID;
+        }
+
+        let mut buf = vec![SIGNER_STATE_VERSION, flags, extended_flags];
+        buf.extend_from_slice(&(self.position as u32).to_be_bytes());
+        buf.extend_from_slice(&(self.pubkeys.len() as u32).to_be_bytes());
+        for pubkey in self.pubkeys.iter() {
+            pubkey.write(&mut buf).expect("has serialized pubkey");
+        }
+        if !self.tweak.is_zero() {
+            write_scalar::<E>(&self.tweak, &mut buf);
+        }
+        if let Some(session_id) = self.session_id.as_ref() {
+            buf.extend_from_slice(session_id);
+        }
+
+        if let Some(nonce) = self.nonce {
+            write_scalar::<E>(&nonce, &mut buf);
+        }
+        if let Some(nonce_commitment) = self.nonce_commitment {
+            nonce_commitment
+                .write(&mut buf)
+                .expect("has serialized commitment");
+        }
+        if let Some(pre_commitments) = self.pre_commitments.as_ref() {
+            for pre_commitment in pre_commitments {
+                buf.extend_from_slice(&(pre_commitment.len() as u32).to_be_bytes());
+                buf.extend_from_slice(pre_commitment);
+            }
+        }
+        if let Some(aggregated_commitment) = self.aggregated_commitment {
+            for commitment in self.nonce_commitments.iter() {
+                commitment
+                    .write(&mut buf)
+                    .expect("has serialized commitment");
+            }
+            aggregated_commitment
+                .write(&mut buf)
+                .expect("has serialized commitment");
+        }
+        if let Some(challenge) = self.challenge {
+            write_scalar::<E>(&challenge, &mut buf);
+        }
+        if let Some(adaptor_point) = self.adaptor_point {
+            adaptor_point
+                .write(&mut buf)
+                .expect("has serialized adaptor point");
+        }
+
+        buf
+    }
+
+    /// Restores a signer from the state produced by `export_state`.
+    /// If the exported signer has already produced its signature share,
+    /// or its nonce commitment is in `used_nonces`, restored one refuses
+    /// to sign again.
+    /// Hash suite must be the one the exported signer was created with.
+    pub fn import_state(
+        state: &[u8],
+        params: &'a <E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: H,
+        used_nonces: &UsedNonces,
+    ) -> Result<Self, MusigError> {
+        let mut reader = state;
+
+        let version = read_u8(&mut reader)?;
+        if version != SIGNER_STATE_VERSION {
+            return Err(MusigError::InvalidSignerState);
+        }
+        let flags = read_u8(&mut reader)?;
+        let extended_flags = read_u8(&mut reader)?;
+        if extended_flags & !STATE_HAS_SESSION_ID != 0 {
+            return Err(MusigError::InvalidSignerState);
+        }
+        let position = read_u32(&mut reader)? as usize;
+        let number_of_pubkeys = read_u32(&mut reader)? as usize;
+
+        let mut pubkeys = vec![];
+        for _ in 0..number_of_pubkeys {
+            let pubkey =
+                PublicKey::read(&mut reader, params).map_err(|_| MusigError::InvalidSignerState)?;
+            pubkeys.push(pubkey);
+        }
+
+        let mut context = KeyAggregationContext::new(&pubkeys, params, &hash_suite)?;
+        if flags & STATE_HAS_TWEAK != 0 {
+            let tweak = read_scalar::<E>(&mut reader)?;
+            context
+                .apply_tweak(&tweak, params, generator)
+                .map_err(|_| MusigError::InvalidSignerState)?;
+        }
+
+        let mut signer = Self::new_with_context(&context, position, params, generator, hash_suite)?;
+
+        if extended_flags & STATE_HAS_SESSION_ID != 0 {
+            let mut session_id = [0u8; SESSION_ID_LENGTH];
+            reader
+                .read_exact(&mut session_id)
+                .map_err(|_| MusigError::InvalidSignerState)?;
+            signer.session_id = Some(session_id);
+        }
+
+        if flags & STATE_HAS_NONCE != 0 {
+            signer.nonce = Some(read_scalar::<E>(&mut reader)?);
+        }
+        if flags & STATE_HAS_NONCE_COMMITMENT != 0 {
+            signer.nonce_commitment = Some(read_point::<E>(&mut reader, params)?);
+        }
+        if flags & STATE_HAS_PRE_COMMITMENTS != 0 {
+            let mut pre_commitments = vec![];
+            for _ in 0..number_of_pubkeys {
+                let len = read_u32(&mut reader)? as usize;
+                if reader.len() < len {
+                    return Err(MusigError::InvalidSignerState);
+                }
+                pre_commitments.push(reader[..len].to_vec());
+                reader = &reader[len..];
+            }
+            signer.pre_commitments = Some(pre_commitments);
+        }
+        if flags & STATE_HAS_COMMITMENTS != 0 {
+            for _ in 0..number_of_pubkeys {
+                let commitment = read_point::<E>(&mut reader, params)?;
+                signer.nonce_commitments.push(commitment);
+            }
+            signer.aggregated_commitment = Some(read_point::<E>(&mut reader, params)?);
+        }
+        if flags & STATE_HAS_CHALLENGE != 0 {
+            signer.challenge = Some(read_scalar::<E>(&mut reader)?);
+        }
+        if flags & STATE_HAS_ADAPTOR_POINT != 0 {
+            signer.adaptor_point = Some(read_point::<E>(&mut reader, params)?);
+        }
+        signer.nonce_used = flags & STATE_NONCE_USED != 0;
+
+        // a used nonce is never exported, and each later step
+        // requires the previous one to be present
+        let is_consistent = !(signer.nonce_used && signer.nonce.is_some())
+            && (signer.nonce.is_some() || signer.nonce_used || signer.nonce_commitment.is_none())
+            && (signer.pre_commitments.is_none() || signer.nonce_commitment.is_some())
+            && (signer.aggregated_commitment.is_none() || signer.pre_commitments.is_some())
+            && (signer.challenge.is_none() || signer.aggregated_commitment.is_some());
+
+        if !reader.is_empty() || !is_consistent {
+            return Err(MusigError::InvalidSignerState);
+        }
+
+        // the nonce has signed after this state was exported
+        if let Some(nonce_commitment) = signer.nonce_commitment.as_ref() {
+            if signer.nonce.is_some() && used_nonces.contains(nonce_commitment) {
+                signer.nonce = None;
+                signer.nonce_used = true;
+            }
+        }
+
+        Ok(signer)
+    }
+}
+
+fn write_scalar<E: JubjubEngine>(scalar: &E::Fs, dest: &mut Vec<u8>) {
+    scalar
+        .into_repr()
+        .write_be(dest)
+        .expect("has serialized scalar");
+}
+
+fn read_u8(reader: &mut &[u8]) -> Result<u8, MusigError> {
+    let mut buf = [0u8; 1];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| MusigError::InvalidSignerState)?;
+
+    Ok(buf[0])
+}
+
+fn read_u32(reader: &mut &[u8]) -> Result<u32, MusigError> {
+    let mut buf = [0u8; 4];
+    reader
+        .read_exact(&mut buf)
+        .map_err(|_| MusigError::InvalidSignerState)?;
+
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn read_scalar<E: JubjubEngine>(reader: &mut &[u8]) -> Result<E::Fs, MusigError> {
+    let mut repr = <E::Fs as PrimeField>::Repr::default();
+    repr.read_be(reader)
+        .map_err(|_| MusigError::InvalidSignerState)?;
+
+    E::Fs::from_repr(repr).map_err(|_| MusigError::InvalidSignerState)
+}
+
+fn encode_point<E: JubjubEngine>(point: &Point<E, Unknown>) -> Vec<u8> {
+    let mut buf = vec![];
+    point.write(&mut buf).expect("has serialized point");
+
+    buf
+}
+
+fn read_point<E: JubjubEngine>(
+    reader: &mut &[u8],
+    params: &<E as JubjubEngine>::Params,
+) -> Result<Point<E, Unknown>, MusigError> {
+    Point::read(reader, params).map_err(|_| MusigError::InvalidSignerState)
 }
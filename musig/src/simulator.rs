@@ -0,0 +1,606 @@
+//! In-process network of parties running `MuSigSigner` rounds in lock-step,
+//! where chosen parties misbehave. Each attack must be detected by the
+//! honest parties and attributed to the party which made it.
+
+use crate::aggregated_pubkey::KeyAggregationContext;
+use crate::ceremony::{Received, RoundMessages};
+use crate::errors::{CeremonyError, MusigError};
+use crate::hash_suite::{DefaultHashSuite, HashSuite};
+use crate::signer::MuSigSigner;
+use crate::tests::musig_test_bn256_setup;
+use crate::verifier::MuSigVerifier;
+use crate::wire::{
+    collect_commitments, collect_pre_commitments, collect_signature_shares, Payload, Round,
+    SessionId, WireMessage,
+};
+use bellman::pairing::bn256::Bn256;
+use bellman::Field;
+use franklin_crypto::alt_babyjubjub::{fs::Fs, AltJubjubBn256};
+use franklin_crypto::eddsa::{PrivateKey, PublicKey, Signature};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::FixedGenerators;
+use franklin_crypto::rescue::bn256::Bn256RescueParams;
+use rand::{SeedableRng, XorShiftRng};
+
+const ROUNDS: [Round; 3] = [
+    Round::PreCommitment,
+    Round::Commitment,
+    Round::SignatureShare,
+];
+
+/// What a simulated party does instead of following the protocol
+#[derive(Clone)]
+enum Behavior {
+    Honest,
+    /// Sends its own message of the round and a conflicting one to each party
+    Equivocate(Round),
+    /// Sends its own pre-commitment to parties at even positions
+    /// and a conflicting one to the others
+    SplitView,
+    /// Reveals a commitment which doesn't match its pre-commitment
+    WrongCommitment,
+    /// Pre-commits to the identity point and reveals it
+    IdentityCommitment,
+    /// Sends a signature share which isn't valid
+    InvalidShare,
+    /// Sends its messages of an earlier session instead of the current ones
+    Replay(Vec<WireMessage<Bn256>>),
+    /// Sends payloads of its messages of an earlier session framed
+    /// under the current session id
+    Reframe(Vec<WireMessage<Bn256>>),
+    /// Sends nothing, not even abort, from the round on
+    DropOut(Round),
+}
+
+/// How the session ended for a party
+enum Outcome {
+    Signed(Signature<Bn256>),
+    /// The party stopped with the error, blaming parties at the positions
+    Failed {
+        error: CeremonyError,
+        blamed: Vec<usize>,
+    },
+}
+
+struct Party<'a> {
+    signer: MuSigSigner<'a, Bn256>,
+    private_key: PrivateKey<Bn256>,
+    behavior: Behavior,
+    // own message of the current round
+    own_message: Option<WireMessage<Bn256>>,
+    inbox: Vec<WireMessage<Bn256>>,
+    outcome: Option<Outcome>,
+    abort_sent: bool,
+}
+
+struct Simulation {
+    pubkeys: Vec<PublicKey<Bn256>>,
+    outcomes: Vec<Outcome>,
+    // every message sent in the session
+    transcript: Vec<WireMessage<Bn256>>,
+}
+
+/// Runs a session of parties with the behaviors, parties are at positions
+/// of their behaviors. A party which fails sends abort in the next round.
+/// Messages of a round which haven't arrived when the round ends are
+/// missing, as if the round had timed out.
+fn simulate(session_id: SessionId, behaviors: Vec<Behavior>, message: &[u8]) -> Simulation {
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+    let number_of_parties = behaviors.len();
+
+    let (privkeys, pubkeys, signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+    let key_aggregation_hash = KeyAggregationContext::new(&pubkeys, &jubjub_params, &hash_suite)
+        .unwrap()
+        .key_aggregation_hash(&hash_suite);
+
+    let mut parties: Vec<_> = signers
+        .into_iter()
+        .zip(privkeys.into_iter())
+        .zip(behaviors.into_iter())
+        .map(|((mut signer, private_key), behavior)| {
+            signer.set_session_id(session_id).unwrap();
+            Party {
+                signer,
+                private_key,
+                behavior,
+                own_message: None,
+                inbox: vec![],
+                outcome: None,
+                abort_sent: false,
+            }
+        })
+        .collect();
+
+    for (position, party) in parties.iter_mut().enumerate() {
+        // nonces differ between sessions
+        let rng = &mut XorShiftRng::from_seed([
+            0x3dbe6259,
+            0x8d313d76,
+            u32::from(session_id[0]),
+            position as u32,
+        ]);
+        let pre_commitment = party.signer.compute_precommitment(rng).unwrap();
+        party.own_message = Some(
+            WireMessage::new(session_id, position, Payload::PreCommitment(pre_commitment)).unwrap(),
+        );
+    }
+
+    let mut transcript = vec![];
+    for round in ROUNDS.iter().copied() {
+        let mut deliveries = vec![];
+        for (position, party) in parties.iter_mut().enumerate() {
+            if let Behavior::DropOut(drop_round) = party.behavior {
+                if round as u8 >= drop_round as u8 {
+                    continue;
+                }
+            }
+
+            if party.outcome.is_some() {
+                if !party.abort_sent {
+                    party.abort_sent = true;
+                    let abort = WireMessage::new(session_id, position, Payload::Abort).unwrap();
+                    deliveries.extend(broadcast(position, number_of_parties, &abort));
+                }
+                continue;
+            }
+
+            let own_message = party.own_message.as_ref().unwrap();
+            deliveries.extend(outgoing(
+                &party.behavior,
+                own_message,
+                number_of_parties,
+                &key_aggregation_hash,
+                &hash_suite,
+                &jubjub_params,
+            ));
+        }
+
+        for (recipient, message) in deliveries {
+            transcript.push(message.clone());
+            parties[recipient].inbox.push(message);
+        }
+
+        for party in parties.iter_mut().filter(|party| party.outcome.is_none()) {
+            if let Err((error, blamed)) = run_round(party, session_id, message) {
+                party.outcome = Some(Outcome::Failed { error, blamed });
+            }
+        }
+    }
+
+    Simulation {
+        pubkeys,
+        outcomes: parties
+            .into_iter()
+            .map(|party| party.outcome.unwrap())
+            .collect(),
+        transcript,
+    }
+}
+
+fn broadcast(
+    sender: usize,
+    number_of_parties: usize,
+    message: &WireMessage<Bn256>,
+) -> Vec<(usize, WireMessage<Bn256>)> {
+    (0..number_of_parties)
+        .filter(|recipient| *recipient != sender)
+        .map(|recipient| (recipient, message.clone()))
+        .collect()
+}
+
+// messages a party sends in the round instead of its own message
+fn outgoing(
+    behavior: &Behavior,
+    own_message: &WireMessage<Bn256>,
+    number_of_parties: usize,
+    key_aggregation_hash: &[u8],
+    hash_suite: &DefaultHashSuite<Bn256>,
+    jubjub_params: &AltJubjubBn256,
+) -> Vec<(usize, WireMessage<Bn256>)> {
+    let sender = own_message.sender();
+    let round = own_message.round();
+    let replace = |payload| {
+        let message = WireMessage::new(*own_message.session_id(), sender, payload).unwrap();
+        broadcast(sender, number_of_parties, &message)
+    };
+
+    match (behavior, round) {
+        (Behavior::Equivocate(equivocation_round), _) if *equivocation_round == round => {
+            let mut messages = broadcast(sender, number_of_parties, own_message);
+            messages.extend(broadcast(
+                sender,
+                number_of_parties,
+                &conflicting(own_message, jubjub_params),
+            ));
+            messages
+        }
+        (Behavior::SplitView, Round::PreCommitment) => {
+            let other_message = conflicting(own_message, jubjub_params);
+            (0..number_of_parties)
+                .filter(|recipient| *recipient != sender)
+                .map(|recipient| match recipient % 2 {
+                    0 => (recipient, own_message.clone()),
+                    _ => (recipient, other_message.clone()),
+                })
+                .collect()
+        }
+        (Behavior::WrongCommitment, Round::Commitment) => broadcast(
+            sender,
+            number_of_parties,
+            &conflicting(own_message, jubjub_params),
+        ),
+        (Behavior::IdentityCommitment, Round::PreCommitment) => {
+            replace(Payload::PreCommitment(hash_suite.hash_session_commitment(
+                own_message.session_id(),
+                sender,
+                key_aggregation_hash,
+                &hash_suite.hash_commitment(&Point::zero()),
+            )))
+        }
+        (Behavior::IdentityCommitment, Round::Commitment) => {
+            replace(Payload::Commitment(Point::zero()))
+        }
+        (Behavior::InvalidShare, Round::SignatureShare) => {
+            replace(Payload::SignatureShare(Fs::one()))
+        }
+        (Behavior::Replay(old_messages), _) => old_messages
+            .iter()
+            .filter(|message| message.sender() == sender && message.round() == round)
+            .take(1)
+            .flat_map(|message| broadcast(sender, number_of_parties, message))
+            .collect(),
+        (Behavior::Reframe(old_messages), _) => old_messages
+            .iter()
+            .filter(|message| message.sender() == sender && message.round() == round)
+            .take(1)
+            .flat_map(|message| replace(message.payload().clone()))
+            .collect(),
+        _ => broadcast(sender, number_of_parties, own_message),
+    }
+}
+
+// another message of the same round from the same sender
+fn conflicting(message: &WireMessage<Bn256>, jubjub_params: &AltJubjubBn256) -> WireMessage<Bn256> {
+    let payload = match message.payload() {
+        Payload::PreCommitment(pre_commitment) => {
+            let mut pre_commitment = pre_commitment.clone();
+            pre_commitment[0] ^= 1;
+            Payload::PreCommitment(pre_commitment)
+        }
+        Payload::Commitment(commitment) => Payload::Commitment(commitment.double(jubjub_params)),
+        Payload::SignatureShare(signature_share) => {
+            let mut signature_share = *signature_share;
+            signature_share.add_assign(&Fs::one());
+            Payload::SignatureShare(signature_share)
+        }
+        Payload::Abort => Payload::Abort,
+    };
+
+    WireMessage::new(*message.session_id(), message.sender(), payload).unwrap()
+}
+
+// collects messages of the round from the inbox of the party and runs
+// the round, the error comes with positions of blamed parties
+fn run_round(
+    party: &mut Party,
+    session_id: SessionId,
+    message: &[u8],
+) -> Result<(), (CeremonyError, Vec<usize>)> {
+    let number_of_parties = party.signer.pubkeys().len();
+    let own_message = party.own_message.take().unwrap();
+    let round = own_message.round();
+
+    let mut messages = RoundMessages::new(own_message, session_id, number_of_parties);
+    for message in std::mem::take(&mut party.inbox) {
+        match messages.receive(message) {
+            Ok(Received::Later(message)) => party.inbox.push(message),
+            Ok(_) => {}
+            Err(error) => {
+                let blamed = match error {
+                    CeremonyError::Aborted(position) | CeremonyError::Equivocation(position) => {
+                        vec![position]
+                    }
+                    _ => vec![],
+                };
+                return Err((error, blamed));
+            }
+        }
+    }
+
+    if !messages.is_complete() {
+        let missing = messages.missing();
+        return Err((
+            CeremonyError::Timeout {
+                round,
+                missing: missing.clone(),
+            },
+            missing,
+        ));
+    }
+
+    let messages = messages.into_messages();
+    let position = party.signer.position();
+    let signer = &mut party.signer;
+    let unblamed = |error: MusigError| (CeremonyError::Musig(error), vec![]);
+
+    let payload = match round {
+        Round::PreCommitment => {
+            let pre_commitments =
+                collect_pre_commitments(&messages, &session_id, number_of_parties)
+                    .map_err(unblamed)?;
+            let commitment = signer
+                .receive_precommitments(&pre_commitments)
+                .map_err(unblamed)?;
+            Payload::Commitment(commitment)
+        }
+        Round::Commitment => {
+            let commitments =
+                collect_commitments(&messages, &session_id, number_of_parties).map_err(unblamed)?;
+            if let Err(error) = signer.receive_commitments(&commitments) {
+                let blamed = signer.blame_commitments(&commitments).unwrap();
+                return Err((error.into(), blamed));
+            }
+            let signature_share = signer.sign(&party.private_key, message).map_err(unblamed)?;
+            Payload::SignatureShare(signature_share)
+        }
+        _ => {
+            let signature_shares =
+                collect_signature_shares(&messages, &session_id, number_of_parties)
+                    .map_err(unblamed)?;
+            match signer.receive_signatures(&signature_shares) {
+                Ok(signature) => party.outcome = Some(Outcome::Signed(signature)),
+                Err(error) => {
+                    let blamed = signer.blame_signature_shares(&signature_shares).unwrap();
+                    return Err((error.into(), blamed));
+                }
+            }
+            return Ok(());
+        }
+    };
+
+    party.own_message = Some(WireMessage::new(session_id, position, payload).unwrap());
+
+    Ok(())
+}
+
+fn behaviors(number_of_parties: usize, byzantine: &[(usize, Behavior)]) -> Vec<Behavior> {
+    let mut behaviors = vec![Behavior::Honest; number_of_parties];
+    for (position, behavior) in byzantine {
+        behaviors[*position] = behavior.clone();
+    }
+
+    behaviors
+}
+
+// checks that each honest party has failed with the error blaming the parties
+fn assert_blamed(
+    simulation: &Simulation,
+    byzantine: &[usize],
+    expected_error: impl Fn(&CeremonyError) -> bool,
+    expected_blamed: &[usize],
+) {
+    for (position, outcome) in simulation.outcomes.iter().enumerate() {
+        if byzantine.contains(&position) {
+            continue;
+        }
+
+        match outcome {
+            Outcome::Failed { error, blamed } => {
+                assert!(expected_error(error), "party {}: {}", position, error);
+                assert_eq!(blamed, expected_blamed, "party {}", position);
+            }
+            Outcome::Signed(_) => panic!("party {} signs despite the attack", position),
+        }
+    }
+}
+
+const SESSION_ID: SessionId = [6u8; 32];
+const MESSAGE: &[u8] = &[7u8; 32];
+
+#[test]
+fn test_simulated_honest_parties() {
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let simulation = simulate(SESSION_ID, behaviors(4, &[]), MESSAGE);
+
+    let signatures: Vec<_> = simulation
+        .outcomes
+        .iter()
+        .map(|outcome| match outcome {
+            Outcome::Signed(signature) => signature,
+            Outcome::Failed { error, .. } => panic!("honest party fails: {}", error),
+        })
+        .collect();
+    for signature in signatures.iter() {
+        assert!(signature.r == signatures[0].r && signature.s == signatures[0].s);
+    }
+    assert!(MuSigVerifier::verify_in_session(
+        MESSAGE,
+        &SESSION_ID,
+        &simulation.pubkeys,
+        signatures[0],
+        &jubjub_params,
+        FixedGenerators::SpendingKeyGenerator,
+        &hash_suite,
+    )
+    .unwrap());
+}
+
+#[test]
+fn test_simulated_equivocation() {
+    for round in ROUNDS.iter().copied() {
+        let simulation = simulate(
+            SESSION_ID,
+            behaviors(4, &[(1, Behavior::Equivocate(round))]),
+            MESSAGE,
+        );
+        assert_blamed(
+            &simulation,
+            &[1],
+            |error| matches!(error, CeremonyError::Equivocation(1)),
+            &[1],
+        );
+    }
+}
+
+#[test]
+fn test_simulated_split_view() {
+    // parties with the conflicting pre-commitment catch the sender, the
+    // others can only see them abort, since there is no broadcast channel
+    let simulation = simulate(
+        SESSION_ID,
+        behaviors(4, &[(0, Behavior::SplitView)]),
+        MESSAGE,
+    );
+
+    for (position, outcome) in simulation.outcomes.iter().enumerate().skip(1) {
+        match (position % 2, outcome) {
+            (1, Outcome::Failed { error, blamed }) => {
+                assert!(matches!(
+                    error,
+                    CeremonyError::Musig(MusigError::SessionCommitmentMismatch)
+                ));
+                assert_eq!(blamed, &[0]);
+            }
+            (0, Outcome::Failed { error, .. }) => {
+                assert!(matches!(error, CeremonyError::Aborted(1)));
+            }
+            _ => panic!("party {} signs despite the attack", position),
+        }
+    }
+}
+
+#[test]
+fn test_simulated_wrong_commitment() {
+    let simulation = simulate(
+        SESSION_ID,
+        behaviors(4, &[(2, Behavior::WrongCommitment)]),
+        MESSAGE,
+    );
+    assert_blamed(
+        &simulation,
+        &[2],
+        |error| {
+            matches!(
+                error,
+                CeremonyError::Musig(MusigError::SessionCommitmentMismatch)
+            )
+        },
+        &[2],
+    );
+}
+
+#[test]
+fn test_simulated_identity_commitment() {
+    let simulation = simulate(
+        SESSION_ID,
+        behaviors(3, &[(1, Behavior::IdentityCommitment)]),
+        MESSAGE,
+    );
+    assert_blamed(
+        &simulation,
+        &[1],
+        |error| matches!(error, CeremonyError::Musig(MusigError::IdentityCommitment)),
+        &[1],
+    );
+}
+
+#[test]
+fn test_simulated_invalid_shares() {
+    let simulation = simulate(
+        SESSION_ID,
+        behaviors(
+            5,
+            &[(1, Behavior::InvalidShare), (3, Behavior::InvalidShare)],
+        ),
+        MESSAGE,
+    );
+    assert_blamed(
+        &simulation,
+        &[1, 3],
+        |error| {
+            matches!(
+                error,
+                CeremonyError::Musig(MusigError::InvalidSignatureShare)
+            )
+        },
+        &[1, 3],
+    );
+}
+
+#[test]
+fn test_simulated_replay() {
+    // messages of an earlier session of the same group are ignored,
+    // so the replaying party is missing
+    let earlier_session = simulate([5u8; 32], behaviors(3, &[]), MESSAGE);
+    let simulation = simulate(
+        SESSION_ID,
+        behaviors(3, &[(2, Behavior::Replay(earlier_session.transcript))]),
+        MESSAGE,
+    );
+    assert_blamed(
+        &simulation,
+        &[2],
+        |error| {
+            matches!(
+                error,
+                CeremonyError::Timeout {
+                    round: Round::PreCommitment,
+                    ..
+                }
+            )
+        },
+        &[2],
+    );
+}
+
+#[test]
+fn test_simulated_reframed_replay() {
+    // messages of an earlier session framed under the current session id
+    // pass the session check of the wire, but pre-commitments are bound
+    // to the session they were computed for
+    let earlier_session = simulate([5u8; 32], behaviors(3, &[]), MESSAGE);
+    let simulation = simulate(
+        SESSION_ID,
+        behaviors(3, &[(2, Behavior::Reframe(earlier_session.transcript))]),
+        MESSAGE,
+    );
+    assert_blamed(
+        &simulation,
+        &[2],
+        |error| {
+            matches!(
+                error,
+                CeremonyError::Musig(MusigError::SessionCommitmentMismatch)
+            )
+        },
+        &[2],
+    );
+}
+
+#[test]
+fn test_simulated_dropout() {
+    for round in ROUNDS.iter().copied() {
+        let simulation = simulate(
+            SESSION_ID,
+            behaviors(4, &[(3, Behavior::DropOut(round))]),
+            MESSAGE,
+        );
+        assert_blamed(
+            &simulation,
+            &[3],
+            |error| match error {
+                CeremonyError::Timeout {
+                    round: timed_out, ..
+                } => *timed_out == round,
+                _ => false,
+            },
+            &[3],
+        );
+    }
+}
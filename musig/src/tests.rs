@@ -1,16 +1,39 @@
-use crate::errors::MusigError;
-use crate::signer::MuSigSigner;
+use crate::aggregated_pubkey::{AggregatedPublicKey, KeyAggregationContext};
+use crate::batch::MuSigBatchSigner;
+use crate::ceremony::{Ceremony, CeremonyConfig};
+use crate::encoder::write_point;
+use crate::errors::{CeremonyError, MusigError};
+use crate::hash_suite::{
+    DefaultHashSuite, HashSuite, PoseidonHashSuite, RescueHashSuite, Sha256ChallengeHashSuite,
+    Sha256HashSuite,
+};
+use crate::hasher::{sha256_hash_to_scalar, tag_prefix};
+use crate::nonce_pool::TwoRoundNoncePool;
+use crate::signature::{MusigSignature, SIGNATURE_ENCODING_LENGTH};
+use crate::signer::{MuSigSigner, UsedNonces};
+use crate::transport::{InMemoryTransport, TcpTransport, Transport};
+use crate::two_round::MuSigTwoRoundSigner;
 use crate::verifier::MuSigVerifier;
-use bellman::pairing::bn256::Bn256;
-use bellman::Field;
+use crate::wire::{
+    collect_commitments, collect_pre_commitments, collect_signature_shares, Payload, Round,
+    SessionId, WireMessage, SESSION_ID_LENGTH, WIRE_PROTOCOL_VERSION,
+};
+use bellman::pairing::bn256::{Bn256, Fr};
+use bellman::{Field, PrimeField, PrimeFieldRepr};
 use franklin_crypto::alt_babyjubjub::{fs::Fs, AltJubjubBn256};
 use franklin_crypto::eddsa::{PrivateKey, PublicKey, Signature};
 use franklin_crypto::jubjub::edwards::Point;
-use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine};
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, JubjubParams, Unknown};
+use franklin_crypto::poseidon::bn256::Bn256PoseidonParams;
 use franklin_crypto::rescue::{bn256::Bn256RescueParams, RescueEngine};
 use rand::{Rng, SeedableRng, XorShiftRng};
 
 use sha2::{Digest, Sha256};
+use std::io;
+use std::net::{SocketAddr, TcpListener};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
 
 pub fn random_message_hash(rng: &mut impl Rng) -> Vec<u8> {
     let size = 32;
@@ -38,7 +61,10 @@ fn test_musig_single_party() {
 
     let number_of_parties = 1;
 
-    let (privkeys, pubkeys, mut signers) = musig_test_bn256_setup(number_of_parties).unwrap();
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
 
     assert!(musig_multi_party_test_runner(
         rng,
@@ -47,7 +73,7 @@ fn test_musig_single_party() {
         &privkeys,
         &mut signers,
         &jubjub_params,
-        &rescue_params,
+        &hash_suite,
         generator
     )
     .is_ok());
@@ -64,35 +90,1254 @@ fn test_musig_multiparty_full_round() {
 
     let number_of_parties = 5;
 
-    let (privkeys, pubkeys, mut signers) = musig_test_bn256_setup(number_of_parties).unwrap();
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
 
-    assert!(musig_multi_party_test_runner(
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    let signature = musig_multi_party_test_runner(
         rng,
         &message,
         &pubkeys,
         &privkeys,
         &mut signers,
         &jubjub_params,
+        &hash_suite,
+        generator,
+    )
+    .unwrap();
+
+    // default suite produces signatures of franklin_crypto
+    let (aggregated_pubkey, _) =
+        AggregatedPublicKey::compute_for_each_party(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+    assert!(aggregated_pubkey.verify_musig_rescue(
+        &message,
+        &signature,
+        generator,
         &rescue_params,
-        generator
+        &jubjub_params
+    ));
+}
+
+#[test]
+fn test_musig_hash_suites() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let poseidon_params = Bn256PoseidonParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let number_of_parties = 3;
+
+    fn run_with<H: HashSuite<Bn256> + Clone>(
+        rng: &mut XorShiftRng,
+        message: &[u8],
+        number_of_parties: usize,
+        jubjub_params: &AltJubjubBn256,
+        hash_suite: H,
+    ) -> (Vec<PublicKey<Bn256>>, Signature<Bn256>) {
+        let (privkeys, pubkeys, mut signers) =
+            musig_test_bn256_setup(number_of_parties, jubjub_params, hash_suite.clone()).unwrap();
+
+        let signature = musig_multi_party_test_runner(
+            rng,
+            message,
+            &pubkeys,
+            &privkeys,
+            &mut signers,
+            jubjub_params,
+            &hash_suite,
+            FixedGenerators::SpendingKeyGenerator,
+        )
+        .unwrap();
+
+        (pubkeys, signature)
+    }
+
+    let default_suite = DefaultHashSuite::new(&rescue_params);
+    let rescue_suite = RescueHashSuite::new(&rescue_params);
+    let poseidon_suite = PoseidonHashSuite::new(&poseidon_params);
+    let sha256_suite = Sha256HashSuite::new();
+
+    let (pubkeys, rescue_signature) = run_with(
+        rng,
+        &message,
+        number_of_parties,
+        &jubjub_params,
+        rescue_suite,
+    );
+    let (_, poseidon_signature) = run_with(
+        rng,
+        &message,
+        number_of_parties,
+        &jubjub_params,
+        poseidon_suite,
+    );
+    let (_, sha256_signature) = run_with(
+        rng,
+        &message,
+        number_of_parties,
+        &jubjub_params,
+        sha256_suite,
+    );
+
+    // signatures are valid only under the suite they were produced with
+    for signature in [&rescue_signature, &poseidon_signature, &sha256_signature] {
+        assert!(!MuSigVerifier::verify(
+            &message,
+            &pubkeys,
+            signature,
+            &jubjub_params,
+            generator,
+            &default_suite,
+        )
+        .unwrap());
+    }
+    assert!(!MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &sha256_signature,
+        &jubjub_params,
+        generator,
+        &poseidon_suite,
     )
-    .is_ok());
+    .unwrap());
+    assert!(!MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &poseidon_signature,
+        &jubjub_params,
+        generator,
+        &sha256_suite,
+    )
+    .unwrap());
+}
+
+#[test]
+fn test_musig_domain_tags() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let number_of_parties = 3;
+
+    let hash_suite = DefaultHashSuite::new_with_tag(&rescue_params, b"application a");
+
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    let signature = musig_multi_party_test_runner(
+        rng,
+        &message,
+        &pubkeys,
+        &privkeys,
+        &mut signers,
+        &jubjub_params,
+        &hash_suite,
+        generator,
+    )
+    .unwrap();
+
+    // verification requires the same tag
+    for other_suite in [
+        DefaultHashSuite::new(&rescue_params),
+        DefaultHashSuite::new_with_tag(&rescue_params, b"application b"),
+        DefaultHashSuite::new_with_tag(&rescue_params, b"application"),
+    ] {
+        assert!(!MuSigVerifier::verify(
+            &message,
+            &pubkeys,
+            &signature,
+            &jubjub_params,
+            generator,
+            &other_suite,
+        )
+        .unwrap());
+    }
+
+    // pre-commitments are bound to the tag as well
+    let (_, _, mut tagged_signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+    let (_, _, mut untagged_signers) = musig_test_bn256_setup(
+        number_of_parties,
+        &jubjub_params,
+        DefaultHashSuite::new(&rescue_params),
+    )
+    .unwrap();
+
+    let pre_commitment = tagged_signers[0].compute_precommitment(rng).unwrap();
+    let pre_commitments = vec![pre_commitment; number_of_parties];
+    let commitment = tagged_signers[0]
+        .receive_precommitments(&pre_commitments)
+        .unwrap();
+
+    untagged_signers[0].compute_precommitment(rng).unwrap();
+    untagged_signers[0]
+        .receive_precommitments(&pre_commitments)
+        .unwrap();
+    assert_eq!(
+        untagged_signers[0]
+            .receive_commitments(&vec![commitment; number_of_parties])
+            .unwrap_err(),
+        MusigError::InvalidCommitment
+    );
+
+    // session hashes follow the suite and its tag
+    let session_id = [1u8; SESSION_ID_LENGTH];
+    let untagged_suite = DefaultHashSuite::new(&rescue_params);
+    let sha256_suite = Sha256HashSuite::<Bn256>::new_with_tag(b"application a");
+    assert!(
+        hash_suite.hash_session_message(&session_id, &message)
+            != untagged_suite.hash_session_message(&session_id, &message)
+    );
+    assert!(
+        hash_suite.hash_session_commitment(&session_id, 0, &[2u8; 32], &[3u8; 32])
+            != untagged_suite.hash_session_commitment(&session_id, 0, &[2u8; 32], &[3u8; 32])
+    );
+    let poseidon_params = Bn256PoseidonParams::new_checked_2_into_1();
+    let poseidon_suite =
+        PoseidonHashSuite::<Bn256>::new_with_tag(&poseidon_params, b"application a");
+    assert!(
+        poseidon_suite.hash_session_message(&session_id, &message)
+            != sha256_suite.hash_session_message(&session_id, &message)
+    );
+
+    // tags are prepended as fixed-length prefixes, so a tag followed
+    // by data can't be read as a shorter tag followed by other data
+    assert_eq!(
+        tag_prefix(b"application").len(),
+        tag_prefix(b"application a").len()
+    );
+    assert!(tag_prefix(b"application a").starts_with(&Sha256::digest(b"application a")));
+    assert!(tag_prefix(&[]).is_empty());
+}
+
+#[test]
+fn test_sha256_challenge_vectors() {
+    // (persona, a, b, uint256(sha256(persona || a || b)) % order)
+    let vectors: Vec<(&[u8], Vec<u8>, Vec<u8>, &str)> = vec![
+        (
+            &b""[..],
+            vec![],
+            vec![],
+            "1754212967297449126645585750766425504957783678331046402971417546023112284032",
+        ),
+        (
+            &b""[..],
+            (1..=32).collect(),
+            vec![0; 32],
+            "1307834936301803753356851649538727550314881380916869981303707466094925938104",
+        ),
+        (
+            &b"musig"[..],
+            b"abc".to_vec(),
+            vec![],
+            "171440862398840462372418769602669597305654748658937551546135846119352370851",
+        ),
+        (
+            &b""[..],
+            vec![0xff; 64],
+            vec![0xff; 32],
+            "330532950567535585498321344322358499186775098020846464981566370845151921551",
+        ),
+    ];
+
+    for (persona, a, b, expected) in vectors {
+        assert_eq!(
+            sha256_hash_to_scalar::<Bn256>(persona, &a, &b),
+            Fs::from_str(expected).unwrap()
+        );
+    }
+}
+
+#[test]
+fn test_musig_sha256_challenge() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let number_of_parties = 3;
+
+    let hash_suite = Sha256ChallengeHashSuite::new();
+
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    let signature = musig_multi_party_test_runner(
+        rng,
+        &message,
+        &pubkeys,
+        &privkeys,
+        &mut signers,
+        &jubjub_params,
+        &hash_suite,
+        generator,
+    )
+    .unwrap();
+
+    // aggregated key is the same as with the default suite
+    let (aggregated_pubkey, _) =
+        AggregatedPublicKey::compute_for_each_party(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+    let (default_aggregated_pubkey, _) = AggregatedPublicKey::compute_for_each_party(
+        &pubkeys,
+        &jubjub_params,
+        &DefaultHashSuite::new(&rescue_params),
+    )
+    .unwrap();
+    assert!(aggregated_pubkey.0.eq(&default_aggregated_pubkey.0));
+
+    // but the signature is not a rescue one
+    assert!(!aggregated_pubkey.verify_musig_rescue(
+        &message,
+        &signature,
+        generator,
+        &rescue_params,
+        &jubjub_params
+    ));
+
+    // verification the way an EVM contract does it: digest is read
+    // as a big-endian integer and reduced modulo the group order
+    let mut preimage = vec![];
+    write_point(&aggregated_pubkey.0, &mut preimage);
+    write_point(&signature.r, &mut preimage);
+    preimage.extend_from_slice(&message);
+    preimage.resize(3 * 32, 0);
+
+    let base = Fs::from_str("256").unwrap();
+    let mut c = Fs::zero();
+    for byte in Sha256::digest(&preimage).iter() {
+        c.mul_assign(&base);
+        c.add_assign(&Fs::from_str(&byte.to_string()).unwrap());
+    }
+
+    let lhs = Point::from(
+        jubjub_params
+            .generator(generator)
+            .mul(signature.s, &jubjub_params),
+    );
+    let rhs = signature
+        .r
+        .add(&aggregated_pubkey.0.mul(c, &jubjub_params), &jubjub_params);
+    assert!(lhs.eq(&rhs));
+}
+
+#[test]
+fn test_musig_adaptor_signature() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(3, &jubjub_params, hash_suite).unwrap();
+    let (aggregated_pubkey, _) =
+        AggregatedPublicKey::compute_for_each_party(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+
+    // T = t * G is known to all parties, t only to the counterparty
+    let adaptor_secret: Fs = rng.gen();
+    let adaptor_point: Point<Bn256, Unknown> = jubjub_params
+        .generator(generator)
+        .mul(adaptor_secret, &jubjub_params)
+        .into();
+    let other_adaptor_point: Point<Bn256, Unknown> = jubjub_params
+        .generator(generator)
+        .mul(rng.gen::<Fs>(), &jubjub_params)
+        .into();
+
+    // T is bound before pre-commitments
+    let mut pre_commitments = vec![];
+    for signer in signers.iter_mut() {
+        signer.set_adaptor_point(adaptor_point).unwrap();
+        pre_commitments.push(signer.compute_precommitment(rng).unwrap());
+    }
+    assert_eq!(
+        signers[0]
+            .set_adaptor_point(other_adaptor_point)
+            .unwrap_err(),
+        MusigError::SessionAlreadyStarted
+    );
+    let mut commitments = vec![];
+    for signer in signers.iter_mut() {
+        commitments.push(signer.receive_precommitments(&pre_commitments).unwrap());
+    }
+    assert_eq!(
+        pre_commitments[0],
+        hash_suite.hash_adaptor_commitment(&commitments[0], &adaptor_point)
+    );
+    assert!(pre_commitments[0] != hash_suite.hash_commitment(&commitments[0]));
+    for signer in signers.iter_mut() {
+        signer.receive_commitments(&commitments).unwrap();
+    }
+
+    // shares are refused for another adaptor point or without it
+    assert_eq!(
+        signers[0]
+            .sign_with_adaptor(&privkeys[0], &message, &other_adaptor_point)
+            .unwrap_err(),
+        MusigError::AdaptorPointMismatch
+    );
+    assert_eq!(
+        signers[0].sign(&privkeys[0], &message).unwrap_err(),
+        MusigError::AdaptorPointMismatch
+    );
+
+    let mut signature_shares = vec![];
+    for (position, signer) in signers.iter_mut().enumerate() {
+        signature_shares.push(
+            signer
+                .sign_with_adaptor(&privkeys[position], &message, &adaptor_point)
+                .unwrap(),
+        );
+    }
+
+    assert_eq!(
+        signers[0]
+            .receive_signatures(&signature_shares)
+            .err()
+            .unwrap(),
+        MusigError::PreSignatureExpected
+    );
+
+    // each share is verified against the adapted challenge
+    let mut invalid_shares = signature_shares.clone();
+    invalid_shares[1].add_assign(&Fs::one());
+    assert_eq!(
+        signers[0]
+            .receive_pre_signatures(&invalid_shares)
+            .err()
+            .unwrap(),
+        MusigError::InvalidSignatureShare
+    );
+
+    let pre_signature = signers[0]
+        .receive_pre_signatures(&signature_shares)
+        .unwrap();
+    assert!(pre_signature.verify(
+        &message,
+        &aggregated_pubkey,
+        &jubjub_params,
+        generator,
+        &hash_suite
+    ));
+
+    // pre-signature is not a valid signature by itself
+    let incomplete_signature = Signature {
+        r: pre_signature.r,
+        s: pre_signature.s,
+    };
+    assert!(!MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &incomplete_signature,
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+
+    // pre-signature for another adaptor point
+    let mut other_pre_signature = pre_signature.clone();
+    other_pre_signature.adaptor_point = jubjub_params
+        .generator(generator)
+        .mul(rng.gen::<Fs>(), &jubjub_params)
+        .into();
+    assert!(!other_pre_signature.verify(
+        &message,
+        &aggregated_pubkey,
+        &jubjub_params,
+        generator,
+        &hash_suite
+    ));
+
+    let signature = pre_signature.complete(&adaptor_secret, &jubjub_params);
+    assert!(MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &signature,
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+    assert!(aggregated_pubkey.verify_musig_rescue(
+        &message,
+        &signature,
+        generator,
+        &rescue_params,
+        &jubjub_params
+    ));
+
+    // published signature reveals the secret
+    assert_eq!(
+        pre_signature
+            .extract_secret(&signature, &jubjub_params, generator)
+            .unwrap(),
+        adaptor_secret
+    );
+
+    // completed with a wrong secret
+    let mut wrong_secret = adaptor_secret;
+    wrong_secret.add_assign(&Fs::one());
+    let wrong_signature = pre_signature.complete(&wrong_secret, &jubjub_params);
+    assert!(!MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &wrong_signature,
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+    assert_eq!(
+        pre_signature
+            .extract_secret(&wrong_signature, &jubjub_params, generator)
+            .unwrap_err(),
+        MusigError::AdaptorSecretMismatch
+    );
+
+    // adaptor point survives state export
+    let state = signers[1].export_state();
+    let restored_signer = MuSigSigner::import_state(
+        &state,
+        &jubjub_params,
+        generator,
+        hash_suite,
+        &UsedNonces::new(),
+    )
+    .unwrap();
+    let restored_pre_signature = restored_signer
+        .receive_pre_signatures(&signature_shares)
+        .unwrap();
+    assert!(restored_pre_signature.adaptor_point == adaptor_point);
+    assert_eq!(restored_pre_signature.s, pre_signature.s);
+
+    // regular signing doesn't produce a pre-signature
+    let (privkeys, _, mut signers) = musig_test_bn256_setup(2, &jubjub_params, hash_suite).unwrap();
+    let pre_commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.compute_precommitment(rng).unwrap())
+        .collect();
+    let commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.receive_precommitments(&pre_commitments).unwrap())
+        .collect();
+    for signer in signers.iter_mut() {
+        signer.receive_commitments(&commitments).unwrap();
+    }
+    let signature_shares: Vec<_> = signers
+        .iter_mut()
+        .zip(privkeys.iter())
+        .map(|(signer, privkey)| signer.sign(privkey, &message).unwrap())
+        .collect();
+    assert_eq!(
+        signers[0]
+            .receive_pre_signatures(&signature_shares)
+            .err()
+            .unwrap(),
+        MusigError::AdaptorPointNotSet
+    );
+}
+
+#[test]
+fn test_musig_key_tweaks() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+    let number_of_parties = 3;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, _) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+    let context = KeyAggregationContext::new(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+
+    // plain tweak, X' + t * G
+    let tweak: Fs = rng.gen();
+    let mut tweaked_context = context.clone();
+    tweaked_context
+        .apply_tweak(&tweak, &jubjub_params, generator)
+        .unwrap();
+    let expected_pubkey = context.aggregated_pubkey.0.add(
+        &jubjub_params
+            .generator(generator)
+            .mul(tweak, &jubjub_params)
+            .into(),
+        &jubjub_params,
+    );
+    assert!(tweaked_context.aggregated_pubkey.0 == expected_pubkey);
+
+    // taproot-style tweak commits to data
+    let data = b"alternative spending conditions";
+    let mut taproot_context = context.clone();
+    let taproot_tweak = taproot_context
+        .apply_taproot_tweak(data, &jubjub_params, generator, &hash_suite)
+        .unwrap();
+    assert_eq!(
+        taproot_tweak,
+        hash_suite.hash_tweak(&context.aggregated_pubkey, data)
+    );
+    assert!(taproot_tweak != hash_suite.hash_tweak(&context.aggregated_pubkey, b"other data"));
+
+    for tweaked_context in [&tweaked_context, &taproot_context] {
+        let mut signers: Vec<_> = (0..number_of_parties)
+            .map(|position| {
+                MuSigSigner::new_with_context(
+                    tweaked_context,
+                    position,
+                    &jubjub_params,
+                    generator,
+                    hash_suite,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        let signature_shares = musig_signing_rounds(rng, &message, &privkeys, &mut signers);
+
+        // tweak survives state export
+        let state = signers[2].export_state();
+        let restored_signer = MuSigSigner::import_state(
+            &state,
+            &jubjub_params,
+            generator,
+            hash_suite,
+            &UsedNonces::new(),
+        )
+        .unwrap();
+
+        for signer in [&signers[0], &restored_signer] {
+            let signature = signer.receive_signatures(&signature_shares).unwrap();
+
+            assert!(MuSigVerifier::verify_with_context(
+                &message,
+                tweaked_context,
+                &signature,
+                &jubjub_params,
+                generator,
+                &hash_suite
+            ));
+            assert!(MuSigVerifier::verify_aggregated(
+                &message,
+                &tweaked_context.aggregated_pubkey,
+                &signature,
+                &jubjub_params,
+                generator,
+                &hash_suite
+            ));
+            assert!(tweaked_context.aggregated_pubkey.verify_musig_rescue(
+                &message,
+                &signature,
+                generator,
+                &rescue_params,
+                &jubjub_params
+            ));
+
+            // signature is not valid for untweaked key
+            assert!(!MuSigVerifier::verify(
+                &message,
+                &pubkeys,
+                &signature,
+                &jubjub_params,
+                generator,
+                &hash_suite,
+            )
+            .unwrap());
+        }
+    }
+
+    // tweak which cancels aggregated key out
+    let mut cancelling_context = context.clone();
+    cancelling_context
+        .apply_tweak(&tweak, &jubjub_params, generator)
+        .unwrap();
+    let mut negated_tweak = tweak;
+    negated_tweak.negate();
+    cancelling_context
+        .apply_tweak(&negated_tweak, &jubjub_params, generator)
+        .unwrap();
+    assert!(cancelling_context.aggregated_pubkey.0 == context.aggregated_pubkey.0);
+    assert!(cancelling_context.tweak.is_zero());
+
+    let mut single_context =
+        KeyAggregationContext::new(&pubkeys[..1], &jubjub_params, &hash_suite).unwrap();
+    let mut secret_tweak = privkeys[0].0;
+    secret_tweak.negate();
+    assert_eq!(
+        single_context
+            .apply_tweak(&secret_tweak, &jubjub_params, generator)
+            .unwrap_err(),
+        MusigError::InvalidTweak
+    );
+}
+
+#[test]
+fn test_musig_nested() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    // sub-group of three members is the last party of the parent group
+    let sub_privkeys: Vec<_> = (0..3).map(|_| PrivateKey::<Bn256>(rng.gen())).collect();
+    let sub_pubkeys: Vec<_> = sub_privkeys
+        .iter()
+        .map(|privkey| PublicKey::from_private(privkey, generator, &jubjub_params))
+        .collect();
+    let sub_context =
+        KeyAggregationContext::new(&sub_pubkeys, &jubjub_params, &hash_suite).unwrap();
+    let mut sub_signers: Vec<_> = (0..sub_pubkeys.len())
+        .map(|position| {
+            MuSigSigner::new_with_context(
+                &sub_context,
+                position,
+                &jubjub_params,
+                generator,
+                hash_suite,
+            )
+            .unwrap()
+        })
+        .collect();
+
+    let (privkeys, mut pubkeys, _) = musig_test_bn256_setup(2, &jubjub_params, hash_suite).unwrap();
+    pubkeys.push(sub_context.aggregated_pubkey.clone());
+    let mut signers: Vec<_> = (0..pubkeys.len())
+        .map(|position| {
+            MuSigSigner::new(&pubkeys, position, &jubjub_params, generator, hash_suite).unwrap()
+        })
+        .collect();
+
+    // sub-group agrees on its commitment first
+    let sub_pre_commitments: Vec<_> = sub_signers
+        .iter_mut()
+        .map(|signer| signer.compute_precommitment(rng).unwrap())
+        .collect();
+    let sub_commitments: Vec<_> = sub_signers
+        .iter_mut()
+        .map(|signer| signer.receive_precommitments(&sub_pre_commitments).unwrap())
+        .collect();
+    let sub_aggregated_commitments: Vec<_> = sub_signers
+        .iter_mut()
+        .map(|signer| signer.receive_commitments(&sub_commitments).unwrap())
+        .collect();
+
+    // identity point can't stand for the sub-group commitment
+    assert_eq!(
+        signers[2]
+            .receive_nested_commitment(&Point::zero())
+            .unwrap_err(),
+        MusigError::IdentityCommitment
+    );
+
+    let pre_commitments = vec![
+        signers[0].compute_precommitment(rng).unwrap(),
+        signers[1].compute_precommitment(rng).unwrap(),
+        signers[2]
+            .receive_nested_commitment(&sub_aggregated_commitments[0])
+            .unwrap(),
+    ];
+    let commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.receive_precommitments(&pre_commitments).unwrap())
+        .collect();
+    for signer in signers.iter_mut() {
+        signer.receive_commitments(&commitments).unwrap();
+    }
+
+    // members recompute the parent challenge and a_i of the sub-group
+    // from the parent context, commitments and message
+    let parent_context = KeyAggregationContext::new(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+    assert_eq!(
+        sub_signers[0]
+            .sign_nested(
+                &sub_privkeys[0],
+                &parent_context,
+                1,
+                &commitments,
+                None,
+                &message,
+            )
+            .unwrap_err(),
+        MusigError::InvalidPublicKey
+    );
+    let mut wrong_commitments = commitments.clone();
+    wrong_commitments.swap(0, 2);
+    assert_eq!(
+        sub_signers[0]
+            .sign_nested(
+                &sub_privkeys[0],
+                &parent_context,
+                2,
+                &wrong_commitments,
+                None,
+                &message,
+            )
+            .unwrap_err(),
+        MusigError::InvalidCommitment
+    );
+
+    let nested_challenge = signers[2].compute_nested_challenge(&message).unwrap();
+    let sub_signature_shares: Vec<_> = sub_signers
+        .iter_mut()
+        .zip(sub_privkeys.iter())
+        .map(|(signer, privkey)| {
+            signer
+                .sign_nested(privkey, &parent_context, 2, &commitments, None, &message)
+                .unwrap()
+        })
+        .collect();
+
+    let mut invalid_sub_shares = sub_signature_shares.clone();
+    invalid_sub_shares[0].add_assign(&Fs::one());
+    assert_eq!(
+        sub_signers[1]
+            .receive_nested_signatures(&invalid_sub_shares)
+            .unwrap_err(),
+        MusigError::InvalidSignatureShare
+    );
+
+    let sub_signature_share = sub_signers[1]
+        .receive_nested_signatures(&sub_signature_shares)
+        .unwrap();
+
+    // parent signer of the sub-group has no nonce to sign with
+    assert_eq!(
+        signers[2].sign(&sub_privkeys[0], &message).unwrap_err(),
+        MusigError::NonceAlreadyUsed
+    );
+
+    let signature_shares = vec![
+        signers[0].sign(&privkeys[0], &message).unwrap(),
+        signers[1].sign(&privkeys[1], &message).unwrap(),
+        sub_signature_share,
+    ];
+
+    for signer in signers.iter() {
+        let signature = signer.receive_signatures(&signature_shares).unwrap();
+
+        assert!(MuSigVerifier::verify(
+            &message,
+            &pubkeys,
+            &signature,
+            &jubjub_params,
+            generator,
+            &hash_suite,
+        )
+        .unwrap());
+
+        let (aggregated_pubkey, _) =
+            AggregatedPublicKey::compute_for_each_party(&pubkeys, &jubjub_params, &hash_suite)
+                .unwrap();
+        assert!(aggregated_pubkey.verify_musig_rescue(
+            &message,
+            &signature,
+            generator,
+            &rescue_params,
+            &jubjub_params
+        ));
+    }
+
+    // tampered share of the sub-group is rejected by the parent
+    let mut wrong_shares = signature_shares.clone();
+    let mut wrong_share = sub_signature_share;
+    wrong_share.add_assign(&nested_challenge);
+    wrong_shares[2] = wrong_share;
+    assert_eq!(
+        signers[0].receive_signatures(&wrong_shares).unwrap_err(),
+        MusigError::InvalidSignatureShare
+    );
+}
+
+#[test]
+fn test_musig_batch_signing() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let number_of_parties = 3;
+    let batch_size = 5;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let messages: Vec<_> = (0..batch_size).map(|_| random_message_hash(rng)).collect();
+
+    let (privkeys, pubkeys, _) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+    let context = KeyAggregationContext::new(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+
+    let mut signers: Vec<_> = (0..number_of_parties)
+        .map(|position| {
+            MuSigBatchSigner::new_with_context(
+                &context,
+                position,
+                batch_size,
+                &jubjub_params,
+                generator,
+                hash_suite,
+            )
+            .unwrap()
+        })
+        .collect();
+
+    // one exchange per round for the whole batch
+    let pre_commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.compute_precommitments(rng).unwrap())
+        .collect();
+
+    let mut short_pre_commitments = pre_commitments.clone();
+    short_pre_commitments[1].pop();
+    assert_eq!(
+        signers[0]
+            .receive_precommitments(&short_pre_commitments)
+            .unwrap_err(),
+        MusigError::InvalidBatchSize
+    );
+
+    let commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.receive_precommitments(&pre_commitments).unwrap())
+        .collect();
+    let aggregated_commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.receive_commitments(&commitments).unwrap())
+        .collect();
+
+    // each message has its own nonce
+    for (i, commitment) in aggregated_commitments[0].iter().enumerate() {
+        for other_commitment in aggregated_commitments[0][i + 1..].iter() {
+            assert!(commitment != other_commitment);
+        }
+    }
+
+    assert_eq!(
+        signers[0].sign(&privkeys[0], &messages[1..]).unwrap_err(),
+        MusigError::InvalidBatchSize
+    );
+
+    let signature_shares: Vec<_> = signers
+        .iter_mut()
+        .zip(privkeys.iter())
+        .map(|(signer, privkey)| signer.sign(privkey, &messages).unwrap())
+        .collect();
+
+    let mut tampered_shares = signature_shares.clone();
+    tampered_shares[2][3].add_assign(&Fs::one());
+    assert_eq!(
+        signers[0].receive_signatures(&tampered_shares).unwrap_err(),
+        MusigError::InvalidSignatureShare
+    );
+
+    for signer in signers.iter() {
+        let signatures = signer.receive_signatures(&signature_shares).unwrap();
+        assert_eq!(signatures.len(), batch_size);
+
+        for (message, signature) in messages.iter().zip(signatures.iter()) {
+            assert!(MuSigVerifier::verify_with_context(
+                message,
+                &context,
+                signature,
+                &jubjub_params,
+                generator,
+                &hash_suite
+            ));
+        }
+
+        // signatures are bound to their messages
+        assert!(!MuSigVerifier::verify_with_context(
+            &messages[0],
+            &context,
+            &signatures[1],
+            &jubjub_params,
+            generator,
+            &hash_suite
+        ));
+    }
+
+    // signing the batch again would reuse nonces
+    assert_eq!(
+        signers[0].sign(&privkeys[0], &messages).unwrap_err(),
+        MusigError::NonceAlreadyUsed
+    );
+
+    assert_eq!(
+        MuSigBatchSigner::new(&pubkeys, 0, 0, &jubjub_params, generator, hash_suite)
+            .err()
+            .unwrap(),
+        MusigError::InvalidBatchSize
+    );
+}
+
+#[test]
+fn test_musig_two_round() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+    let number_of_parties = 3;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, _) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    let mut signers: Vec<_> = (0..number_of_parties)
+        .map(|position| {
+            MuSigTwoRoundSigner::new(&pubkeys, position, &jubjub_params, generator, hash_suite)
+                .unwrap()
+        })
+        .collect();
+
+    assert_eq!(
+        signers[0].sign(&privkeys[0], &message).unwrap_err(),
+        MusigError::NonceCommitmentsNotReceived
+    );
+
+    // first round doesn't depend on the message
+    let commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.compute_commitments(rng).unwrap())
+        .collect();
+    assert_eq!(
+        signers[0]
+            .receive_commitments(&commitments[1..])
+            .unwrap_err(),
+        MusigError::NonceCommitmentsAndParticipantsNotMatch
+    );
+    // nonces of a party are never zero
+    let mut identity_commitments = commitments.clone();
+    identity_commitments[1].1 = Point::zero();
+    assert_eq!(
+        signers[0]
+            .receive_commitments(&identity_commitments)
+            .unwrap_err(),
+        MusigError::IdentityCommitment
+    );
+    for signer in signers.iter_mut() {
+        signer.receive_commitments(&commitments).unwrap();
+    }
+
+    let signature_shares: Vec<_> = signers
+        .iter_mut()
+        .zip(privkeys.iter())
+        .map(|(signer, privkey)| signer.sign(privkey, &message).unwrap())
+        .collect();
+
+    assert_eq!(
+        signers[0].sign(&privkeys[0], &message).unwrap_err(),
+        MusigError::NonceAlreadyUsed
+    );
+
+    let mut tampered_shares = signature_shares.clone();
+    tampered_shares[1].add_assign(&Fs::one());
+    assert_eq!(
+        signers[0].receive_signatures(&tampered_shares).unwrap_err(),
+        MusigError::InvalidSignatureShare
+    );
+
+    let (aggregated_pubkey, _) =
+        AggregatedPublicKey::compute_for_each_party(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+    for signer in signers.iter() {
+        let signature = signer.receive_signatures(&signature_shares).unwrap();
+
+        assert!(MuSigVerifier::verify(
+            &message,
+            &pubkeys,
+            &signature,
+            &jubjub_params,
+            generator,
+            &hash_suite,
+        )
+        .unwrap());
+        assert!(aggregated_pubkey.verify_musig_rescue(
+            &message,
+            &signature,
+            generator,
+            &rescue_params,
+            &jubjub_params
+        ));
+    }
+}
+
+#[test]
+fn test_musig_nonce_pool() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let number_of_parties = 3;
+    let pool_size = 4;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, _) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+    let context = KeyAggregationContext::new(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+
+    let mut pools: Vec<_> = (0..number_of_parties)
+        .map(|position| {
+            TwoRoundNoncePool::new_with_context(
+                &context,
+                position,
+                pool_size,
+                &jubjub_params,
+                generator,
+                hash_suite,
+            )
+            .unwrap()
+        })
+        .collect();
+
+    // entry is not used if pre-processing hasn't been finished
+    assert_eq!(
+        pools[0].sign(0, &privkeys[0], &[1, 2, 3]).unwrap_err(),
+        MusigError::NonceCommitmentsNotReceived
+    );
+    assert!(!pools[0].is_used(0).unwrap());
+
+    // pre-processing takes one round
+    let commitments: Vec<_> = pools
+        .iter_mut()
+        .map(|pool| pool.compute_commitments(rng).unwrap())
+        .collect();
+    for pool in pools.iter_mut() {
+        pool.receive_commitments(&commitments).unwrap();
+    }
+
+    // entries are consumed in any order as messages arrive
+    for index in [2, 0] {
+        let message = random_message_hash(rng);
+        let signature_shares: Vec<_> = pools
+            .iter_mut()
+            .zip(privkeys.iter())
+            .map(|(pool, privkey)| pool.sign(index, privkey, &message).unwrap())
+            .collect();
+        let signature = pools[1]
+            .receive_signatures(index, &signature_shares)
+            .unwrap();
+
+        assert!(MuSigVerifier::verify_with_context(
+            &message,
+            &context,
+            &signature,
+            &jubjub_params,
+            generator,
+            &hash_suite
+        ));
+    }
+
+    assert_eq!(pools[0].remaining(), pool_size - 2);
+    assert_eq!(pools[0].next_unused(), Some(1));
+    assert!(pools[0].is_used(2).unwrap());
+    assert!(!pools[0].is_used(3).unwrap());
+
+    // used entry is never used again
+    assert_eq!(
+        pools[0].sign(2, &privkeys[0], &[1, 2, 3]).unwrap_err(),
+        MusigError::NonceAlreadyUsed
+    );
+    assert_eq!(
+        pools[0]
+            .sign(pool_size, &privkeys[0], &[1, 2, 3])
+            .unwrap_err(),
+        MusigError::InvalidPoolEntry
+    );
+
+    while let Some(index) = pools[0].next_unused() {
+        let message = random_message_hash(rng);
+        let signature_shares: Vec<_> = pools
+            .iter_mut()
+            .zip(privkeys.iter())
+            .map(|(pool, privkey)| pool.sign(index, privkey, &message).unwrap())
+            .collect();
+        let signature = pools[0]
+            .receive_signatures(index, &signature_shares)
+            .unwrap();
+
+        assert!(MuSigVerifier::verify_with_context(
+            &message,
+            &context,
+            &signature,
+            &jubjub_params,
+            generator,
+            &hash_suite
+        ));
+    }
+    assert_eq!(pools[0].remaining(), 0);
+}
+
+/// Runs the first three rounds and returns signature shares of all parties
+pub fn musig_signing_rounds<E: JubjubEngine + RescueEngine, H: HashSuite<E>>(
+    rng: &mut impl Rng,
+    message: &[u8],
+    privkeys: &[PrivateKey<E>],
+    signers: &mut [MuSigSigner<E, H>],
+) -> Vec<E::Fs> {
+    let pre_commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.compute_precommitment(rng).unwrap())
+        .collect();
+    let commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.receive_precommitments(&pre_commitments).unwrap())
+        .collect();
+    for signer in signers.iter_mut() {
+        signer.receive_commitments(&commitments).unwrap();
+    }
+
+    signers
+        .iter_mut()
+        .zip(privkeys.iter())
+        .map(|(signer, privkey)| signer.sign(privkey, message).unwrap())
+        .collect()
 }
 
 #[allow(clippy::type_complexity)]
-pub fn musig_test_bn256_setup(
+pub fn musig_test_bn256_setup<H: HashSuite<Bn256> + Clone>(
     number_of_participants: usize,
+    jubjub_params: &AltJubjubBn256,
+    hash_suite: H,
 ) -> Result<
     (
         Vec<PrivateKey<Bn256>>,
         Vec<PublicKey<Bn256>>,
-        Vec<MuSigSigner<Bn256>>,
+        Vec<MuSigSigner<Bn256, H>>,
     ),
     MusigError,
 > {
     let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
     let generator = FixedGenerators::SpendingKeyGenerator;
-    let jubjub_params = AltJubjubBn256::new();
 
     let mut privkeys = vec![];
     let mut pubkeys = vec![];
@@ -100,32 +1345,51 @@ pub fn musig_test_bn256_setup(
 
     for _ in 0..number_of_participants {
         let privkey = PrivateKey::<Bn256>(rng.gen());
-        let pubkey = PublicKey::from_private(&privkey, generator, &jubjub_params);
+        let pubkey = PublicKey::from_private(&privkey, generator, jubjub_params);
+
+        privkeys.push(privkey);
+        pubkeys.push(pubkey);
+    }
+
+    for position in 0..privkeys.len() {
+        let signer = MuSigSigner::new(
+            &pubkeys,
+            position,
+            jubjub_params,
+            generator,
+            hash_suite.clone(),
+        )?;
+        signers.push(signer);
+    }
+
+    Ok((privkeys, pubkeys, signers))
+}
+
+#[test]
+fn test_musig_signers_share_params() {
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
 
-        privkeys.push(privkey);
-        pubkeys.push(pubkey);
-    }
+    let (_, _, signers) = musig_test_bn256_setup(3, &jubjub_params, hash_suite).unwrap();
 
-    for position in 0..privkeys.len() {
-        let signer =
-            MuSigSigner::<Bn256>::new(&pubkeys, position, AltJubjubBn256::new(), generator)?;
-        signers.push(signer);
+    // parameters are borrowed, not copied into each signer
+    for signer in signers.iter() {
+        assert!(std::ptr::eq(signer.jubjub_params(), &jubjub_params));
     }
-
-    Ok((privkeys, pubkeys, signers))
 }
 
 #[allow(clippy::too_many_arguments)]
-fn musig_multi_party_test_runner<E: JubjubEngine + RescueEngine>(
+pub fn musig_multi_party_test_runner<E: JubjubEngine + RescueEngine, H: HashSuite<E>>(
     rng: &mut impl Rng,
     message: &[u8],
     pubkeys: &[PublicKey<E>],
     privkeys: &[PrivateKey<E>],
-    signers: &mut [MuSigSigner<E>],
+    signers: &mut [MuSigSigner<E, H>],
     jubjub_params: &<E as JubjubEngine>::Params,
-    rescue_params: &<E as RescueEngine>::Params,
+    hash_suite: &H,
     generator: FixedGenerators,
-) -> Result<(), MusigError> {
+) -> Result<Signature<E>, MusigError> {
     let number_of_participants = privkeys.len();
 
     let mut pre_commitments = vec![vec![]; number_of_participants];
@@ -150,7 +1414,7 @@ fn musig_multi_party_test_runner<E: JubjubEngine + RescueEngine>(
 
     let mut signature_shares = vec![E::Fs::zero(); number_of_participants];
     for (position, signer) in signers.iter_mut().enumerate() {
-        signature_shares[position] = signer.sign(&privkeys[position], &message, &rescue_params)?;
+        signature_shares[position] = signer.sign(&privkeys[position], &message)?;
     }
 
     let mut aggregated_signatures = vec![
@@ -177,12 +1441,12 @@ fn musig_multi_party_test_runner<E: JubjubEngine + RescueEngine>(
             signature,
             &jubjub_params,
             generator,
-            &rescue_params,
+            hash_suite,
         )?;
         assert!(is_verified);
     }
 
-    Ok(())
+    Ok(first_signature)
 }
 
 #[test]
@@ -220,10 +1484,13 @@ fn test_musig_api_errors() {
 
     let number_of_parties = 2;
     let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
-    let (privkeys, pubkeys, _) = musig_test_bn256_setup(number_of_parties).unwrap();
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+    let (privkeys, pubkeys, _) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
 
     let generator = FixedGenerators::SpendingKeyGenerator;
-    let rescue_params = franklin_crypto::rescue::bn256::Bn256RescueParams::new_checked_2_into_1();
 
     let inputs = vec![
         TestInput {
@@ -315,8 +1582,9 @@ fn test_musig_api_errors() {
                 let result = MuSigSigner::new(
                     input.pubkeys,
                     input.position,
-                    AltJubjubBn256::new(),
+                    &jubjub_params,
                     generator,
+                    hash_suite,
                 );
                 match result {
                     Err(e) => assert_eq!(e, input.expected_error),
@@ -327,8 +1595,9 @@ fn test_musig_api_errors() {
                 let mut signer = MuSigSigner::new(
                     input.pubkeys,
                     input.position,
-                    AltJubjubBn256::new(),
+                    &jubjub_params,
                     generator,
+                    hash_suite,
                 )
                 .unwrap();
                 let result = signer.receive_precommitments(&[]);
@@ -341,8 +1610,9 @@ fn test_musig_api_errors() {
                 let mut signer = MuSigSigner::new(
                     input.pubkeys,
                     input.position,
-                    AltJubjubBn256::new(),
+                    &jubjub_params,
                     generator,
+                    hash_suite,
                 )
                 .unwrap();
                 let _ = signer.compute_precommitment(rng).unwrap();
@@ -356,8 +1626,9 @@ fn test_musig_api_errors() {
                 let mut signer = MuSigSigner::new(
                     input.pubkeys,
                     input.position,
-                    AltJubjubBn256::new(),
+                    &jubjub_params,
                     generator,
+                    hash_suite,
                 )
                 .unwrap();
                 let result = signer.receive_commitments(&[]);
@@ -370,8 +1641,9 @@ fn test_musig_api_errors() {
                 let mut signer = MuSigSigner::new(
                     input.pubkeys,
                     input.position,
-                    AltJubjubBn256::new(),
+                    &jubjub_params,
                     generator,
+                    hash_suite,
                 )
                 .unwrap();
                 let pre_commitment = signer.compute_precommitment(rng).unwrap();
@@ -388,19 +1660,16 @@ fn test_musig_api_errors() {
                 let mut signer = MuSigSigner::new(
                     input.pubkeys,
                     input.position,
-                    AltJubjubBn256::new(),
+                    &jubjub_params,
                     generator,
+                    hash_suite,
                 )
                 .unwrap();
                 let pre_commitment = signer.compute_precommitment(rng).unwrap();
                 let _ = signer
                     .receive_precommitments(&[pre_commitment.clone(), pre_commitment])
                     .unwrap();
-                let result = signer.sign(
-                    input.private_key.unwrap(),
-                    input.message.unwrap(),
-                    &rescue_params,
-                );
+                let result = signer.sign(input.private_key.unwrap(), input.message.unwrap());
                 match result {
                     Err(e) => assert_eq!(e, input.expected_error),
                     _ => panic!("expected error not received for {:?}", input),
@@ -410,8 +1679,9 @@ fn test_musig_api_errors() {
                 let mut signer = MuSigSigner::new(
                     input.pubkeys,
                     input.position,
-                    AltJubjubBn256::new(),
+                    &jubjub_params,
                     generator,
+                    hash_suite,
                 )
                 .unwrap();
                 let pre_commitment = signer.compute_precommitment(rng).unwrap();
@@ -431,8 +1701,9 @@ fn test_musig_api_errors() {
                 let mut signer = MuSigSigner::new(
                     input.pubkeys,
                     input.position,
-                    AltJubjubBn256::new(),
+                    &jubjub_params,
                     generator,
+                    hash_suite,
                 )
                 .unwrap();
                 let pre_commitment = signer.compute_precommitment(rng).unwrap();
@@ -443,11 +1714,7 @@ fn test_musig_api_errors() {
                     .receive_commitments(&[commitment, commitment])
                     .unwrap();
                 let _ = signer
-                    .sign(
-                        input.private_key.unwrap(),
-                        input.message.unwrap(),
-                        &rescue_params,
-                    )
+                    .sign(input.private_key.unwrap(), input.message.unwrap())
                     .unwrap();
                 let result = signer.receive_signatures(input.signature_shares.unwrap());
                 match result {
@@ -458,3 +1725,925 @@ fn test_musig_api_errors() {
         }
     }
 }
+
+#[test]
+fn test_musig_signer_state_export_import() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let number_of_parties = 3;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+    let mut used_nonces = UsedNonces::new();
+
+    let mut pre_commitments = vec![];
+    for signer in signers.iter_mut() {
+        pre_commitments.push(signer.compute_precommitment(rng).unwrap());
+    }
+
+    // first signer is restored after each round
+    let state = signers[0].export_state();
+    signers[0] =
+        MuSigSigner::import_state(&state, &jubjub_params, generator, hash_suite, &used_nonces)
+            .unwrap();
+
+    let mut commitments = vec![];
+    for signer in signers.iter_mut() {
+        commitments.push(signer.receive_precommitments(&pre_commitments).unwrap());
+    }
+
+    let state = signers[0].export_state();
+    signers[0] =
+        MuSigSigner::import_state(&state, &jubjub_params, generator, hash_suite, &used_nonces)
+            .unwrap();
+
+    for signer in signers.iter_mut() {
+        signer.receive_commitments(&commitments).unwrap();
+    }
+
+    let state_before_signing = signers[0].export_state();
+    signers[0] = MuSigSigner::import_state(
+        &state_before_signing,
+        &jubjub_params,
+        generator,
+        hash_suite,
+        &used_nonces,
+    )
+    .unwrap();
+
+    let mut signature_shares = vec![];
+    for (position, signer) in signers.iter_mut().enumerate() {
+        signature_shares.push(signer.sign(&privkeys[position], &message).unwrap());
+    }
+    used_nonces.insert(signers[0].nonce_commitment().unwrap());
+
+    // used nonce can't produce a second share
+    let state_after_signing = signers[0].export_state();
+    let mut restored_signer = MuSigSigner::import_state(
+        &state_after_signing,
+        &jubjub_params,
+        generator,
+        hash_suite,
+        &used_nonces,
+    )
+    .unwrap();
+    assert_eq!(
+        restored_signer.sign(&privkeys[0], &[1, 2, 3]).unwrap_err(),
+        MusigError::NonceAlreadyUsed
+    );
+    assert_eq!(
+        signers[0].sign(&privkeys[0], &message).unwrap_err(),
+        MusigError::NonceAlreadyUsed
+    );
+
+    // state exported before signing still holds the nonce, recorded
+    // commitment keeps it from signing another message
+    let mut stale_signer = MuSigSigner::import_state(
+        &state_before_signing,
+        &jubjub_params,
+        generator,
+        hash_suite,
+        &used_nonces,
+    )
+    .unwrap();
+    assert_eq!(
+        stale_signer.sign(&privkeys[0], &[1, 2, 3]).unwrap_err(),
+        MusigError::NonceAlreadyUsed
+    );
+    assert_eq!(
+        UsedNonces::from_bytes(&used_nonces.to_bytes()).unwrap(),
+        used_nonces
+    );
+
+    let signature = restored_signer
+        .receive_signatures(&signature_shares)
+        .unwrap();
+    assert!(MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &signature,
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+
+    // truncated and extended states are rejected
+    for state in [
+        state_before_signing[..state_before_signing.len() - 1].to_vec(),
+        [state_before_signing.clone(), vec![0]].concat(),
+    ] {
+        assert_eq!(
+            MuSigSigner::import_state(&state, &jubjub_params, generator, hash_suite, &used_nonces)
+                .err()
+                .unwrap(),
+            MusigError::InvalidSignerState
+        );
+    }
+}
+
+#[test]
+fn test_musig_signature_encoding() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let number_of_parties = 3;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    let signature = musig_multi_party_test_runner(
+        rng,
+        &message,
+        &pubkeys,
+        &privkeys,
+        &mut signers,
+        &jubjub_params,
+        &hash_suite,
+        generator,
+    )
+    .unwrap();
+
+    let encoded = MusigSignature::from(signature).to_bytes();
+    assert_eq!(encoded.len(), SIGNATURE_ENCODING_LENGTH);
+
+    let decoded = MusigSignature::<Bn256>::from_bytes(&encoded, &jubjub_params).unwrap();
+    assert!(decoded.to_bytes() == encoded);
+    assert!(MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &Signature::from(decoded.clone()),
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+
+    let decode = |input: &[u8]| {
+        MusigSignature::<Bn256>::from_bytes(input, &jubjub_params)
+            .err()
+            .unwrap()
+    };
+    let (r_bytes, s_bytes) = encoded.split_at(32);
+
+    // truncated and extended signatures
+    assert_eq!(decode(&encoded[1..]), MusigError::InvalidSignatureLength);
+    assert_eq!(
+        decode(&[encoded.clone(), vec![0]].concat()),
+        MusigError::InvalidSignatureLength
+    );
+
+    // s equal to the group order and the largest 32 byte value
+    let mut order = vec![0u8; 32];
+    Fs::char().write_le(&mut order[..]).unwrap();
+    assert_eq!(
+        decode(&[r_bytes, &order[..]].concat()),
+        MusigError::InvalidSignatureScalar
+    );
+    assert_eq!(
+        decode(&[r_bytes, &[0xff; 32][..]].concat()),
+        MusigError::InvalidSignatureScalar
+    );
+
+    // y which is not on the curve
+    let mut off_curve = r_bytes.to_vec();
+    while Point::<Bn256, Unknown>::read(&off_curve[..], &jubjub_params).is_ok() {
+        off_curve[0] = off_curve[0].wrapping_add(1);
+    }
+    assert_eq!(
+        decode(&[&off_curve[..], s_bytes].concat()),
+        MusigError::InvalidSignaturePoint
+    );
+
+    // R shifted by the point (0, -1) of order 2
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+    let low_order = Point::<Bn256, Unknown>::get_for_y(minus_one, false, &jubjub_params).unwrap();
+    let mut shifted = vec![];
+    decoded
+        .r
+        .add(&low_order, &jubjub_params)
+        .write(&mut shifted)
+        .unwrap();
+    assert_eq!(
+        decode(&[&shifted[..], s_bytes].concat()),
+        MusigError::InvalidSignaturePoint
+    );
+
+    // identity point with the sign bit of x set
+    let mut identity = vec![0u8; 32];
+    identity[0] = 1;
+    assert!(MusigSignature::<Bn256>::from_bytes(
+        &[&identity[..], s_bytes].concat(),
+        &jubjub_params
+    )
+    .is_ok());
+    identity[31] |= 0x80;
+    assert_eq!(
+        decode(&[&identity[..], s_bytes].concat()),
+        MusigError::InvalidSignaturePoint
+    );
+}
+
+#[test]
+fn test_musig_wire_messages() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let number_of_parties = 3;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    let session_id = [7u8; SESSION_ID_LENGTH];
+
+    // each message goes through encoding, parties receive them in reverse order
+    let transmit = |messages: Vec<WireMessage<Bn256>>| -> Vec<WireMessage<Bn256>> {
+        messages
+            .iter()
+            .rev()
+            .map(|message| {
+                let decoded =
+                    WireMessage::<Bn256>::from_bytes(&message.to_bytes(), &jubjub_params).unwrap();
+                assert!(decoded == *message);
+                assert_eq!(decoded.to_bytes(), message.to_bytes());
+                decoded
+            })
+            .collect()
+    };
+
+    let mut messages = vec![];
+    for (position, signer) in signers.iter_mut().enumerate() {
+        let pre_commitment = signer.compute_precommitment(rng).unwrap();
+        messages.push(
+            WireMessage::new(session_id, position, Payload::PreCommitment(pre_commitment)).unwrap(),
+        );
+    }
+    let pre_commitment_messages = transmit(messages);
+    let pre_commitments =
+        collect_pre_commitments(&pre_commitment_messages, &session_id, number_of_parties).unwrap();
+
+    let mut messages = vec![];
+    for (position, signer) in signers.iter_mut().enumerate() {
+        let commitment = signer.receive_precommitments(&pre_commitments).unwrap();
+        messages
+            .push(WireMessage::new(session_id, position, Payload::Commitment(commitment)).unwrap());
+    }
+    let commitment_messages = transmit(messages);
+    let commitments =
+        collect_commitments(&commitment_messages, &session_id, number_of_parties).unwrap();
+
+    let mut messages = vec![];
+    for (position, signer) in signers.iter_mut().enumerate() {
+        signer.receive_commitments(&commitments).unwrap();
+        let signature_share = signer.sign(&privkeys[position], &message).unwrap();
+        messages.push(
+            WireMessage::new(
+                session_id,
+                position,
+                Payload::SignatureShare(signature_share),
+            )
+            .unwrap(),
+        );
+    }
+    let signature_share_messages = transmit(messages);
+    let signature_shares =
+        collect_signature_shares(&signature_share_messages, &session_id, number_of_parties)
+            .unwrap();
+
+    let signature = signers[0].receive_signatures(&signature_shares).unwrap();
+    assert!(MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &signature,
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+
+    // messages of another session, of another round,
+    // with a missing or a duplicated sender
+    let other_session = [8u8; SESSION_ID_LENGTH];
+    assert_eq!(
+        collect_commitments(&commitment_messages, &other_session, number_of_parties)
+            .err()
+            .unwrap(),
+        MusigError::WireSessionMismatch
+    );
+    assert_eq!(
+        collect_commitments(&pre_commitment_messages, &session_id, number_of_parties)
+            .err()
+            .unwrap(),
+        MusigError::UnexpectedWireRound
+    );
+    assert_eq!(
+        collect_commitments(&commitment_messages[1..], &session_id, number_of_parties)
+            .err()
+            .unwrap(),
+        MusigError::InvalidParticipantPosition
+    );
+    let duplicated = [&commitment_messages[..], &commitment_messages[..1]].concat();
+    assert_eq!(
+        collect_commitments(&duplicated, &session_id, number_of_parties)
+            .err()
+            .unwrap(),
+        MusigError::InvalidParticipantPosition
+    );
+
+    // pre-commitment must be 32 bytes
+    assert_eq!(
+        WireMessage::<Bn256>::new(session_id, 0, Payload::PreCommitment(vec![0u8; 31]))
+            .err()
+            .unwrap(),
+        MusigError::InvalidWireMessage
+    );
+
+    let decode = |input: &[u8]| {
+        WireMessage::<Bn256>::from_bytes(input, &jubjub_params)
+            .err()
+            .unwrap()
+    };
+    let encoded = commitment_messages[0].to_bytes();
+    // offsets of the header fields
+    let (round_offset, length_offset, payload_offset) = (33, 38, 42);
+
+    assert_eq!(
+        decode(&encoded[..payload_offset]),
+        MusigError::InvalidWireMessage
+    );
+    assert_eq!(decode(&encoded[1..]), MusigError::UnsupportedWireVersion);
+    assert_eq!(
+        decode(&[encoded.clone(), vec![0]].concat()),
+        MusigError::InvalidWireMessage
+    );
+
+    let mut unsupported_version = encoded.clone();
+    unsupported_version[0] = WIRE_PROTOCOL_VERSION + 1;
+    assert_eq!(
+        decode(&unsupported_version),
+        MusigError::UnsupportedWireVersion
+    );
+
+    let mut unknown_round = encoded.clone();
+    unknown_round[round_offset] = 4;
+    assert_eq!(decode(&unknown_round), MusigError::InvalidWireMessage);
+
+    // declared length doesn't match the payload
+    let mut wrong_length = encoded.clone();
+    wrong_length[length_offset + 3] = 31;
+    assert_eq!(decode(&wrong_length), MusigError::InvalidWireMessage);
+
+    // commitment which is not on the curve or not canonically encoded
+    let mut off_curve = encoded.clone();
+    while Point::<Bn256, Unknown>::read(&off_curve[payload_offset..], &jubjub_params).is_ok() {
+        off_curve[payload_offset] = off_curve[payload_offset].wrapping_add(1);
+    }
+    assert_eq!(decode(&off_curve), MusigError::InvalidWireMessage);
+
+    let mut identity = vec![0u8; 32];
+    identity[0] = 1;
+    identity[31] |= 0x80;
+    let non_canonical = [&encoded[..payload_offset], &identity[..]].concat();
+    assert_eq!(decode(&non_canonical), MusigError::InvalidWireMessage);
+
+    // signature share equal to the group order
+    let encoded = signature_share_messages[0].to_bytes();
+    let mut order = vec![];
+    Fs::char().write_be(&mut order).unwrap();
+    let non_canonical = [&encoded[..payload_offset], &order[..]].concat();
+    assert_eq!(decode(&non_canonical), MusigError::InvalidWireMessage);
+}
+
+#[test]
+fn test_musig_session_binding() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let number_of_parties = 3;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, pubkeys, mut signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    let first_session = [1u8; SESSION_ID_LENGTH];
+    let second_session = [2u8; SESSION_ID_LENGTH];
+
+    for signer in signers.iter_mut() {
+        signer.set_session_id(first_session).unwrap();
+    }
+
+    let mut first_pre_commitments = vec![];
+    for signer in signers.iter_mut() {
+        first_pre_commitments.push(signer.compute_precommitment(rng).unwrap());
+    }
+
+    // session id can't be changed after pre-commitment
+    assert_eq!(
+        signers[0].set_session_id(second_session).unwrap_err(),
+        MusigError::SessionAlreadyStarted
+    );
+
+    // state keeps the session
+    let state = signers[0].export_state();
+    signers[0] = MuSigSigner::import_state(
+        &state,
+        &jubjub_params,
+        generator,
+        hash_suite,
+        &UsedNonces::new(),
+    )
+    .unwrap();
+    assert_eq!(signers[0].session_id(), Some(&first_session));
+
+    let mut first_commitments = vec![];
+    for signer in signers.iter_mut() {
+        first_commitments.push(
+            signer
+                .receive_precommitments(&first_pre_commitments)
+                .unwrap(),
+        );
+    }
+
+    for signer in signers.iter_mut() {
+        signer.receive_commitments(&first_commitments).unwrap();
+    }
+
+    let mut signature_shares = vec![];
+    for (position, signer) in signers.iter_mut().enumerate() {
+        signature_shares.push(signer.sign(&privkeys[position], &message).unwrap());
+    }
+
+    // signature is bound to the session, it doesn't verify
+    // for the bare message or under another session
+    let signature = signers[0].receive_signatures(&signature_shares).unwrap();
+    assert!(MuSigVerifier::verify_in_session(
+        &message,
+        &first_session,
+        &pubkeys,
+        &signature,
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+    assert!(!MuSigVerifier::verify(
+        &message,
+        &pubkeys,
+        &signature,
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+    assert!(!MuSigVerifier::verify_in_session(
+        &message,
+        &second_session,
+        &pubkeys,
+        &signature,
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+
+    let new_signers = |session_id: Option<SessionId>| {
+        let (_, _, mut signers) =
+            musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+        if let Some(session_id) = session_id {
+            for signer in signers.iter_mut() {
+                signer.set_session_id(session_id).unwrap();
+            }
+        }
+        signers
+    };
+
+    // pre-commitments and commitments of the first session
+    // are replayed in the second one
+    let mut signers = new_signers(Some(second_session));
+    signers[0].compute_precommitment(rng).unwrap();
+    signers[0]
+        .receive_precommitments(&first_pre_commitments)
+        .unwrap();
+    assert_eq!(
+        signers[0]
+            .receive_commitments(&first_commitments)
+            .unwrap_err(),
+        MusigError::SessionCommitmentMismatch
+    );
+
+    // and in a run which isn't bound to a session
+    let mut signers = new_signers(None);
+    signers[0].compute_precommitment(rng).unwrap();
+    signers[0]
+        .receive_precommitments(&first_pre_commitments)
+        .unwrap();
+    assert_eq!(
+        signers[0]
+            .receive_commitments(&first_commitments)
+            .unwrap_err(),
+        MusigError::InvalidCommitment
+    );
+
+    // pre-commitment and commitment of a party are copied by another one
+    let mut signers = new_signers(Some(second_session));
+    let mut pre_commitments = vec![];
+    for signer in signers.iter_mut() {
+        pre_commitments.push(signer.compute_precommitment(rng).unwrap());
+    }
+    let mut commitments = vec![];
+    for signer in signers.iter_mut() {
+        commitments.push(signer.receive_precommitments(&pre_commitments).unwrap());
+    }
+    pre_commitments[2] = pre_commitments[1].clone();
+    commitments[2] = commitments[1];
+    let mut signer = new_signers(Some(second_session)).remove(0);
+    signer.compute_precommitment(rng).unwrap();
+    signer.receive_precommitments(&pre_commitments).unwrap();
+    assert_eq!(
+        signer.receive_commitments(&commitments).unwrap_err(),
+        MusigError::SessionCommitmentMismatch
+    );
+
+    // another group has another key aggregation hash
+    let (_, other_pubkeys, _) =
+        musig_test_bn256_setup(number_of_parties + 1, &jubjub_params, hash_suite).unwrap();
+    let first_context = KeyAggregationContext::new(&pubkeys, &jubjub_params, &hash_suite).unwrap();
+    let other_context =
+        KeyAggregationContext::new(&other_pubkeys, &jubjub_params, &hash_suite).unwrap();
+    assert!(
+        first_context.key_aggregation_hash(&hash_suite)
+            != other_context.key_aggregation_hash(&hash_suite)
+    );
+
+    // and so does the same group under another domain tag
+    let tagged_hash_suite = DefaultHashSuite::new_with_tag(&rescue_params, b"other application");
+    assert!(
+        first_context.key_aggregation_hash(&hash_suite)
+            != first_context.key_aggregation_hash(&tagged_hash_suite)
+    );
+}
+
+fn run_ceremony_party<T: Transport>(
+    position: usize,
+    number_of_parties: usize,
+    transport: T,
+    message: &[u8],
+    config: CeremonyConfig,
+) -> Result<Signature<Bn256>, CeremonyError> {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, position as u32]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (privkeys, _, signers) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+    let signer = signers.into_iter().nth(position).unwrap();
+
+    let mut ceremony = Ceremony::new(signer, transport, [3u8; 32], &jubjub_params, config)?;
+
+    ceremony.run(rng, &privkeys[position], message)
+}
+
+// runs parties at the first positions of a group of `number_of_parties`,
+// one for each transport
+fn run_ceremony<T: Transport + Send + 'static>(
+    number_of_parties: usize,
+    transports: Vec<T>,
+    messages: Vec<Vec<u8>>,
+    config: CeremonyConfig,
+) -> Vec<Result<Signature<Bn256>, CeremonyError>> {
+    let handles: Vec<_> = transports
+        .into_iter()
+        .zip(messages.into_iter())
+        .enumerate()
+        .map(|(position, (transport, message))| {
+            let config = config.clone();
+            thread::spawn(move || {
+                run_ceremony_party(position, number_of_parties, transport, &message, config)
+            })
+        })
+        .collect();
+
+    handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_musig_ceremony() {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+
+    let message = random_message_hash(rng);
+
+    let number_of_parties = 3;
+
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let (_, pubkeys, _) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    let check_signatures = |results: Vec<Result<Signature<Bn256>, CeremonyError>>| {
+        let signatures: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        for signature in signatures.iter() {
+            assert!(signature.r == signatures[0].r && signature.s == signatures[0].s);
+        }
+        assert!(MuSigVerifier::verify_in_session(
+            &message,
+            &[3u8; 32],
+            &pubkeys,
+            &signatures[0],
+            &jubjub_params,
+            generator,
+            &hash_suite,
+        )
+        .unwrap());
+    };
+
+    // parties in the same process
+    check_signatures(run_ceremony(
+        number_of_parties,
+        InMemoryTransport::network(number_of_parties),
+        vec![message.clone(); number_of_parties],
+        CeremonyConfig::default(),
+    ));
+
+    // parties connected over TCP
+    let listeners: Vec<_> = (0..number_of_parties)
+        .map(|_| TcpListener::bind("127.0.0.1:0").unwrap())
+        .collect();
+    let addresses: Vec<_> = listeners
+        .iter()
+        .map(|listener| listener.local_addr().unwrap())
+        .collect();
+    let handles: Vec<_> = listeners
+        .into_iter()
+        .enumerate()
+        .map(|(position, listener)| {
+            let addresses = addresses.clone();
+            thread::spawn(move || {
+                TcpTransport::connect(position, listener, &addresses, Duration::from_secs(10))
+                    .unwrap()
+            })
+        })
+        .collect();
+    let transports = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .collect();
+    check_signatures(run_ceremony(
+        number_of_parties,
+        transports,
+        vec![message.clone(); number_of_parties],
+        CeremonyConfig::default(),
+    ));
+}
+
+#[test]
+fn test_musig_ceremony_failures() {
+    let number_of_parties = 3;
+    let message = vec![1u8; 32];
+    let config = CeremonyConfig {
+        round_timeout: Duration::from_millis(500),
+        retry_interval: Duration::from_millis(100),
+        send_attempts: 1,
+    };
+
+    // the last party never starts
+    let mut transports = InMemoryTransport::network(number_of_parties);
+    let idle_transport = transports.pop().unwrap();
+    let results = run_ceremony(
+        number_of_parties,
+        transports,
+        vec![message.clone(); number_of_parties - 1],
+        config.clone(),
+    );
+    // the party which times out first aborts the ceremony
+    // for the other one
+    let mut timeouts = 0;
+    for result in results {
+        match result {
+            Err(CeremonyError::Timeout { round, missing }) => {
+                assert_eq!(round, Round::PreCommitment);
+                assert_eq!(missing, vec![2]);
+                timeouts += 1;
+            }
+            Err(CeremonyError::Aborted(_)) => {}
+            _ => panic!("ceremony doesn't time out"),
+        }
+    }
+    assert!(timeouts > 0);
+    drop(idle_transport);
+
+    // the last party signs another message, so its share is invalid for
+    // the others and their shares are invalid for it
+    let mut messages = vec![message.clone(); number_of_parties];
+    messages[2] = vec![2u8; 32];
+    let results = run_ceremony(
+        number_of_parties,
+        InMemoryTransport::network(number_of_parties),
+        messages,
+        config.clone(),
+    );
+    for result in results {
+        match result {
+            Err(CeremonyError::Musig(MusigError::InvalidSignatureShare))
+            | Err(CeremonyError::Aborted(_)) => {}
+            _ => panic!("invalid signature share is accepted"),
+        }
+    }
+
+    // the last party sends a pre-commitment in the name of another one
+    let mut transports = InMemoryTransport::network(number_of_parties);
+    let mut forging_transport = transports.pop().unwrap();
+    let forged_message =
+        WireMessage::<Bn256>::new([3u8; 32], 1, Payload::PreCommitment(vec![0u8; 32])).unwrap();
+    forging_transport
+        .broadcast(&forged_message.to_bytes())
+        .unwrap();
+    let results = run_ceremony(
+        number_of_parties,
+        transports,
+        vec![message.clone(); number_of_parties - 1],
+        config,
+    );
+    for result in results {
+        match result {
+            Err(CeremonyError::ForgedSender {
+                party: 2,
+                sender: 1,
+            })
+            | Err(CeremonyError::Aborted(_)) => {}
+            _ => panic!("forged message is accepted"),
+        }
+    }
+    drop(forging_transport);
+}
+
+const CEREMONY_POSITION_VAR: &str = "MUSIG_CEREMONY_POSITION";
+const CEREMONY_ADDRESSES_VAR: &str = "MUSIG_CEREMONY_ADDRESSES";
+// printed by a party whose port has been taken by another process
+const CEREMONY_ADDRESS_IN_USE: &str = "address in use";
+const CEREMONY_ATTEMPTS: usize = 5;
+
+// party of `test_musig_ceremony_processes`, it is run by that test in a
+// process of its own and does nothing otherwise
+#[test]
+fn test_musig_ceremony_process_party() {
+    let position = match std::env::var(CEREMONY_POSITION_VAR) {
+        Ok(position) => position.parse().unwrap(),
+        Err(_) => return,
+    };
+    let addresses: Vec<SocketAddr> = std::env::var(CEREMONY_ADDRESSES_VAR)
+        .unwrap()
+        .split(',')
+        .map(|address| address.parse().unwrap())
+        .collect();
+
+    let listener = match TcpListener::bind(addresses[position]) {
+        Ok(listener) => listener,
+        Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+            println!("{}", CEREMONY_ADDRESS_IN_USE);
+            return;
+        }
+        Err(e) => panic!("can't listen: {}", e),
+    };
+    let transport =
+        TcpTransport::connect(position, listener, &addresses, Duration::from_secs(10)).unwrap();
+    let signature = run_ceremony_party(
+        position,
+        addresses.len(),
+        transport,
+        &[1u8; 32],
+        CeremonyConfig::default(),
+    )
+    .unwrap();
+
+    println!(
+        "signature {}",
+        hex::encode(MusigSignature::from(signature).to_bytes())
+    );
+}
+
+#[test]
+fn test_musig_ceremony_processes() {
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    let generator = FixedGenerators::SpendingKeyGenerator;
+    let hash_suite = DefaultHashSuite::new(&rescue_params);
+
+    let number_of_parties = 3;
+
+    let (_, pubkeys, _) =
+        musig_test_bn256_setup(number_of_parties, &jubjub_params, hash_suite).unwrap();
+
+    // free local ports are released before the parties listen on them,
+    // so another process may take one in between, then the run is
+    // repeated with other ports
+    let mut attempts = 0;
+    let outputs = loop {
+        attempts += 1;
+
+        let addresses: Vec<_> = (0..number_of_parties)
+            .map(|_| {
+                TcpListener::bind("127.0.0.1:0")
+                    .unwrap()
+                    .local_addr()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect();
+
+        let children: Vec<_> = (0..number_of_parties)
+            .map(|position| {
+                Command::new(std::env::current_exe().unwrap())
+                    .args(&[
+                        "--exact",
+                        "tests::test_musig_ceremony_process_party",
+                        "--nocapture",
+                    ])
+                    .env(CEREMONY_POSITION_VAR, position.to_string())
+                    .env(CEREMONY_ADDRESSES_VAR, addresses.join(","))
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .unwrap()
+            })
+            .collect();
+
+        let outputs: Vec<_> = children
+            .into_iter()
+            .map(|child| child.wait_with_output().unwrap())
+            .collect();
+        let address_in_use = outputs.iter().any(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .any(|line| line == CEREMONY_ADDRESS_IN_USE)
+        });
+        if !address_in_use || attempts == CEREMONY_ATTEMPTS {
+            break outputs;
+        }
+    };
+
+    let mut signatures = vec![];
+    for output in outputs {
+        assert!(output.status.success());
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        let encoded = stdout
+            .lines()
+            .find_map(|line| line.strip_prefix("signature "))
+            .expect("party has printed its signature");
+        let signature =
+            MusigSignature::from_bytes(&hex::decode(encoded).unwrap(), &jubjub_params).unwrap();
+        signatures.push(signature);
+    }
+
+    for signature in signatures.iter() {
+        assert!(*signature == signatures[0]);
+    }
+    assert!(MuSigVerifier::verify_in_session(
+        &[1u8; 32],
+        &[3u8; 32],
+        &pubkeys,
+        &signatures[0].clone().into(),
+        &jubjub_params,
+        generator,
+        &hash_suite,
+    )
+    .unwrap());
+}
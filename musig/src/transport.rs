@@ -0,0 +1,234 @@
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// frames longer than this are dropped together with the connection,
+// each wire message is much shorter
+const MAX_FRAME_LENGTH: usize = 1 << 16;
+
+// pause between attempts to connect to a party which isn't listening yet
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Channel between a party and all other parties of a ceremony.
+/// Messages are opaque byte strings, `Ceremony` puts wire messages in them.
+pub trait Transport {
+    /// Sends message to each other party
+    fn broadcast(&mut self, message: &[u8]) -> io::Result<()>;
+
+    /// Returns next message of any other party together with the position
+    /// of the party it came from, or `None` if nothing has arrived within
+    /// the timeout. `Ceremony` rejects messages which claim another sender
+    /// than this position, but the position is only as trustworthy as the
+    /// transport: `TcpTransport` takes it from the handshake of the peer,
+    /// which is not authenticated.
+    fn receive(&mut self, timeout: Duration) -> io::Result<Option<(usize, Vec<u8>)>>;
+}
+
+/// Transport between parties of the same process, e.g. threads
+pub struct InMemoryTransport {
+    position: usize,
+    inbox: Receiver<(usize, Vec<u8>)>,
+    peers: Vec<Sender<(usize, Vec<u8>)>>,
+}
+
+impl InMemoryTransport {
+    /// Connects given number of parties with each other,
+    /// transport of each party is at its position.
+    pub fn network(number_of_parties: usize) -> Vec<Self> {
+        let (senders, inboxes): (Vec<_>, Vec<_>) =
+            (0..number_of_parties).map(|_| channel()).unzip();
+
+        inboxes
+            .into_iter()
+            .enumerate()
+            .map(|(position, inbox)| Self {
+                position,
+                inbox,
+                peers: senders
+                    .iter()
+                    .enumerate()
+                    .filter(|(peer, _)| *peer != position)
+                    .map(|(_, sender)| sender.clone())
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+impl Transport for InMemoryTransport {
+    fn broadcast(&mut self, message: &[u8]) -> io::Result<()> {
+        for peer in self.peers.iter() {
+            peer.send((self.position, message.to_vec()))
+                .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "party has left"))?;
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self, timeout: Duration) -> io::Result<Option<(usize, Vec<u8>)>> {
+        receive_from(&self.inbox, timeout)
+    }
+}
+
+/// Transport over TCP connections between each pair of parties, for
+/// parties in different processes. Each message is framed with its
+/// length as big-endian u32, and received frames are tagged with the
+/// position the connection was opened with. That position is sent by the
+/// connecting party and isn't authenticated, so any host which reaches
+/// the listener can claim to be another party.
+pub struct TcpTransport {
+    inbox: Receiver<(usize, Vec<u8>)>,
+    peers: Vec<(usize, TcpStream)>,
+}
+
+impl TcpTransport {
+    /// Connects this party to all other parties. `addresses` are listening
+    /// addresses of all parties in order of positions, `listener` must be
+    /// bound to the address of this party. The party connects to parties
+    /// with lower positions and accepts connections of the others, so all
+    /// parties must call it within the timeout.
+    pub fn connect(
+        position: usize,
+        listener: TcpListener,
+        addresses: &[SocketAddr],
+        timeout: Duration,
+    ) -> io::Result<Self> {
+        let position_bytes = u32::try_from(position)
+            .map_err(|_| invalid_data("position doesn't fit in u32"))?
+            .to_be_bytes();
+        let deadline = Instant::now() + timeout;
+
+        let mut peers = vec![];
+        for (peer, address) in addresses.iter().enumerate().take(position) {
+            let mut stream = connect_until(address, deadline)?;
+            // connecting party introduces itself
+            stream.write_all(&position_bytes)?;
+            peers.push((peer, stream));
+        }
+
+        let mut accepted = vec![false; addresses.len()];
+        listener.set_nonblocking(true)?;
+        for _ in position + 1..addresses.len() {
+            let mut stream = accept_until(&listener, deadline)?;
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(remaining(deadline)?))?;
+            let peer = read_u32(&mut stream)? as usize;
+            stream.set_read_timeout(None)?;
+
+            if peer <= position || peer >= addresses.len() || accepted[peer] {
+                return Err(invalid_data("unexpected party position"));
+            }
+            accepted[peer] = true;
+            peers.push((peer, stream));
+        }
+
+        let (sender, inbox) = channel();
+        for (peer, stream) in peers.iter() {
+            let stream = stream.try_clone()?;
+            let peer = *peer;
+            let sender = sender.clone();
+            thread::spawn(move || read_frames(stream, peer, sender));
+        }
+
+        Ok(Self { inbox, peers })
+    }
+}
+
+impl Transport for TcpTransport {
+    fn broadcast(&mut self, message: &[u8]) -> io::Result<()> {
+        let length =
+            u32::try_from(message.len()).map_err(|_| invalid_data("message is too long"))?;
+        for (_, stream) in self.peers.iter_mut() {
+            stream.write_all(&length.to_be_bytes())?;
+            stream.write_all(message)?;
+        }
+
+        Ok(())
+    }
+
+    fn receive(&mut self, timeout: Duration) -> io::Result<Option<(usize, Vec<u8>)>> {
+        receive_from(&self.inbox, timeout)
+    }
+}
+
+fn receive_from(
+    inbox: &Receiver<(usize, Vec<u8>)>,
+    timeout: Duration,
+) -> io::Result<Option<(usize, Vec<u8>)>> {
+    match inbox.recv_timeout(timeout) {
+        Ok(message) => Ok(Some(message)),
+        Err(RecvTimeoutError::Timeout) => Ok(None),
+        Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            "all parties have left",
+        )),
+    }
+}
+
+// forwards frames of the connection with the party at `peer`
+// until it is closed
+fn read_frames(mut stream: TcpStream, peer: usize, sender: Sender<(usize, Vec<u8>)>) {
+    while let Ok(length) = read_u32(&mut stream) {
+        let length = length as usize;
+        if length > MAX_FRAME_LENGTH {
+            return;
+        }
+
+        let mut frame = vec![0u8; length];
+        if stream.read_exact(&mut frame).is_err() || sender.send((peer, frame)).is_err() {
+            return;
+        }
+    }
+}
+
+fn connect_until(address: &SocketAddr, deadline: Instant) -> io::Result<TcpStream> {
+    loop {
+        match TcpStream::connect_timeout(address, remaining(deadline)?) {
+            Ok(stream) => return Ok(stream),
+            // the party may not be listening yet
+            Err(err) if err.kind() == io::ErrorKind::ConnectionRefused => {
+                thread::sleep(CONNECT_RETRY_INTERVAL)
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn accept_until(listener: &TcpListener, deadline: Instant) -> io::Result<TcpStream> {
+    loop {
+        match listener.accept() {
+            Ok((stream, _)) => return Ok(stream),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                remaining(deadline)?;
+                thread::sleep(CONNECT_RETRY_INTERVAL)
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn remaining(deadline: Instant) -> io::Result<Duration> {
+    let now = Instant::now();
+    if now >= deadline {
+        return Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "parties haven't connected in time",
+        ));
+    }
+
+    Ok(deadline - now)
+}
+
+fn read_u32(stream: &mut TcpStream) -> io::Result<u32> {
+    let mut buf = [0u8; 4];
+    stream.read_exact(&mut buf)?;
+
+    Ok(u32::from_be_bytes(buf))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
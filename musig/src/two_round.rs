@@ -0,0 +1,273 @@
+use crate::aggregated_pubkey::KeyAggregationContext;
+use crate::errors::MusigError;
+use crate::hash_suite::{DefaultHashSuite, HashSuite};
+use crate::hasher::Hasher;
+use crate::jubjub::JubJubWrapper;
+use crate::verifier::MuSigVerifier;
+use bellman::pairing::ff::Field;
+use franklin_crypto::eddsa::{PrivateKey, PublicKey, Signature};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, Unknown};
+use franklin_crypto::rescue::RescueEngine;
+use rand::{Rand, Rng};
+
+/// Pair of nonce commitments (R_{i,1}, R_{i,2}) of a party
+pub type NonceCommitments<E> = (Point<E, Unknown>, Point<E, Unknown>);
+
+/// MuSig signer party with two rounds. Each party commits to two nonces
+/// and aggregated commitment is R = R_1 + b * R_2 where
+/// b = H_non(X', R_1, R_2, m), so commitments are exchanged without the
+/// pre-commitment round. The first round doesn't depend on the message
+/// and can be run ahead of time. Signatures are the same as the ones
+/// produced by `MuSigSigner`.
+pub struct MuSigTwoRoundSigner<
+    'a,
+    E: JubjubEngine + RescueEngine,
+    H: HashSuite<E> = DefaultHashSuite<'a, E>,
+> {
+    position: usize,
+    nonces: Option<(E::Fs, E::Fs)>,
+    nonce_commitments: Option<NonceCommitments<E>>,
+    received_commitments: Vec<NonceCommitments<E>>,
+    aggregated_commitments: Option<NonceCommitments<E>>,
+    aggregated_commitment: Option<Point<E, Unknown>>,
+    nonce_coefficient: Option<E::Fs>,
+    challenge: Option<E::Fs>,
+    nonce_used: bool,
+    aggregated_pubkey: PublicKey<E>,
+    a_values: Vec<E::Fs>,
+    tweak: E::Fs,
+    pubkeys: Vec<PublicKey<E>>,
+    jubjub_wrapper: JubJubWrapper<'a, E>,
+    hash_suite: H,
+}
+
+impl<'a, E: JubjubEngine + RescueEngine, H: HashSuite<E>> MuSigTwoRoundSigner<'a, E, H> {
+    /// Initializes new party, see `MuSigSigner::new`
+    pub fn new(
+        pubkeys: &[PublicKey<E>],
+        position: usize,
+        params: &'a <E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: H,
+    ) -> Result<Self, MusigError> {
+        let context = KeyAggregationContext::new(pubkeys, params, &hash_suite)?;
+
+        Self::new_with_context(&context, position, params, generator, hash_suite)
+    }
+
+    /// Initializes new party from already computed key aggregation,
+    /// see `MuSigSigner::new_with_context`
+    pub fn new_with_context(
+        context: &KeyAggregationContext<E>,
+        position: usize,
+        params: &'a <E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: H,
+    ) -> Result<Self, MusigError> {
+        let jubjub_wrapper = JubJubWrapper::new(params, generator);
+
+        if position >= context.pubkeys.len() {
+            return Err(MusigError::InvalidParticipantPosition);
+        }
+
+        Ok(Self {
+            position,
+            nonces: None,
+            nonce_commitments: None,
+            received_commitments: vec![],
+            aggregated_commitments: None,
+            aggregated_commitment: None,
+            nonce_coefficient: None,
+            challenge: None,
+            nonce_used: false,
+            aggregated_pubkey: context.aggregated_pubkey.clone(),
+            a_values: context.a_values.clone(),
+            tweak: context.tweak,
+            pubkeys: context.pubkeys.clone(),
+            jubjub_wrapper,
+            hash_suite,
+        })
+    }
+
+    /// Position of this party in the list of public keys
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Generates two nonces and returns commitments to them,
+    /// R_{i,1} = r_{i,1} * G and R_{i,2} = r_{i,2} * G.
+    /// rng must be a cryptographically secure one.
+    pub fn compute_commitments(
+        &mut self,
+        rng: &mut impl Rng,
+    ) -> Result<NonceCommitments<E>, MusigError> {
+        let r_1 = E::Fs::rand(rng);
+        let r_2 = E::Fs::rand(rng);
+
+        // constant-time multiplication
+        let commitments = (
+            self.jubjub_wrapper.mul_by_generator_ct(r_1),
+            self.jubjub_wrapper.mul_by_generator_ct(r_2),
+        );
+
+        self.nonces = Some((r_1, r_2));
+        self.nonce_commitments = Some(commitments);
+
+        Ok(commitments)
+    }
+
+    /// Receives commitments of all parties and returns their sums
+    /// (R_1, R_2). Each party must produce the same sums.
+    pub fn receive_commitments(
+        &mut self,
+        commitments: &[NonceCommitments<E>],
+    ) -> Result<NonceCommitments<E>, MusigError> {
+        // check that whether previous step passed or not
+        if self.nonce_commitments.is_none() {
+            return Err(MusigError::NonceCommitmentNotGenerated);
+        }
+
+        if commitments.len() != self.pubkeys.len() {
+            return Err(MusigError::NonceCommitmentsAndParticipantsNotMatch);
+        }
+
+        // R_1 = \sum{1<=i<=n}{R_{i,1}}, R_2 = \sum{1<=i<=n}{R_{i,2}}
+        let mut acc = (Point::zero(), Point::zero());
+        for (R_1, R_2) in commitments {
+            if !self.jubjub_wrapper.is_in_correct_subgroup(R_1)
+                || !self.jubjub_wrapper.is_in_correct_subgroup(R_2)
+            {
+                return Err(MusigError::CommitmentIsNotInCorrectSubgroup);
+            }
+            // nonces of an honest party are never zero
+            if *R_1 == Point::zero() || *R_2 == Point::zero() {
+                return Err(MusigError::IdentityCommitment);
+            }
+
+            acc.0 = self.jubjub_wrapper.add(&acc.0, R_1);
+            acc.1 = self.jubjub_wrapper.add(&acc.1, R_2);
+        }
+
+        self.aggregated_commitments = Some(acc);
+        self.received_commitments = commitments.to_vec();
+
+        Ok(acc)
+    }
+
+    /// Computes signature share s_i = r_{i,1} + b * r_{i,2} + c * a_i * x_i
+    /// with R = R_1 + b * R_2 and c = H_sig(X', R, m)
+    pub fn sign(
+        &mut self,
+        private_key: &PrivateKey<E>,
+        message: &[u8],
+    ) -> Result<E::Fs, MusigError> {
+        // check that whether previous step passed or not
+        if self.aggregated_commitments.is_none() {
+            return Err(MusigError::NonceCommitmentsNotReceived);
+        }
+
+        // a second share with the same nonces and a different
+        // challenge would reveal the private key
+        if self.nonce_used {
+            return Err(MusigError::NonceAlreadyUsed);
+        }
+
+        let aggregated_commitments = self.aggregated_commitments.unwrap();
+        // since commitments have already been received
+        // we can safely unwrap nonces
+        let (r_1, r_2) = self.nonces.unwrap();
+
+        // b = H_non(X', R_1, R_2, m)
+        let b = Hasher::hash_nonce_coefficient(
+            &self.aggregated_pubkey,
+            &aggregated_commitments,
+            message,
+        );
+
+        // R = R_1 + b * R_2
+        let aggregated_commitment = self.jubjub_wrapper.add(
+            &aggregated_commitments.0,
+            &self.jubjub_wrapper.mul(&aggregated_commitments.1, b),
+        );
+
+        // c = H_sig(X', R, m)
+        let c = self.hash_suite.hash_signature_data(
+            &self.aggregated_pubkey,
+            &aggregated_commitment,
+            message,
+        );
+
+        // s = r_1 + b * r_2 + c * a_i * x_i
+        let mut s = c;
+        s.mul_assign(&self.a_values[self.position]);
+        s.mul_assign(&private_key.0);
+        let mut nonce = r_2;
+        nonce.mul_assign(&b);
+        nonce.add_assign(&r_1);
+        s.add_assign(&nonce);
+
+        self.nonce_coefficient = Some(b);
+        self.challenge = Some(c);
+        self.aggregated_commitment = Some(aggregated_commitment);
+
+        // nonces are not needed anymore
+        self.nonces = None;
+        self.nonce_used = true;
+
+        Ok(s)
+    }
+
+    /// Receives signature shares and verifies them. If all signature shares
+    /// are valid then returns an aggregated signature. Each party must produce
+    /// same aggregated signature.
+    pub fn receive_signatures(
+        &self,
+        signature_shares: &[E::Fs],
+    ) -> Result<Signature<E>, MusigError> {
+        // check that whether previous step passed or not
+        if self.challenge.is_none() {
+            return Err(MusigError::ChallengeNotGenerated);
+        }
+
+        if signature_shares.len() != self.pubkeys.len() {
+            return Err(MusigError::SignatureShareAndParticipantsNotMatch);
+        }
+
+        let b = self.nonce_coefficient.unwrap();
+        let challenge = self.challenge.unwrap();
+
+        let mut aggregated_signature = E::Fs::zero();
+        // s = \sum{1<=i<=n}{s_i}
+        for (position, signature) in signature_shares.iter().enumerate() {
+            // s_i * G = R_{i,1} + b * R_{i,2} + (c * a_i) * X_i
+            let (R_1, R_2) = &self.received_commitments[position];
+            let R_i = self
+                .jubjub_wrapper
+                .add(R_1, &self.jubjub_wrapper.mul(R_2, b));
+
+            if !MuSigVerifier::verify_share(
+                signature,
+                &R_i,
+                &challenge,
+                &self.a_values[position],
+                &self.pubkeys[position],
+                &self.jubjub_wrapper,
+            ) {
+                return Err(MusigError::InvalidSignatureShare);
+            }
+            aggregated_signature.add_assign(signature);
+        }
+
+        // s = s + c * t, so the signature is valid for X' + t * G
+        let mut tweak_term = challenge;
+        tweak_term.mul_assign(&self.tweak);
+        aggregated_signature.add_assign(&tweak_term);
+
+        // σ = (R, s)
+        Ok(Signature {
+            r: self.aggregated_commitment.unwrap(),
+            s: aggregated_signature,
+        })
+    }
+}
@@ -0,0 +1,487 @@
+//! Known-answer vectors for implementations of the protocol in other
+//! languages, published in `vectors/musig.json`. Vectors are generated
+//! from fixed seeds by running `MuSigSigner`. The published ones are
+//! checked against the signer and against values computed again from
+//! private keys and nonces alone. Vectors with a session id are bound to
+//! the session: t_i = H_sess(sid, i, H_keyagg(L, X'), H_comm(R_i)) and the
+//! challenge signs m' = H_msg(sid, m).
+//!
+//! To regenerate the published vectors after an intended change of
+//! the protocol:
+//!
+//! ```text
+//! MUSIG_UPDATE_VECTORS=1 cargo test --lib test_musig_vectors
+//! ```
+
+use crate::aggregated_pubkey::{AggregatedPublicKey, KeyAggregationContext};
+use crate::hash_suite::{DefaultHashSuite, HashSuite, Sha256HashSuite};
+use crate::signature::MusigSignature;
+use crate::signer::MuSigSigner;
+use crate::verifier::MuSigVerifier;
+use crate::wire::{SessionId, SESSION_ID_LENGTH};
+use bellman::pairing::bn256::Bn256;
+use bellman::{Field, PrimeField, PrimeFieldRepr};
+use franklin_crypto::alt_babyjubjub::{fs::Fs, AltJubjubBn256};
+use franklin_crypto::eddsa::{PrivateKey, PublicKey, Signature};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubParams, Unknown};
+use franklin_crypto::rescue::bn256::Bn256RescueParams;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+
+const VECTORS_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/vectors/musig.json");
+
+// published vectors are written instead of checked if it is set
+const UPDATE_VARIABLE: &str = "MUSIG_UPDATE_VECTORS";
+
+const GENERATOR: FixedGenerators = FixedGenerators::SpendingKeyGenerator;
+
+// (number of parties, message length), messages are at most 32 bytes
+const CASES: [(usize, usize); 6] = [(1, 32), (2, 0), (2, 32), (3, 1), (3, 20), (5, 32)];
+
+// (number of parties, message length, session id) of runs bound to a session
+const SESSION_CASES: [(usize, usize, SessionId); 1] = [(3, 32, [0x5a; SESSION_ID_LENGTH])];
+
+/// All values of a signing run, nonces are r_i of R_i = r_i * G
+struct Vector {
+    hash_suite: &'static str,
+    session_id: Option<SessionId>,
+    message: Vec<u8>,
+    // m, or m' = H_msg(sid, m) for a run bound to a session
+    signed_message: Vec<u8>,
+    private_keys: Vec<Fs>,
+    public_keys: Vec<PublicKey<Bn256>>,
+    a_values: Vec<Fs>,
+    aggregated_public_key: PublicKey<Bn256>,
+    key_aggregation_hash: Vec<u8>,
+    nonces: Vec<Fs>,
+    commitments: Vec<Point<Bn256, Unknown>>,
+    pre_commitments: Vec<Vec<u8>>,
+    aggregated_commitment: Point<Bn256, Unknown>,
+    challenge: Fs,
+    signature_shares: Vec<Fs>,
+    signature: MusigSignature<Bn256>,
+}
+
+impl Vector {
+    fn to_json(&self) -> Value {
+        json!({
+            "hash_suite": self.hash_suite,
+            "session_id": self.session_id.map(hex::encode),
+            "message": hex::encode(&self.message),
+            "signed_message": hex::encode(&self.signed_message),
+            "private_keys": self.private_keys.iter().map(scalar_hex).collect::<Vec<_>>(),
+            "public_keys": self
+                .public_keys
+                .iter()
+                .map(|pubkey| point_hex(&pubkey.0))
+                .collect::<Vec<_>>(),
+            "a_values": self.a_values.iter().map(scalar_hex).collect::<Vec<_>>(),
+            "aggregated_public_key": point_hex(&self.aggregated_public_key.0),
+            "key_aggregation_hash": hex::encode(&self.key_aggregation_hash),
+            "nonces": self.nonces.iter().map(scalar_hex).collect::<Vec<_>>(),
+            "commitments": self.commitments.iter().map(point_hex).collect::<Vec<_>>(),
+            "pre_commitments": self.pre_commitments.iter().map(hex::encode).collect::<Vec<_>>(),
+            "aggregated_commitment": point_hex(&self.aggregated_commitment),
+            "challenge": scalar_hex(&self.challenge),
+            "signature_shares": self.signature_shares.iter().map(scalar_hex).collect::<Vec<_>>(),
+            "signature": hex::encode(self.signature.to_bytes()),
+        })
+    }
+}
+
+fn scalar_hex(scalar: &Fs) -> String {
+    let mut bytes = vec![];
+    scalar.into_repr().write_be(&mut bytes).unwrap();
+
+    hex::encode(bytes)
+}
+
+// compressed point, as `Point::write` and `MusigSignature` encode it
+fn point_hex(point: &Point<Bn256, Unknown>) -> String {
+    let mut bytes = vec![];
+    point.write(&mut bytes).unwrap();
+
+    hex::encode(bytes)
+}
+
+fn read_scalar(value: &Value) -> Fs {
+    let bytes = hex::decode(value.as_str().unwrap()).unwrap();
+    let mut repr = Fs::zero().into_repr();
+    repr.read_be(&bytes[..]).unwrap();
+
+    Fs::from_repr(repr).unwrap()
+}
+
+fn read_scalars(value: &Value) -> Vec<Fs> {
+    value.as_array().unwrap().iter().map(read_scalar).collect()
+}
+
+fn read_session_id(value: &Value) -> Option<SessionId> {
+    value.as_str().map(|encoded| {
+        let mut session_id = [0u8; SESSION_ID_LENGTH];
+        session_id.copy_from_slice(&hex::decode(encoded).unwrap());
+        session_id
+    })
+}
+
+// m, or m' = H_msg(sid, m) if the run is bound to a session
+fn signed_message<H: HashSuite<Bn256>>(
+    session_id: Option<&SessionId>,
+    message: &[u8],
+    hash_suite: &H,
+) -> Vec<u8> {
+    match session_id {
+        Some(session_id) => hash_suite.hash_session_message(session_id, message),
+        None => message.to_vec(),
+    }
+}
+
+/// Runs `MuSigSigner` for keys and nonces from the seed
+fn generate_vector<H: HashSuite<Bn256> + Clone>(
+    hash_suite_name: &'static str,
+    hash_suite: H,
+    seed: u32,
+    number_of_parties: usize,
+    message_length: usize,
+    session_id: Option<SessionId>,
+    jubjub_params: &AltJubjubBn256,
+) -> Vector {
+    let rng = &mut XorShiftRng::from_seed([0x3dbe6259, 0x8d313d76, 0x3237db17, seed]);
+
+    let mut message = vec![0u8; message_length];
+    rng.fill_bytes(&mut message);
+
+    let private_keys: Vec<Fs> = (0..number_of_parties).map(|_| rng.gen()).collect();
+    let public_keys: Vec<_> = private_keys
+        .iter()
+        .map(|private_key| {
+            PublicKey::from_private(&PrivateKey(*private_key), GENERATOR, jubjub_params)
+        })
+        .collect();
+    let context = KeyAggregationContext::new(&public_keys, jubjub_params, &hash_suite).unwrap();
+
+    let mut signers: Vec<_> = (0..number_of_parties)
+        .map(|position| {
+            MuSigSigner::new_with_context(
+                &context,
+                position,
+                jubjub_params,
+                GENERATOR,
+                hash_suite.clone(),
+            )
+            .unwrap()
+        })
+        .collect();
+    if let Some(session_id) = session_id {
+        for signer in signers.iter_mut() {
+            signer.set_session_id(session_id).unwrap();
+        }
+    }
+
+    let pre_commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.compute_precommitment(rng).unwrap())
+        .collect();
+    let commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.receive_precommitments(&pre_commitments).unwrap())
+        .collect();
+    let aggregated_commitments: Vec<_> = signers
+        .iter_mut()
+        .map(|signer| signer.receive_commitments(&commitments).unwrap())
+        .collect();
+    let signature_shares: Vec<_> = signers
+        .iter_mut()
+        .zip(private_keys.iter())
+        .map(|(signer, private_key)| signer.sign(&PrivateKey(*private_key), &message).unwrap())
+        .collect();
+    let signature = signers[0].receive_signatures(&signature_shares).unwrap();
+
+    let signed_message = signed_message(session_id.as_ref(), &message, &hash_suite);
+    let challenge = hash_suite.hash_signature_data(
+        &context.aggregated_pubkey,
+        &aggregated_commitments[0],
+        &signed_message,
+    );
+
+    // r_i = s_i - c * a_i * x_i
+    let nonces = signature_shares
+        .iter()
+        .zip(context.a_values.iter())
+        .zip(private_keys.iter())
+        .map(|((signature_share, a_i), private_key)| {
+            let mut key_term = challenge;
+            key_term.mul_assign(a_i);
+            key_term.mul_assign(private_key);
+
+            let mut nonce = *signature_share;
+            nonce.sub_assign(&key_term);
+            nonce
+        })
+        .collect();
+
+    Vector {
+        hash_suite: hash_suite_name,
+        session_id,
+        message,
+        signed_message,
+        private_keys,
+        public_keys,
+        a_values: context.a_values.clone(),
+        aggregated_public_key: context.aggregated_pubkey.clone(),
+        key_aggregation_hash: context.key_aggregation_hash(&hash_suite),
+        nonces,
+        commitments,
+        pre_commitments,
+        aggregated_commitment: aggregated_commitments[0],
+        challenge,
+        signature_shares,
+        signature: signature.into(),
+    }
+}
+
+/// Computes all values of the published vector again from its private
+/// keys, nonces, message and session id, following the protocol description
+/// rather than `MuSigSigner`
+fn recompute_vector<H: HashSuite<Bn256>>(
+    published: &Value,
+    hash_suite_name: &'static str,
+    hash_suite: &H,
+    jubjub_params: &AltJubjubBn256,
+) -> Vector {
+    let session_id = read_session_id(&published["session_id"]);
+    let message = hex::decode(published["message"].as_str().unwrap()).unwrap();
+    let private_keys = read_scalars(&published["private_keys"]);
+    let nonces = read_scalars(&published["nonces"]);
+
+    // X_i = x_i * G
+    let public_keys: Vec<_> = private_keys
+        .iter()
+        .map(|private_key| {
+            PublicKey::from_private(&PrivateKey(*private_key), GENERATOR, jubjub_params)
+        })
+        .collect();
+
+    // X' = \sum{a_i * X_i}
+    let (aggregated_public_key, a_values) =
+        AggregatedPublicKey::compute_for_each_party(&public_keys, jubjub_params, hash_suite)
+            .unwrap();
+    let key_aggregation_hash =
+        hash_suite.hash_key_aggregation(&public_keys, &aggregated_public_key);
+
+    // R_i = r_i * G, t_i = H_comm(R_i) or
+    // t_i = H_sess(sid, i, H_keyagg(L, X'), H_comm(R_i)), R = \sum{R_i}
+    let commitments: Vec<Point<Bn256, Unknown>> = nonces
+        .iter()
+        .map(|nonce| {
+            jubjub_params
+                .generator(GENERATOR)
+                .mul(*nonce, jubjub_params)
+                .into()
+        })
+        .collect();
+    let pre_commitments = commitments
+        .iter()
+        .enumerate()
+        .map(|(position, commitment)| {
+            let commitment_hash = hash_suite.hash_commitment(commitment);
+            match session_id.as_ref() {
+                Some(session_id) => hash_suite.hash_session_commitment(
+                    session_id,
+                    position,
+                    &key_aggregation_hash,
+                    &commitment_hash,
+                ),
+                None => commitment_hash,
+            }
+        })
+        .collect();
+    let aggregated_commitment = commitments.iter().fold(Point::zero(), |acc, commitment| {
+        acc.add(commitment, jubjub_params)
+    });
+
+    // c = H_sig(X', R, m) with m' = H_msg(sid, m) in place of m for a run
+    // bound to a session, s_i = r_i + c * a_i * x_i, s = \sum{s_i}
+    let signed_message = signed_message(session_id.as_ref(), &message, hash_suite);
+    let challenge = hash_suite.hash_signature_data(
+        &aggregated_public_key,
+        &aggregated_commitment,
+        &signed_message,
+    );
+    let signature_shares: Vec<Fs> = nonces
+        .iter()
+        .zip(a_values.iter())
+        .zip(private_keys.iter())
+        .map(|((nonce, a_i), private_key)| {
+            let mut signature_share = challenge;
+            signature_share.mul_assign(a_i);
+            signature_share.mul_assign(private_key);
+            signature_share.add_assign(nonce);
+            signature_share
+        })
+        .collect();
+    let s = signature_shares.iter().fold(Fs::zero(), |mut acc, share| {
+        acc.add_assign(share);
+        acc
+    });
+
+    Vector {
+        hash_suite: hash_suite_name,
+        session_id,
+        message,
+        signed_message,
+        private_keys,
+        public_keys,
+        a_values,
+        aggregated_public_key,
+        key_aggregation_hash,
+        nonces,
+        commitments,
+        pre_commitments,
+        aggregated_commitment,
+        challenge,
+        signature_shares,
+        signature: MusigSignature {
+            r: aggregated_commitment,
+            s,
+        },
+    }
+}
+
+fn generate_vectors(jubjub_params: &AltJubjubBn256, rescue_params: &Bn256RescueParams) -> Value {
+    let cases = CASES
+        .iter()
+        .map(|(number_of_parties, message_length)| (*number_of_parties, *message_length, None))
+        .chain(
+            SESSION_CASES
+                .iter()
+                .map(|(number_of_parties, message_length, session_id)| {
+                    (*number_of_parties, *message_length, Some(*session_id))
+                }),
+        );
+
+    let mut vectors = vec![];
+    for (seed, (number_of_parties, message_length, session_id)) in cases.enumerate() {
+        let seed = seed as u32 + 1;
+        vectors.push(generate_vector(
+            "default",
+            DefaultHashSuite::new(rescue_params),
+            seed,
+            number_of_parties,
+            message_length,
+            session_id,
+            jubjub_params,
+        ));
+        vectors.push(generate_vector(
+            "sha256",
+            Sha256HashSuite::new(),
+            seed,
+            number_of_parties,
+            message_length,
+            session_id,
+            jubjub_params,
+        ));
+    }
+
+    json!({
+        "curve": "alt_babyjubjub over bn256",
+        "generator": "SpendingKeyGenerator",
+        "hash_suites": {
+            "default": "Blake2b for H_agg, SHA-256 for H_comm and Rescue for H_sig",
+            "sha256": "SHA-256 for all of H_agg, H_comm and H_sig",
+        },
+        "session": "H_keyagg, H_sess and H_msg are SHA-256 with the personas MuSig/keyagg, \
+                    MuSig/session and MuSig/message, vectors without a session id aren't \
+                    bound to a session",
+        "encoding": "scalars are big-endian, points are compressed, 32 bytes of little-endian y \
+                     with the sign of x in the top bit, signature is R || s with little-endian s. \
+                     Hash preimages encode points by their x coordinate alone, 32 bytes \
+                     little-endian",
+        "vectors": vectors.iter().map(Vector::to_json).collect::<Vec<_>>(),
+    })
+}
+
+fn check_vector<H: HashSuite<Bn256>>(
+    published: &Value,
+    hash_suite_name: &'static str,
+    hash_suite: &H,
+    jubjub_params: &AltJubjubBn256,
+) {
+    let recomputed = recompute_vector(published, hash_suite_name, hash_suite, jubjub_params);
+    assert_eq!(recomputed.to_json(), *published);
+
+    let signature: Signature<Bn256> = recomputed.signature.clone().into();
+    let is_valid = match recomputed.session_id.as_ref() {
+        Some(session_id) => MuSigVerifier::verify_in_session(
+            &recomputed.message,
+            session_id,
+            &recomputed.public_keys,
+            &signature,
+            jubjub_params,
+            GENERATOR,
+            hash_suite,
+        ),
+        None => MuSigVerifier::verify(
+            &recomputed.message,
+            &recomputed.public_keys,
+            &signature,
+            jubjub_params,
+            GENERATOR,
+            hash_suite,
+        ),
+    };
+    assert!(is_valid.unwrap());
+
+    let signature =
+        MusigSignature::from_bytes(&recomputed.signature.to_bytes(), jubjub_params).unwrap();
+    assert!(signature == recomputed.signature);
+}
+
+#[test]
+fn test_musig_vectors() {
+    let jubjub_params = AltJubjubBn256::new();
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+
+    let generated = generate_vectors(&jubjub_params, &rescue_params);
+
+    if std::env::var_os(UPDATE_VARIABLE).is_some() {
+        fs::create_dir_all(Path::new(VECTORS_PATH).parent().unwrap()).unwrap();
+        fs::write(
+            VECTORS_PATH,
+            serde_json::to_string_pretty(&generated).unwrap() + "\n",
+        )
+        .unwrap();
+    }
+
+    let published: Value =
+        serde_json::from_str(&fs::read_to_string(VECTORS_PATH).unwrap_or_else(|_| {
+            panic!(
+                "{} is missing, set {} to generate it",
+                VECTORS_PATH, UPDATE_VARIABLE
+            )
+        }))
+        .unwrap();
+
+    // the signer still produces the published values
+    assert_eq!(
+        published, generated,
+        "published vectors differ from the signer, set {} to update them",
+        UPDATE_VARIABLE
+    );
+
+    let default_suite = DefaultHashSuite::new(&rescue_params);
+    let sha256_suite = Sha256HashSuite::<Bn256>::new();
+    let vectors = published["vectors"].as_array().unwrap();
+    assert_eq!(vectors.len(), 2 * (CASES.len() + SESSION_CASES.len()));
+
+    for vector in vectors {
+        match vector["hash_suite"].as_str().unwrap() {
+            "default" => check_vector(vector, "default", &default_suite, &jubjub_params),
+            "sha256" => check_vector(vector, "sha256", &sha256_suite, &jubjub_params),
+            name => panic!("unknown hash suite {}", name),
+        }
+    }
+}
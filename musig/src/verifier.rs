@@ -1,10 +1,11 @@
-use crate::aggregated_pubkey::AggregatedPublicKey;
+use crate::aggregated_pubkey::{AggregatedPublicKey, KeyAggregationContext};
 use crate::errors::MusigError;
+use crate::hash_suite::HashSuite;
 use crate::jubjub::JubJubWrapper;
-use bellman::Field;
+use bellman::{Field, PrimeField};
 use franklin_crypto::eddsa::{PublicKey, Signature};
 use franklin_crypto::jubjub::edwards::Point;
-use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, Unknown};
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine, JubjubParams, Unknown};
 use franklin_crypto::rescue::RescueEngine;
 use std::marker::PhantomData;
 pub struct MuSigVerifier<E: JubjubEngine + RescueEngine> {
@@ -13,26 +14,109 @@ pub struct MuSigVerifier<E: JubjubEngine + RescueEngine> {
 
 impl<E: JubjubEngine + RescueEngine> MuSigVerifier<E> {
     /// Verifies an aggregated signature according to its public keys.
-    pub fn verify(
+    /// Hash suite must be the same one signers used.
+    pub fn verify<H: HashSuite<E>>(
         message: &[u8],
         pubkeys: &[PublicKey<E>],
         signature: &Signature<E>,
         jubjub_params: &<E as JubjubEngine>::Params,
         generator: FixedGenerators,
-        rescue_params: &<E as RescueEngine>::Params,
+        hash_suite: &H,
     ) -> Result<bool, MusigError> {
         let (aggregated_pubkey, _) =
-            AggregatedPublicKey::compute_from_pubkeys(pubkeys, jubjub_params)?;
+            AggregatedPublicKey::compute_from_pubkeys(pubkeys, jubjub_params, hash_suite)?;
 
-        Ok(aggregated_pubkey.verify_musig_rescue(
+        Ok(Self::verify_aggregated(
             message,
-            &signature,
+            &aggregated_pubkey,
+            signature,
+            jubjub_params,
             generator,
-            &rescue_params,
-            &jubjub_params,
+            hash_suite,
         ))
     }
 
+    /// Verifies an aggregated signature of a run bound to a session,
+    /// which signs m' = H_msg(sid, m) instead of the message itself.
+    pub fn verify_in_session<H: HashSuite<E>>(
+        message: &[u8],
+        session_id: &[u8],
+        pubkeys: &[PublicKey<E>],
+        signature: &Signature<E>,
+        jubjub_params: &<E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: &H,
+    ) -> Result<bool, MusigError> {
+        let message = hash_suite.hash_session_message(session_id, message);
+
+        Self::verify(
+            &message,
+            pubkeys,
+            signature,
+            jubjub_params,
+            generator,
+            hash_suite,
+        )
+    }
+
+    /// Verifies an aggregated signature against already computed key
+    /// aggregation.
+    pub fn verify_with_context<H: HashSuite<E>>(
+        message: &[u8],
+        context: &KeyAggregationContext<E>,
+        signature: &Signature<E>,
+        jubjub_params: &<E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: &H,
+    ) -> bool {
+        Self::verify_aggregated(
+            message,
+            &context.aggregated_pubkey,
+            signature,
+            jubjub_params,
+            generator,
+            hash_suite,
+        )
+    }
+
+    /// Verifies an aggregated signature against the aggregated public key
+    /// itself, e.g. a tweaked key whose committee keys are not known.
+    pub fn verify_aggregated<H: HashSuite<E>>(
+        message: &[u8],
+        aggregated_pubkey: &PublicKey<E>,
+        signature: &Signature<E>,
+        jubjub_params: &<E as JubjubEngine>::Params,
+        generator: FixedGenerators,
+        hash_suite: &H,
+    ) -> bool {
+        // both points must be in the prime order subgroup,
+        // R comes from the signer so it is always checked
+        if aggregated_pubkey.0.mul(E::Fs::char(), jubjub_params) != Point::zero()
+            || signature.r.mul(E::Fs::char(), jubjub_params) != Point::zero()
+        {
+            return false;
+        }
+
+        // c = H_sig(X', R, m)
+        let c = hash_suite.hash_signature_data(aggregated_pubkey, &signature.r, message);
+
+        // 0 = h_G(-s * G + R + c * X')
+        aggregated_pubkey
+            .0
+            .mul(c, jubjub_params)
+            .add(&signature.r, jubjub_params)
+            .add(
+                &jubjub_params
+                    .generator(generator)
+                    .mul(signature.s, jubjub_params)
+                    .negate()
+                    .into(),
+                jubjub_params,
+            )
+            .mul_by_cofactor(jubjub_params)
+            .eq(&Point::zero())
+    }
+
     pub(crate) fn verify_share(
         signature_share: &E::Fs,
         R_i: &Point<E, Unknown>,
@@ -0,0 +1,354 @@
+use crate::encoder::{read_canonical_point, STANDARD_ENCODING_LENGTH};
+use crate::errors::MusigError;
+use bellman::{PrimeField, PrimeFieldRepr};
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{JubjubEngine, Unknown};
+use std::convert::TryFrom;
+
+/// Version of the wire format, messages of any other version are rejected
+pub const WIRE_PROTOCOL_VERSION: u8 = 1;
+
+pub const SESSION_ID_LENGTH: usize = 32;
+
+/// Identifier of a signing ceremony, chosen by the parties before it starts
+pub type SessionId = [u8; SESSION_ID_LENGTH];
+
+// version || session id || round || sender (u32, big-endian) || payload length (u32, big-endian)
+const HEADER_LENGTH: usize = 1 + SESSION_ID_LENGTH + 1 + 4 + 4;
+
+/// Round of `MuSigSigner` a message belongs to. Abort isn't a round,
+/// a party sends it to stop the ceremony at any round.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Round {
+    PreCommitment = 1,
+    Commitment = 2,
+    SignatureShare = 3,
+    Abort = 0xff,
+}
+
+impl Round {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            1 => Some(Round::PreCommitment),
+            2 => Some(Round::Commitment),
+            3 => Some(Round::SignatureShare),
+            0xff => Some(Round::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// Output of a party in a single round
+pub enum Payload<E: JubjubEngine> {
+    /// Pre-commitment t_i = H_comm(R_i), 32 bytes
+    PreCommitment(Vec<u8>),
+    /// Revealed nonce commitment R_i, compressed point
+    Commitment(Point<E, Unknown>),
+    /// Signature share s_i, big-endian scalar
+    SignatureShare(E::Fs),
+    /// Sender stops the ceremony, empty payload
+    Abort,
+}
+
+impl<E: JubjubEngine> Payload<E> {
+    pub fn round(&self) -> Round {
+        match self {
+            Payload::PreCommitment(_) => Round::PreCommitment,
+            Payload::Commitment(_) => Round::Commitment,
+            Payload::SignatureShare(_) => Round::SignatureShare,
+            Payload::Abort => Round::Abort,
+        }
+    }
+
+    fn write(&self, dest: &mut Vec<u8>) {
+        match self {
+            Payload::PreCommitment(pre_commitment) => dest.extend_from_slice(pre_commitment),
+            Payload::Commitment(commitment) => {
+                commitment.write(dest).expect("has serialized point")
+            }
+            Payload::SignatureShare(share) => share
+                .into_repr()
+                .write_be(dest)
+                .expect("has serialized scalar"),
+            Payload::Abort => {}
+        }
+    }
+
+    fn read(
+        round: Round,
+        input: &[u8],
+        jubjub_params: &<E as JubjubEngine>::Params,
+    ) -> Result<Self, MusigError> {
+        // each payload except abort is a single 32 byte element
+        let expected_length = match round {
+            Round::Abort => 0,
+            _ => STANDARD_ENCODING_LENGTH,
+        };
+        if input.len() != expected_length {
+            return Err(MusigError::InvalidWireMessage);
+        }
+
+        match round {
+            Round::PreCommitment => Ok(Payload::PreCommitment(input.to_vec())),
+            Round::Commitment => read_canonical_point(input, jubjub_params)
+                .map(Payload::Commitment)
+                .ok_or(MusigError::InvalidWireMessage),
+            Round::SignatureShare => {
+                let mut repr = <E::Fs as PrimeField>::Repr::default();
+                repr.read_be(input)
+                    .map_err(|_| MusigError::InvalidWireMessage)?;
+                // rejects s_i >= order
+                E::Fs::from_repr(repr)
+                    .map(Payload::SignatureShare)
+                    .map_err(|_| MusigError::InvalidWireMessage)
+            }
+            Round::Abort => Ok(Payload::Abort),
+        }
+    }
+}
+
+impl<E: JubjubEngine> Clone for Payload<E> {
+    fn clone(&self) -> Self {
+        match self {
+            Payload::PreCommitment(pre_commitment) => {
+                Payload::PreCommitment(pre_commitment.clone())
+            }
+            Payload::Commitment(commitment) => Payload::Commitment(commitment.clone()),
+            Payload::SignatureShare(share) => Payload::SignatureShare(*share),
+            Payload::Abort => Payload::Abort,
+        }
+    }
+}
+
+impl<E: JubjubEngine> PartialEq for Payload<E> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Payload::PreCommitment(a), Payload::PreCommitment(b)) => a == b,
+            (Payload::Commitment(a), Payload::Commitment(b)) => a == b,
+            (Payload::SignatureShare(a), Payload::SignatureShare(b)) => a == b,
+            (Payload::Abort, Payload::Abort) => true,
+            _ => false,
+        }
+    }
+}
+
+/// Framed message of a signing ceremony. It is encoded as
+/// `version || session id || round || sender || payload length || payload`
+/// where sender position and payload length are big-endian u32.
+///
+/// Decoding is strict: it rejects unknown versions and rounds, payload
+/// length which doesn't match the round or the rest of the input, points
+/// which are off the curve or not canonically encoded and scalars which
+/// are not less than the group order.
+pub struct WireMessage<E: JubjubEngine> {
+    session_id: SessionId,
+    sender: usize,
+    payload: Payload<E>,
+}
+
+impl<E: JubjubEngine> WireMessage<E> {
+    pub fn new(
+        session_id: SessionId,
+        sender: usize,
+        payload: Payload<E>,
+    ) -> Result<Self, MusigError> {
+        if u32::try_from(sender).is_err() {
+            return Err(MusigError::InvalidParticipantPosition);
+        }
+
+        if let Payload::PreCommitment(pre_commitment) = &payload {
+            if pre_commitment.len() != STANDARD_ENCODING_LENGTH {
+                return Err(MusigError::InvalidWireMessage);
+            }
+        }
+
+        Ok(Self {
+            session_id,
+            sender,
+            payload,
+        })
+    }
+
+    pub fn session_id(&self) -> &SessionId {
+        &self.session_id
+    }
+
+    /// Position of the party who produced this message
+    pub fn sender(&self) -> usize {
+        self.sender
+    }
+
+    pub fn round(&self) -> Round {
+        self.payload.round()
+    }
+
+    pub fn payload(&self) -> &Payload<E> {
+        &self.payload
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = vec![];
+        self.payload.write(&mut payload);
+
+        let mut encoded = Vec::with_capacity(HEADER_LENGTH + payload.len());
+        encoded.push(WIRE_PROTOCOL_VERSION);
+        encoded.extend_from_slice(&self.session_id);
+        encoded.push(self.round() as u8);
+        encoded.extend_from_slice(&(self.sender as u32).to_be_bytes());
+        encoded.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        encoded.extend_from_slice(&payload);
+
+        encoded
+    }
+
+    pub fn from_bytes(
+        input: &[u8],
+        jubjub_params: &<E as JubjubEngine>::Params,
+    ) -> Result<Self, MusigError> {
+        if input.len() < HEADER_LENGTH {
+            return Err(MusigError::InvalidWireMessage);
+        }
+
+        if input[0] != WIRE_PROTOCOL_VERSION {
+            return Err(MusigError::UnsupportedWireVersion);
+        }
+
+        let mut session_id = [0u8; SESSION_ID_LENGTH];
+        session_id.copy_from_slice(&input[1..1 + SESSION_ID_LENGTH]);
+        let mut offset = 1 + SESSION_ID_LENGTH;
+
+        let round = Round::from_byte(input[offset]).ok_or(MusigError::InvalidWireMessage)?;
+        offset += 1;
+
+        let sender = read_u32(&input[offset..offset + 4]) as usize;
+        offset += 4;
+
+        let payload_length = read_u32(&input[offset..offset + 4]) as usize;
+        offset += 4;
+
+        // no trailing bytes after the payload
+        if input.len() - offset != payload_length {
+            return Err(MusigError::InvalidWireMessage);
+        }
+
+        let payload = Payload::read(round, &input[offset..], jubjub_params)?;
+
+        Ok(Self {
+            session_id,
+            sender,
+            payload,
+        })
+    }
+}
+
+impl<E: JubjubEngine> Clone for WireMessage<E> {
+    fn clone(&self) -> Self {
+        Self {
+            session_id: self.session_id,
+            sender: self.sender,
+            payload: self.payload.clone(),
+        }
+    }
+}
+
+impl<E: JubjubEngine> PartialEq for WireMessage<E> {
+    fn eq(&self, other: &Self) -> bool {
+        self.session_id == other.session_id
+            && self.sender == other.sender
+            && self.payload == other.payload
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut buf = [0u8; 4];
+    buf.copy_from_slice(bytes);
+
+    u32::from_be_bytes(buf)
+}
+
+/// Collects messages of all parties for a round of the session and returns
+/// their payloads in order of positions, as they are expected by the
+/// signer. Each position of `0..number_of_parties` must be present
+/// exactly once, messages may come in any order.
+pub fn collect_round<E: JubjubEngine>(
+    messages: &[WireMessage<E>],
+    session_id: &SessionId,
+    round: Round,
+    number_of_parties: usize,
+) -> Result<Vec<Payload<E>>, MusigError> {
+    let mut payloads: Vec<Option<Payload<E>>> = vec![None; number_of_parties];
+    for message in messages {
+        if &message.session_id != session_id {
+            return Err(MusigError::WireSessionMismatch);
+        }
+
+        if message.round() != round {
+            return Err(MusigError::UnexpectedWireRound);
+        }
+
+        match payloads.get_mut(message.sender) {
+            Some(slot) if slot.is_none() => *slot = Some(message.payload.clone()),
+            _ => return Err(MusigError::InvalidParticipantPosition),
+        }
+    }
+
+    payloads
+        .into_iter()
+        .map(|payload| payload.ok_or(MusigError::InvalidParticipantPosition))
+        .collect()
+}
+
+/// Pre-commitments of all parties, see `collect_round`
+pub fn collect_pre_commitments<E: JubjubEngine>(
+    messages: &[WireMessage<E>],
+    session_id: &SessionId,
+    number_of_parties: usize,
+) -> Result<Vec<Vec<u8>>, MusigError> {
+    collect_round(
+        messages,
+        session_id,
+        Round::PreCommitment,
+        number_of_parties,
+    )?
+    .into_iter()
+    .map(|payload| match payload {
+        Payload::PreCommitment(pre_commitment) => Ok(pre_commitment),
+        _ => Err(MusigError::UnexpectedWireRound),
+    })
+    .collect()
+}
+
+/// Revealed commitments of all parties, see `collect_round`
+pub fn collect_commitments<E: JubjubEngine>(
+    messages: &[WireMessage<E>],
+    session_id: &SessionId,
+    number_of_parties: usize,
+) -> Result<Vec<Point<E, Unknown>>, MusigError> {
+    collect_round(messages, session_id, Round::Commitment, number_of_parties)?
+        .into_iter()
+        .map(|payload| match payload {
+            Payload::Commitment(commitment) => Ok(commitment),
+            _ => Err(MusigError::UnexpectedWireRound),
+        })
+        .collect()
+}
+
+/// Signature shares of all parties, see `collect_round`
+pub fn collect_signature_shares<E: JubjubEngine>(
+    messages: &[WireMessage<E>],
+    session_id: &SessionId,
+    number_of_parties: usize,
+) -> Result<Vec<E::Fs>, MusigError> {
+    collect_round(
+        messages,
+        session_id,
+        Round::SignatureShare,
+        number_of_parties,
+    )?
+    .into_iter()
+    .map(|payload| match payload {
+        Payload::SignatureShare(share) => Ok(share),
+        _ => Err(MusigError::UnexpectedWireRound),
+    })
+    .collect()
+}
@@ -17,6 +17,7 @@ describe("Schnorr-MuSig", () => {
     const commitments = [];
     const aggregated_commitments = [];
     const signature_shares = [];
+    const used_nonces = [];
     const aggregated_signatures = [];
 
     const number_of_participants = 3;
@@ -69,7 +70,11 @@ describe("Schnorr-MuSig", () => {
     it("should compute signature share", () => {
         // each party should produce his own signature share
         for (let i = 0; i < number_of_participants; i++) {
-            signature_shares[i] = signers[i].sign(privkeys[i], message);
+            // list of used nonces is empty before the first signature,
+            // updated one must be stored before the share is sent
+            const output = signers[i].sign(privkeys[i], message, new Uint8Array());
+            used_nonces[i] = output.used_nonces();
+            signature_shares[i] = output.signature_share();
         }
     });
     it("should receive each signature shares", () => {
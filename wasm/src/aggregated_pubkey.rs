@@ -1,9 +1,33 @@
 use crate::decoder::{Decoder, STANDARD_ENCODING_LENGTH};
+use crate::engine::{MusigEngine, DEFAULT_CHALLENGE_HASH};
 use crate::errors::MusigABIError;
-use franklin_crypto::alt_babyjubjub::AltJubjubBn256;
+use bellman::pairing::bn256::Bn256;
 use musig::aggregated_pubkey::AggregatedPublicKey;
 use wasm_bindgen::prelude::*;
 
+/// Engine-generic computation of an encoded aggregated public key.
+pub fn compute<E: MusigEngine>(encoded_pubkeys: &[u8]) -> Result<Vec<u8>, JsValue> {
+    let jubjub_params = E::jubjub_params();
+
+    let pubkeys = Decoder::decode_pubkey_list::<E>(encoded_pubkeys, jubjub_params)?;
+
+    // aggregated key doesn't depend on the challenge hash
+    let (agg_pubkey, _) = AggregatedPublicKey::compute_for_each_party(
+        &pubkeys,
+        jubjub_params,
+        &E::hash_suite(DEFAULT_CHALLENGE_HASH),
+    )
+    .unwrap();
+
+    let mut encoded_agg_pubkey = vec![0u8; STANDARD_ENCODING_LENGTH];
+
+    agg_pubkey
+        .write(&mut encoded_agg_pubkey[..])
+        .map_err(|_| MusigABIError::EncodingError)?;
+
+    Ok(encoded_agg_pubkey)
+}
+
 #[wasm_bindgen]
 pub struct MusigBN256WasmAggregatedPubkey;
 
@@ -11,19 +35,6 @@ pub struct MusigBN256WasmAggregatedPubkey;
 impl MusigBN256WasmAggregatedPubkey {
     #[wasm_bindgen]
     pub fn compute(encoded_pubkeys: &[u8]) -> Result<Vec<u8>, JsValue> {
-        let jubjub_params = AltJubjubBn256::new();
-
-        let pubkeys = Decoder::decode_pubkey_list(encoded_pubkeys)?;
-
-        let (agg_pubkey, _) =
-            AggregatedPublicKey::compute_for_each_party(&pubkeys, &jubjub_params).unwrap();
-
-        let mut encoded_agg_pubkey = vec![0u8; STANDARD_ENCODING_LENGTH];
-
-        agg_pubkey
-            .write(&mut encoded_agg_pubkey[..])
-            .map_err(|_| MusigABIError::EncodingError)?;
-
-        Ok(encoded_agg_pubkey)
+        compute::<Bn256>(encoded_pubkeys)
     }
 }
@@ -1,10 +1,8 @@
 use super::errors::MusigABIError;
-use bellman::pairing::bn256::Bn256;
 use bellman::{Field, PrimeField, PrimeFieldRepr};
-use franklin_crypto::alt_babyjubjub::{fs::Fs, fs::FsRepr, AltJubjubBn256};
 use franklin_crypto::eddsa::{PrivateKey, PublicKey};
 use franklin_crypto::jubjub::edwards::Point;
-use franklin_crypto::jubjub::Unknown;
+use franklin_crypto::jubjub::{JubjubEngine, Unknown};
 
 // each encoded elements(point, scalar, pubkey) needs to have 32byte size
 pub const STANDARD_ENCODING_LENGTH: usize = 32;
@@ -13,49 +11,55 @@ pub struct Decoder;
 
 /// ABI Decoder for input data
 impl Decoder {
-    pub fn decode_private_key(input: &[u8]) -> Result<PrivateKey<Bn256>, MusigABIError> {
+    pub fn decode_private_key<E: JubjubEngine>(
+        input: &[u8],
+    ) -> Result<PrivateKey<E>, MusigABIError> {
         let privkey_len = STANDARD_ENCODING_LENGTH;
         if input.is_empty() || input.len() % privkey_len != 0 {
             return Err(MusigABIError::InvalidInputData);
         }
-        let mut privkey_repr = FsRepr::default();
+        let mut privkey_repr = <E::Fs as PrimeField>::Repr::default();
         privkey_repr.read_be(input).unwrap();
-        let priv_scalar = Fs::from_repr(privkey_repr).unwrap();
+        let priv_scalar = E::Fs::from_repr(privkey_repr).unwrap();
 
-        Ok(PrivateKey::<Bn256>(priv_scalar))
+        Ok(PrivateKey::<E>(priv_scalar))
     }
 
-    pub fn decode_pubkey_list(input: &[u8]) -> Result<Vec<PublicKey<Bn256>>, MusigABIError> {
+    pub fn decode_pubkey_list<E: JubjubEngine>(
+        input: &[u8],
+        params: &<E as JubjubEngine>::Params,
+    ) -> Result<Vec<PublicKey<E>>, MusigABIError> {
         let point_len = STANDARD_ENCODING_LENGTH;
         if input.is_empty() || input.len() % point_len != 0 {
             return Err(MusigABIError::InvalidInputData);
         }
         let number_of_pubkeys = input.len() / point_len;
-        let params = AltJubjubBn256::new();
 
-        let mut pubkeys = vec![PublicKey::<Bn256>(Point::zero()); number_of_pubkeys];
+        let mut pubkeys = vec![PublicKey::<E>(Point::zero()); number_of_pubkeys];
         for (i, pubkey) in pubkeys.iter_mut().enumerate() {
             let offset = i * point_len;
             let buf = input[offset..offset + point_len].to_vec();
-            *pubkey = PublicKey::read(&buf[..], &params).unwrap();
+            *pubkey = PublicKey::read(&buf[..], params).unwrap();
         }
 
         Ok(pubkeys)
     }
 
-    pub fn decode_commitments(input: &[u8]) -> Result<Vec<Point<Bn256, Unknown>>, MusigABIError> {
+    pub fn decode_commitments<E: JubjubEngine>(
+        input: &[u8],
+        params: &<E as JubjubEngine>::Params,
+    ) -> Result<Vec<Point<E, Unknown>>, MusigABIError> {
         let commitment_len = STANDARD_ENCODING_LENGTH;
         if input.is_empty() || input.len() % commitment_len != 0 {
             return Err(MusigABIError::InvalidInputData);
         }
         let number_of_pubkeys = input.len() / commitment_len;
-        let params = AltJubjubBn256::new();
 
         let mut commitments = vec![Point::zero(); number_of_pubkeys];
         for (i, commitment) in commitments.iter_mut().enumerate() {
             let offset = i * commitment_len;
             let buf = input[offset..(offset + commitment_len)].to_vec();
-            *commitment = Point::read(&buf[..], &params).unwrap();
+            *commitment = Point::read(&buf[..], params).unwrap();
         }
 
         Ok(commitments)
@@ -78,22 +82,24 @@ impl Decoder {
         Ok(pre_commitments)
     }
 
-    pub fn decode_signature_shares(input: &[u8]) -> Result<Vec<Fs>, MusigABIError> {
+    pub fn decode_signature_shares<E: JubjubEngine>(
+        input: &[u8],
+    ) -> Result<Vec<E::Fs>, MusigABIError> {
         let share_len = STANDARD_ENCODING_LENGTH;
         if input.is_empty() || input.len() % share_len != 0 {
             return Err(MusigABIError::InvalidInputData);
         }
         let number_of_pubkeys = input.len() / share_len;
 
-        let mut signature_shares = vec![Fs::zero(); number_of_pubkeys];
+        let mut signature_shares = vec![E::Fs::zero(); number_of_pubkeys];
 
         for (i, share) in signature_shares.iter_mut().enumerate() {
             let offset = i * share_len;
             let buf = input[offset..(offset + share_len)].to_vec();
 
-            let mut repr = FsRepr::default();
+            let mut repr = <E::Fs as PrimeField>::Repr::default();
             repr.read_be(&buf[..]).unwrap();
-            *share = Fs::from_repr(repr).unwrap();
+            *share = E::Fs::from_repr(repr).unwrap();
         }
 
         Ok(signature_shares)
@@ -144,11 +150,12 @@ mod tests {
                 .into_repr()
                 .write_be(&mut encoded_privkey[..])
                 .unwrap();
-            let actual_privkey = Decoder::decode_private_key(&encoded_privkey).unwrap();
+            let actual_privkey = Decoder::decode_private_key::<Bn256>(&encoded_privkey).unwrap();
             assert_eq!(expected_privkeys[i].0, actual_privkey.0);
         }
 
-        let actual_pubkeys = Decoder::decode_pubkey_list(&encoded_pubkeys).unwrap();
+        let actual_pubkeys =
+            Decoder::decode_pubkey_list::<Bn256>(&encoded_pubkeys, &params).unwrap();
 
         for (expected, actual) in expected_pubkeys.iter().zip(actual_pubkeys.iter()) {
             assert!(expected.0.eq(&actual.0));
@@ -0,0 +1,60 @@
+use crate::errors::MusigABIError;
+use crate::hash_suite::{ChallengeHash, WasmHashSuite};
+use bellman::pairing::bn256::Bn256;
+use franklin_crypto::alt_babyjubjub::AltJubjubBn256;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine};
+use franklin_crypto::rescue::bn256::Bn256RescueParams;
+use franklin_crypto::rescue::RescueEngine;
+use once_cell::sync::Lazy;
+
+// Construction of both parameter sets is expensive, so they are built
+// once per module instance on first use and shared by all calls.
+static BN256_JUBJUB_PARAMS: Lazy<AltJubjubBn256> = Lazy::new(AltJubjubBn256::new);
+static BN256_RESCUE_PARAMS: Lazy<Bn256RescueParams> =
+    Lazy::new(Bn256RescueParams::new_checked_2_into_1);
+
+/// Curve configuration the bindings are built for. Each exported set of
+/// wasm classes is a thin wrapper around the generic code instantiated
+/// with one implementor of this trait.
+pub trait MusigEngine: JubjubEngine + RescueEngine {
+    fn jubjub_params() -> &'static <Self as JubjubEngine>::Params;
+
+    fn rescue_params() -> &'static <Self as RescueEngine>::Params;
+
+    /// Hash suite with the given challenge hash
+    fn hash_suite(challenge_hash: ChallengeHash) -> WasmHashSuite<Self> {
+        WasmHashSuite::new(challenge_hash, Self::rescue_params())
+    }
+}
+
+impl MusigEngine for Bn256 {
+    fn jubjub_params() -> &'static AltJubjubBn256 {
+        &BN256_JUBJUB_PARAMS
+    }
+
+    fn rescue_params() -> &'static Bn256RescueParams {
+        &BN256_RESCUE_PARAMS
+    }
+}
+
+/// Challenge hash which is used when caller doesn't specify one.
+pub const DEFAULT_CHALLENGE_HASH: ChallengeHash = ChallengeHash::Rescue;
+
+/// Generator which is used when caller doesn't specify one.
+pub const DEFAULT_GENERATOR: FixedGenerators = FixedGenerators::SpendingKeyGenerator;
+
+/// Maps index of a generator, as it is declared in `FixedGenerators`,
+/// into the generator itself.
+pub fn generator_from_index(index: u8) -> Result<FixedGenerators, MusigABIError> {
+    let generator = match index {
+        0 => FixedGenerators::ProofGenerationKey,
+        1 => FixedGenerators::NoteCommitmentRandomness,
+        2 => FixedGenerators::NullifierPosition,
+        3 => FixedGenerators::ValueCommitmentValue,
+        4 => FixedGenerators::ValueCommitmentRandomness,
+        5 => FixedGenerators::SpendingKeyGenerator,
+        _ => return Err(MusigABIError::InvalidGenerator),
+    };
+
+    Ok(generator)
+}
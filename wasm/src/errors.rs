@@ -3,6 +3,12 @@ pub enum MusigABIError {
     MuSigError,
     InvalidInputData,
     EncodingError,
+    InvalidGenerator,
+    InvalidSeed,
+    DecryptionError,
+    InvalidParticipantPosition,
+    SessionAlreadyExists,
+    UnknownSession,
 }
 
 impl MusigABIError {
@@ -11,6 +17,14 @@ impl MusigABIError {
             MusigABIError::InvalidInputData => "Invalid input length",
             MusigABIError::MuSigError => "Error propogated from original musig",
             MusigABIError::EncodingError => "Can't encode output",
+            MusigABIError::InvalidGenerator => "Unknown generator",
+            MusigABIError::InvalidSeed => "Invalid seed",
+            MusigABIError::DecryptionError => "Can't decrypt data, password may be wrong",
+            MusigABIError::InvalidParticipantPosition => {
+                "Each participant position must be present exactly once"
+            }
+            MusigABIError::SessionAlreadyExists => "Session id has already been used",
+            MusigABIError::UnknownSession => "Session with given id does not exist",
         }
     }
 }
@@ -0,0 +1,141 @@
+use crate::errors::MusigABIError;
+use franklin_crypto::eddsa::PublicKey;
+use franklin_crypto::jubjub::edwards::Point;
+use franklin_crypto::jubjub::{JubjubEngine, Unknown};
+use franklin_crypto::rescue::RescueEngine;
+use musig::hash_suite::{DefaultHashSuite, HashSuite, Sha256ChallengeHashSuite};
+use wasm_bindgen::prelude::*;
+
+/// Hash of the challenge `c = H(X', R, m)`. Key aggregation is the same
+/// for both, so a group has a single aggregated key whichever is used.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChallengeHash {
+    /// Verifiable with `verify_musig_rescue` and in-circuit
+    Rescue = 0,
+    /// `uint256(sha256(x(X') || x(R) || m)) % order`, cheap to verify in EVM
+    Sha256 = 1,
+}
+
+impl ChallengeHash {
+    pub fn from_index(index: u8) -> Result<Self, MusigABIError> {
+        match index {
+            0 => Ok(ChallengeHash::Rescue),
+            1 => Ok(ChallengeHash::Sha256),
+            _ => Err(MusigABIError::InvalidInputData),
+        }
+    }
+}
+
+/// Hash suite chosen at runtime, since wasm classes can't be generic.
+pub enum WasmHashSuite<E: JubjubEngine + RescueEngine> {
+    Rescue(DefaultHashSuite<'static, E>),
+    Sha256(Sha256ChallengeHashSuite<'static, E>),
+}
+
+impl<E: JubjubEngine + RescueEngine> WasmHashSuite<E> {
+    pub fn new(
+        challenge_hash: ChallengeHash,
+        rescue_params: &'static <E as RescueEngine>::Params,
+    ) -> Self {
+        match challenge_hash {
+            ChallengeHash::Rescue => WasmHashSuite::Rescue(DefaultHashSuite::new(rescue_params)),
+            ChallengeHash::Sha256 => WasmHashSuite::Sha256(Sha256ChallengeHashSuite::new()),
+        }
+    }
+}
+
+impl<E: JubjubEngine + RescueEngine> HashSuite<E> for WasmHashSuite<E> {
+    fn hash_aggregated(&self, pubkeys: &[PublicKey<E>], position: usize) -> E::Fs {
+        match self {
+            WasmHashSuite::Rescue(suite) => suite.hash_aggregated(pubkeys, position),
+            WasmHashSuite::Sha256(suite) => suite.hash_aggregated(pubkeys, position),
+        }
+    }
+
+    fn hash_commitment(&self, commitment: &Point<E, Unknown>) -> Vec<u8> {
+        match self {
+            WasmHashSuite::Rescue(suite) => suite.hash_commitment(commitment),
+            WasmHashSuite::Sha256(suite) => suite.hash_commitment(commitment),
+        }
+    }
+
+    fn hash_signature_data(
+        &self,
+        aggregated_pubkey: &PublicKey<E>,
+        aggregated_commitment: &Point<E, Unknown>,
+        message: &[u8],
+    ) -> E::Fs {
+        match self {
+            WasmHashSuite::Rescue(suite) => {
+                suite.hash_signature_data(aggregated_pubkey, aggregated_commitment, message)
+            }
+            WasmHashSuite::Sha256(suite) => {
+                suite.hash_signature_data(aggregated_pubkey, aggregated_commitment, message)
+            }
+        }
+    }
+
+    fn hash_tweak(&self, aggregated_pubkey: &PublicKey<E>, data: &[u8]) -> E::Fs {
+        match self {
+            WasmHashSuite::Rescue(suite) => suite.hash_tweak(aggregated_pubkey, data),
+            WasmHashSuite::Sha256(suite) => suite.hash_tweak(aggregated_pubkey, data),
+        }
+    }
+
+    fn hash_adaptor_commitment(
+        &self,
+        commitment: &Point<E, Unknown>,
+        adaptor_point: &Point<E, Unknown>,
+    ) -> Vec<u8> {
+        match self {
+            WasmHashSuite::Rescue(suite) => {
+                suite.hash_adaptor_commitment(commitment, adaptor_point)
+            }
+            WasmHashSuite::Sha256(suite) => {
+                suite.hash_adaptor_commitment(commitment, adaptor_point)
+            }
+        }
+    }
+
+    fn hash_key_aggregation(
+        &self,
+        pubkeys: &[PublicKey<E>],
+        aggregated_pubkey: &PublicKey<E>,
+    ) -> Vec<u8> {
+        match self {
+            WasmHashSuite::Rescue(suite) => suite.hash_key_aggregation(pubkeys, aggregated_pubkey),
+            WasmHashSuite::Sha256(suite) => suite.hash_key_aggregation(pubkeys, aggregated_pubkey),
+        }
+    }
+
+    fn hash_session_commitment(
+        &self,
+        session_id: &[u8],
+        position: usize,
+        key_aggregation_hash: &[u8],
+        commitment_hash: &[u8],
+    ) -> Vec<u8> {
+        match self {
+            WasmHashSuite::Rescue(suite) => suite.hash_session_commitment(
+                session_id,
+                position,
+                key_aggregation_hash,
+                commitment_hash,
+            ),
+            WasmHashSuite::Sha256(suite) => suite.hash_session_commitment(
+                session_id,
+                position,
+                key_aggregation_hash,
+                commitment_hash,
+            ),
+        }
+    }
+
+    fn hash_session_message(&self, session_id: &[u8], message: &[u8]) -> Vec<u8> {
+        match self {
+            WasmHashSuite::Rescue(suite) => suite.hash_session_message(session_id, message),
+            WasmHashSuite::Sha256(suite) => suite.hash_session_message(session_id, message),
+        }
+    }
+}
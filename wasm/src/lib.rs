@@ -1,7 +1,12 @@
 pub mod aggregated_pubkey;
 mod decoder;
+pub mod engine;
 mod errors;
+pub mod hash_suite;
+pub mod messages;
+pub mod session_manager;
 pub mod signer;
+mod storage;
 #[cfg(test)]
 mod tests;
 pub mod verifier;
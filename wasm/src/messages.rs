@@ -0,0 +1,293 @@
+use crate::decoder::STANDARD_ENCODING_LENGTH;
+use crate::errors::MusigABIError;
+use wasm_bindgen::prelude::*;
+
+// position of the sender is encoded as big-endian u32 before the payload
+const POSITION_LENGTH: usize = 4;
+
+fn encode_with_position(position: usize, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = (position as u32).to_be_bytes().to_vec();
+    encoded.extend_from_slice(payload);
+
+    encoded
+}
+
+fn decode_with_position(input: &[u8]) -> Result<(usize, Vec<u8>), MusigABIError> {
+    if input.len() != POSITION_LENGTH + STANDARD_ENCODING_LENGTH {
+        return Err(MusigABIError::InvalidInputData);
+    }
+    let mut position = [0u8; POSITION_LENGTH];
+    position.copy_from_slice(&input[..POSITION_LENGTH]);
+
+    Ok((
+        u32::from_be_bytes(position) as usize,
+        input[POSITION_LENGTH..].to_vec(),
+    ))
+}
+
+/// Concatenates payloads in order of positions. Each position of
+/// `0..number_of_parties` must be present exactly once.
+fn concat_by_position<'a>(
+    messages: impl Iterator<Item = (usize, &'a [u8])>,
+    number_of_parties: usize,
+) -> Result<Vec<u8>, MusigABIError> {
+    let mut payloads: Vec<Option<&[u8]>> = vec![None; number_of_parties];
+    for (position, payload) in messages {
+        match payloads.get_mut(position) {
+            Some(slot) if slot.is_none() => *slot = Some(payload),
+            _ => return Err(MusigABIError::InvalidParticipantPosition),
+        }
+    }
+
+    let mut concatenated = vec![];
+    for payload in payloads {
+        concatenated.extend_from_slice(payload.ok_or(MusigABIError::InvalidParticipantPosition)?);
+    }
+
+    Ok(concatenated)
+}
+
+macro_rules! positioned_message {
+    ($(#[$meta:meta])* $name:ident, $list:ident) => {
+        $(#[$meta])*
+        #[wasm_bindgen]
+        #[derive(Clone)]
+        pub struct $name {
+            position: usize,
+            payload: Vec<u8>,
+        }
+
+        #[wasm_bindgen]
+        impl $name {
+            #[wasm_bindgen]
+            pub fn new(position: usize, payload: &[u8]) -> Result<$name, JsValue> {
+                if payload.len() != STANDARD_ENCODING_LENGTH {
+                    return Err(MusigABIError::InvalidInputData.into());
+                }
+
+                Ok($name {
+                    position,
+                    payload: payload.to_vec(),
+                })
+            }
+
+            /// Position of the participant who produced this message
+            #[wasm_bindgen]
+            pub fn position(&self) -> usize {
+                self.position
+            }
+
+            #[wasm_bindgen]
+            pub fn payload(&self) -> Vec<u8> {
+                self.payload.clone()
+            }
+
+            /// Encodes message as `position (u32, big-endian) || payload`
+            #[wasm_bindgen]
+            pub fn to_bytes(&self) -> Vec<u8> {
+                encode_with_position(self.position, &self.payload)
+            }
+
+            #[wasm_bindgen]
+            pub fn from_bytes(input: &[u8]) -> Result<$name, JsValue> {
+                let (position, payload) = decode_with_position(input)?;
+
+                Ok($name { position, payload })
+            }
+
+            #[wasm_bindgen]
+            pub fn to_hex(&self) -> String {
+                hex::encode(self.to_bytes())
+            }
+        }
+
+        /// Messages of all participants for a single round,
+        /// in any order.
+        #[wasm_bindgen]
+        #[derive(Default)]
+        pub struct $list {
+            messages: Vec<$name>,
+        }
+
+        #[wasm_bindgen]
+        impl $list {
+            #[wasm_bindgen]
+            pub fn new() -> $list {
+                $list::default()
+            }
+
+            #[wasm_bindgen]
+            pub fn push(&mut self, message: &$name) {
+                self.messages.push(message.clone());
+            }
+
+            #[wasm_bindgen]
+            pub fn len(&self) -> usize {
+                self.messages.len()
+            }
+
+            #[wasm_bindgen]
+            pub fn is_empty(&self) -> bool {
+                self.messages.is_empty()
+            }
+        }
+
+        impl $list {
+            /// Payloads concatenated in order of positions,
+            /// as they are expected by the signer.
+            pub(crate) fn concat_payloads(
+                &self,
+                number_of_parties: usize,
+            ) -> Result<Vec<u8>, MusigABIError> {
+                concat_by_position(
+                    self.messages
+                        .iter()
+                        .map(|message| (message.position, &message.payload[..])),
+                    number_of_parties,
+                )
+            }
+        }
+    };
+}
+
+positioned_message!(
+    /// Pre-commitment `t_i = H_comm(R_i)` of a participant
+    PreCommitment,
+    PreCommitmentList
+);
+
+positioned_message!(
+    /// Revealed nonce commitment `R_i` of a participant
+    Commitment,
+    CommitmentList
+);
+
+positioned_message!(
+    /// Signature share `s_i` of a participant
+    SignatureShare,
+    SignatureShareList
+);
+
+/// Aggregated signature `(R, s)`
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct AggregatedSignature {
+    encoded: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl AggregatedSignature {
+    #[wasm_bindgen]
+    pub fn r(&self) -> Vec<u8> {
+        self.encoded[..STANDARD_ENCODING_LENGTH].to_vec()
+    }
+
+    #[wasm_bindgen]
+    pub fn s(&self) -> Vec<u8> {
+        self.encoded[STANDARD_ENCODING_LENGTH..].to_vec()
+    }
+
+    /// Encodes signature as `R || s`
+    #[wasm_bindgen]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.encoded.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn from_bytes(input: &[u8]) -> Result<AggregatedSignature, JsValue> {
+        if input.len() != 2 * STANDARD_ENCODING_LENGTH {
+            return Err(MusigABIError::InvalidInputData.into());
+        }
+
+        Ok(AggregatedSignature {
+            encoded: input.to_vec(),
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn to_hex(&self) -> String {
+        hex::encode(&self.encoded)
+    }
+}
+
+/// Output of `sign`: signature share `s_i` of a participant together
+/// with the updated list of used nonces, which must be stored
+/// persistently before the share is sent.
+#[wasm_bindgen]
+pub struct SignOutput {
+    signature_share: SignatureShare,
+    used_nonces: Vec<u8>,
+}
+
+impl SignOutput {
+    pub(crate) fn new(signature_share: SignatureShare, used_nonces: Vec<u8>) -> SignOutput {
+        SignOutput {
+            signature_share,
+            used_nonces,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SignOutput {
+    #[wasm_bindgen]
+    pub fn signature_share(&self) -> Vec<u8> {
+        self.signature_share.payload()
+    }
+
+    /// Typed version of `signature_share`
+    #[wasm_bindgen]
+    pub fn typed_signature_share(&self) -> SignatureShare {
+        self.signature_share.clone()
+    }
+
+    #[wasm_bindgen]
+    pub fn used_nonces(&self) -> Vec<u8> {
+        self.used_nonces.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_with_position, PreCommitment, PreCommitmentList};
+    use crate::errors::MusigABIError;
+
+    #[test]
+    fn test_positioned_message_encoding() {
+        let message = PreCommitment::new(3, &[7u8; 32]).unwrap();
+
+        let encoded = message.to_bytes();
+        assert_eq!(encoded[..4], [0, 0, 0, 3]);
+        assert_eq!(message.to_hex(), hex::encode(&encoded));
+
+        let decoded = PreCommitment::from_bytes(&encoded).unwrap();
+        assert_eq!(decoded.position(), 3);
+        assert_eq!(decoded.payload(), vec![7u8; 32]);
+
+        assert_eq!(
+            decode_with_position(&encoded[1..]).unwrap_err(),
+            MusigABIError::InvalidInputData
+        );
+    }
+
+    #[test]
+    fn test_message_list_ordering() {
+        let mut list = PreCommitmentList::new();
+        list.push(&PreCommitment::new(1, &[1u8; 32]).unwrap());
+        list.push(&PreCommitment::new(0, &[0u8; 32]).unwrap());
+
+        let concatenated = list.concat_payloads(2).unwrap();
+        assert_eq!(concatenated, [vec![0u8; 32], vec![1u8; 32]].concat());
+
+        assert_eq!(
+            list.concat_payloads(3).unwrap_err(),
+            MusigABIError::InvalidParticipantPosition
+        );
+
+        list.push(&PreCommitment::new(1, &[1u8; 32]).unwrap());
+        assert_eq!(
+            list.concat_payloads(2).unwrap_err(),
+            MusigABIError::InvalidParticipantPosition
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use crate::decoder::{Decoder, STANDARD_ENCODING_LENGTH};
+use crate::engine::{generator_from_index, MusigEngine, DEFAULT_CHALLENGE_HASH, DEFAULT_GENERATOR};
+use crate::errors::MusigABIError;
+use crate::hash_suite::ChallengeHash;
+use crate::signer::WasmSigner;
+use bellman::pairing::bn256::Bn256;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine};
+use musig::aggregated_pubkey::KeyAggregationContext;
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen::prelude::*;
+
+/// Engine-generic manager of concurrent signing sessions of one group.
+/// Key aggregation is computed once and shared by all sessions.
+pub struct SessionManager<E: MusigEngine>
+where
+    <E as JubjubEngine>::Params: 'static,
+{
+    context: KeyAggregationContext<E>,
+    position: usize,
+    generator: FixedGenerators,
+    challenge_hash: ChallengeHash,
+    sessions: HashMap<String, WasmSigner<E>>,
+    // ids of finished sessions are kept as well, since a session id
+    // must never be reused
+    used_session_ids: HashSet<String>,
+}
+
+impl<E: MusigEngine> SessionManager<E>
+where
+    <E as JubjubEngine>::Params: 'static,
+{
+    pub fn new(
+        input: &[u8], // concatenation of all pubkeys
+        position: usize,
+        generator: FixedGenerators,
+        challenge_hash: ChallengeHash,
+    ) -> Result<Self, JsValue> {
+        let jubjub_params = E::jubjub_params();
+
+        let pubkeys = Decoder::decode_pubkey_list::<E>(input, jubjub_params)?;
+
+        let context =
+            KeyAggregationContext::new(&pubkeys, jubjub_params, &E::hash_suite(challenge_hash))
+                .map_err(|e| JsValue::from(format!("{}", e)))?;
+
+        if position >= pubkeys.len() {
+            return Err(MusigABIError::InvalidParticipantPosition.into());
+        }
+
+        Ok(Self {
+            context,
+            position,
+            generator,
+            challenge_hash,
+            sessions: HashMap::new(),
+            used_session_ids: HashSet::new(),
+        })
+    }
+
+    pub fn aggregated_pubkey(&self) -> Result<Vec<u8>, JsValue> {
+        let mut encoded_agg_pubkey = vec![0u8; STANDARD_ENCODING_LENGTH];
+
+        self.context
+            .aggregated_pubkey
+            .write(&mut encoded_agg_pubkey[..])
+            .map_err(|_| MusigABIError::EncodingError)?;
+
+        Ok(encoded_agg_pubkey)
+    }
+
+    pub fn start_session(&mut self, session_id: &str) -> Result<(), JsValue> {
+        if !self.used_session_ids.insert(session_id.to_string()) {
+            return Err(MusigABIError::SessionAlreadyExists.into());
+        }
+
+        let signer = WasmSigner::from_context(
+            &self.context,
+            self.position,
+            self.generator,
+            self.challenge_hash,
+        )?;
+        self.sessions.insert(session_id.to_string(), signer);
+
+        Ok(())
+    }
+
+    pub fn session(&mut self, session_id: &str) -> Result<&mut WasmSigner<E>, MusigABIError> {
+        self.sessions
+            .get_mut(session_id)
+            .ok_or(MusigABIError::UnknownSession)
+    }
+
+    pub fn finish_session(&mut self, session_id: &str) -> Result<(), JsValue> {
+        self.sessions
+            .remove(session_id)
+            .ok_or(MusigABIError::UnknownSession)?;
+
+        Ok(())
+    }
+
+    pub fn number_of_sessions(&self) -> usize {
+        self.sessions.len()
+    }
+}
+
+#[wasm_bindgen]
+pub struct MusigBN256WasmSessionManager {
+    inner: SessionManager<Bn256>,
+}
+
+#[wasm_bindgen]
+impl MusigBN256WasmSessionManager {
+    #[wasm_bindgen]
+    pub fn new(
+        input: &[u8], // concatenation of all pubkeys
+        position: usize,
+    ) -> Result<MusigBN256WasmSessionManager, JsValue> {
+        let inner =
+            SessionManager::new(input, position, DEFAULT_GENERATOR, DEFAULT_CHALLENGE_HASH)?;
+
+        Ok(MusigBN256WasmSessionManager { inner })
+    }
+
+    /// Same as `new` but uses generator with given index
+    /// instead of the default one.
+    #[wasm_bindgen]
+    pub fn new_with_generator(
+        input: &[u8], // concatenation of all pubkeys
+        position: usize,
+        generator: u8,
+    ) -> Result<MusigBN256WasmSessionManager, JsValue> {
+        let generator = generator_from_index(generator)?;
+        let inner = SessionManager::new(input, position, generator, DEFAULT_CHALLENGE_HASH)?;
+
+        Ok(MusigBN256WasmSessionManager { inner })
+    }
+
+    /// Same as `new_with_generator` but also selects hash of the challenge
+    /// for all sessions.
+    #[wasm_bindgen]
+    pub fn new_with_challenge_hash(
+        input: &[u8], // concatenation of all pubkeys
+        position: usize,
+        generator: u8,
+        challenge_hash: ChallengeHash,
+    ) -> Result<MusigBN256WasmSessionManager, JsValue> {
+        let generator = generator_from_index(generator)?;
+        let inner = SessionManager::new(input, position, generator, challenge_hash)?;
+
+        Ok(MusigBN256WasmSessionManager { inner })
+    }
+
+    #[wasm_bindgen]
+    pub fn aggregated_pubkey(&self) -> Result<Vec<u8>, JsValue> {
+        self.inner.aggregated_pubkey()
+    }
+
+    /// Starts a new signing session. Session ids can't be reused,
+    /// even after the session with the same id has been finished.
+    #[wasm_bindgen]
+    pub fn start_session(&mut self, session_id: &str) -> Result<(), JsValue> {
+        self.inner.start_session(session_id)
+    }
+
+    /// Drops all data of the session
+    #[wasm_bindgen]
+    pub fn finish_session(&mut self, session_id: &str) -> Result<(), JsValue> {
+        self.inner.finish_session(session_id)
+    }
+
+    #[wasm_bindgen]
+    pub fn number_of_sessions(&self) -> usize {
+        self.inner.number_of_sessions()
+    }
+
+    #[wasm_bindgen]
+    pub fn compute_precommitment(
+        &mut self,
+        session_id: &str,
+        seed: &[u32],
+    ) -> Result<Vec<u8>, JsValue> {
+        self.inner.session(session_id)?.compute_precommitment(seed)
+    }
+
+    #[wasm_bindgen]
+    pub fn receive_precommitments(
+        &mut self,
+        session_id: &str,
+        input: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        self.inner
+            .session(session_id)?
+            .receive_precommitments(input)
+    }
+
+    #[wasm_bindgen]
+    pub fn receive_commitments(
+        &mut self,
+        session_id: &str,
+        input: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        self.inner.session(session_id)?.receive_commitments(input)
+    }
+
+    #[wasm_bindgen]
+    pub fn sign(
+        &mut self,
+        session_id: &str,
+        private_key_bytes: &[u8],
+        message: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        self.inner
+            .session(session_id)?
+            .sign(private_key_bytes, message)
+    }
+
+    #[wasm_bindgen]
+    pub fn receive_signature_shares(
+        &mut self,
+        session_id: &str,
+        input: &[u8],
+    ) -> Result<Vec<u8>, JsValue> {
+        self.inner
+            .session(session_id)?
+            .receive_signature_shares(input)
+    }
+}
@@ -1,45 +1,144 @@
-use crate::decoder::Decoder;
+use crate::decoder::{Decoder, STANDARD_ENCODING_LENGTH};
+use crate::engine::{generator_from_index, MusigEngine, DEFAULT_CHALLENGE_HASH, DEFAULT_GENERATOR};
 use crate::errors::MusigABIError;
+use crate::hash_suite::{ChallengeHash, WasmHashSuite};
+use crate::messages::{
+    AggregatedSignature, Commitment, CommitmentList, PreCommitment, PreCommitmentList, SignOutput,
+    SignatureShare, SignatureShareList,
+};
+use crate::storage;
 use bellman::pairing::bn256::Bn256;
 use bellman::{PrimeField, PrimeFieldRepr};
-use franklin_crypto::alt_babyjubjub::AltJubjubBn256;
-use franklin_crypto::jubjub::FixedGenerators;
-use musig::signer::MuSigSigner;
+use franklin_crypto::jubjub::{FixedGenerators, JubjubEngine};
+use musig::aggregated_pubkey::KeyAggregationContext;
+use musig::errors::MusigError;
+use musig::signature::MusigSignature;
+use musig::signer::{MuSigSigner, UsedNonces};
 use rand::SeedableRng;
 use wasm_bindgen::prelude::*;
 
-#[wasm_bindgen]
-pub struct MusigBN256WasmSigner {
-    musig_signer: MuSigSigner<Bn256>,
-}
-
 impl From<MusigABIError> for JsValue {
     fn from(err: MusigABIError) -> Self {
         JsValue::from(err.to_string())
     }
 }
 
-#[wasm_bindgen]
-impl MusigBN256WasmSigner {
-    #[wasm_bindgen]
+/// Engine-generic signer which works on encoded inputs and outputs.
+/// Wasm classes forward their calls to it.
+pub struct WasmSigner<E: MusigEngine>
+where
+    <E as JubjubEngine>::Params: 'static,
+{
+    musig_signer: MuSigSigner<'static, E, WasmHashSuite<E>>,
+    generator: FixedGenerators,
+    challenge_hash: ChallengeHash,
+}
+
+impl<E: MusigEngine> WasmSigner<E>
+where
+    <E as JubjubEngine>::Params: 'static,
+{
     pub fn new(
         input: &[u8], // concatenation of all pubkeys
         position: usize,
-    ) -> Result<MusigBN256WasmSigner, JsValue> {
-        let pubkeys = Decoder::decode_pubkey_list(input)?;
+        generator: FixedGenerators,
+        challenge_hash: ChallengeHash,
+    ) -> Result<Self, JsValue> {
+        let jubjub_params = E::jubjub_params();
 
-        let jubjub_params = AltJubjubBn256::new();
-        let generator = FixedGenerators::SpendingKeyGenerator;
+        let pubkeys = Decoder::decode_pubkey_list::<E>(input, jubjub_params)?;
 
-        let signer = MuSigSigner::new(&pubkeys[..], position, jubjub_params, generator)
-            .map_err(|e| JsValue::from(format!("{}", e)))?;
+        let signer = MuSigSigner::new(
+            &pubkeys[..],
+            position,
+            jubjub_params,
+            generator,
+            E::hash_suite(challenge_hash),
+        )
+        .map_err(|e| JsValue::from(format!("{}", e)))?;
 
-        Ok(MusigBN256WasmSigner {
+        Ok(Self {
             musig_signer: signer,
+            generator,
+            challenge_hash,
+        })
+    }
+
+    pub fn from_context(
+        context: &KeyAggregationContext<E>,
+        position: usize,
+        generator: FixedGenerators,
+        challenge_hash: ChallengeHash,
+    ) -> Result<Self, JsValue> {
+        let signer = MuSigSigner::new_with_context(
+            context,
+            position,
+            E::jubjub_params(),
+            generator,
+            E::hash_suite(challenge_hash),
+        )
+        .map_err(|e| JsValue::from(format!("{}", e)))?;
+
+        Ok(Self {
+            musig_signer: signer,
+            generator,
+            challenge_hash,
+        })
+    }
+
+    pub fn position(&self) -> usize {
+        self.musig_signer.position()
+    }
+
+    pub fn number_of_parties(&self) -> usize {
+        self.musig_signer.pubkeys().len()
+    }
+
+    pub fn jubjub_params(&self) -> &'static <E as JubjubEngine>::Params {
+        self.musig_signer.jubjub_params()
+    }
+
+    /// Encrypts current state of the signer with the password, so it can
+    /// be kept in browser storage and restored with `import_state`.
+    pub fn export_state(&self, password: &str, seed: &[u32]) -> Result<Vec<u8>, JsValue> {
+        let mut state = vec![self.generator as u8, self.challenge_hash as u8];
+        state.extend_from_slice(&self.musig_signer.export_state());
+
+        let encrypted_state = storage::encrypt(&state, password, seed)?;
+
+        Ok(encrypted_state)
+    }
+
+    pub fn import_state(
+        encrypted_state: &[u8],
+        password: &str,
+        used_nonces: &[u8],
+    ) -> Result<Self, JsValue> {
+        let state = storage::decrypt(encrypted_state, password)?;
+        if state.len() < 2 {
+            return Err(MusigABIError::InvalidInputData.into());
+        }
+        let generator = generator_from_index(state[0])?;
+        let challenge_hash = ChallengeHash::from_index(state[1])?;
+        let used_nonces = UsedNonces::from_bytes(used_nonces)
+            .map_err(|_| JsValue::from(MusigABIError::InvalidInputData))?;
+
+        let signer = MuSigSigner::import_state(
+            &state[2..],
+            E::jubjub_params(),
+            generator,
+            E::hash_suite(challenge_hash),
+            &used_nonces,
+        )
+        .map_err(|e| JsValue::from(format!("{}", e)))?;
+
+        Ok(Self {
+            musig_signer: signer,
+            generator,
+            challenge_hash,
         })
     }
 
-    #[wasm_bindgen]
     pub fn compute_precommitment(&mut self, seed: &[u32]) -> Result<Vec<u8>, JsValue> {
         if seed.len() < 4 {
             return Err(JsValue::from_str("Invalid seed"));
@@ -54,7 +153,6 @@ impl MusigBN256WasmSigner {
         Ok(pre_commitment)
     }
 
-    #[wasm_bindgen]
     pub fn receive_precommitments(&mut self, input: &[u8]) -> Result<Vec<u8>, JsValue> {
         let pre_commitments = Decoder::decode_pre_commitments(input)?;
 
@@ -63,7 +161,7 @@ impl MusigBN256WasmSigner {
             .receive_precommitments(&pre_commitments)
             .map_err(|e| JsValue::from(format!("{}", e)))?;
 
-        let mut encoded_nonce_commitment = vec![0u8; crate::decoder::STANDARD_ENCODING_LENGTH];
+        let mut encoded_nonce_commitment = vec![0u8; STANDARD_ENCODING_LENGTH];
 
         nonce_commitment
             .write(&mut encoded_nonce_commitment[..])
@@ -72,16 +170,15 @@ impl MusigBN256WasmSigner {
         Ok(encoded_nonce_commitment)
     }
 
-    #[wasm_bindgen]
     pub fn receive_commitments(&mut self, input: &[u8]) -> Result<Vec<u8>, JsValue> {
-        let commitments = Decoder::decode_commitments(input)?;
+        let commitments = Decoder::decode_commitments::<E>(input, E::jubjub_params())?;
 
         let aggregated_commitment = self
             .musig_signer
             .receive_commitments(&commitments)
             .map_err(|e| JsValue::from(format!("{}", e)))?;
 
-        let mut encoded_agg_commitment = vec![0u8; crate::decoder::STANDARD_ENCODING_LENGTH];
+        let mut encoded_agg_commitment = vec![0u8; STANDARD_ENCODING_LENGTH];
 
         aggregated_commitment
             .write(&mut encoded_agg_commitment[..])
@@ -90,19 +187,15 @@ impl MusigBN256WasmSigner {
         Ok(encoded_agg_commitment)
     }
 
-    #[wasm_bindgen]
     pub fn sign(&mut self, private_key_bytes: &[u8], message: &[u8]) -> Result<Vec<u8>, JsValue> {
-        let rescue_params =
-            franklin_crypto::rescue::bn256::Bn256RescueParams::new_checked_2_into_1();
-
-        let private_key = Decoder::decode_private_key(private_key_bytes)?;
+        let private_key = Decoder::decode_private_key::<E>(private_key_bytes)?;
 
         let signature_share = self
             .musig_signer
-            .sign(&private_key, message, &rescue_params)
+            .sign(&private_key, message)
             .map_err(|e| JsValue::from(format!("{}", e)))?;
 
-        let mut encoded_sig_share = vec![0u8; crate::decoder::STANDARD_ENCODING_LENGTH];
+        let mut encoded_sig_share = vec![0u8; STANDARD_ENCODING_LENGTH];
 
         signature_share
             .into_repr()
@@ -112,9 +205,34 @@ impl MusigBN256WasmSigner {
         Ok(encoded_sig_share)
     }
 
-    #[wasm_bindgen]
+    /// Same as `sign` but first checks the nonce of this signer against
+    /// the encoded list of used nonces and returns the share together
+    /// with the list updated with this nonce.
+    pub fn sign_and_record_nonce(
+        &mut self,
+        private_key_bytes: &[u8],
+        message: &[u8],
+        used_nonces: &[u8],
+    ) -> Result<(Vec<u8>, Vec<u8>), JsValue> {
+        let mut used_nonces = UsedNonces::from_bytes(used_nonces)
+            .map_err(|_| JsValue::from(MusigABIError::InvalidInputData))?;
+        let nonce_commitment = self
+            .musig_signer
+            .nonce_commitment()
+            .ok_or_else(|| JsValue::from(format!("{}", MusigError::NonceCommitmentNotGenerated)))?
+            .clone();
+        if used_nonces.contains(&nonce_commitment) {
+            return Err(JsValue::from(format!("{}", MusigError::NonceAlreadyUsed)));
+        }
+
+        let signature_share = self.sign(private_key_bytes, message)?;
+        used_nonces.insert(&nonce_commitment);
+
+        Ok((signature_share, used_nonces.to_bytes()))
+    }
+
     pub fn receive_signature_shares(&self, input: &[u8]) -> Result<Vec<u8>, JsValue> {
-        let signature_shares = Decoder::decode_signature_shares(input)?;
+        let signature_shares = Decoder::decode_signature_shares::<E>(input)?;
 
         let signature = self
             .musig_signer
@@ -122,18 +240,163 @@ impl MusigBN256WasmSigner {
             .map_err(|e| JsValue::from(format!("{}", e)))?;
 
         // (R, s)
-        let mut encoded_sig = vec![0u8; 2 * crate::decoder::STANDARD_ENCODING_LENGTH];
-        signature
-            .r
-            .write(&mut encoded_sig[..crate::decoder::STANDARD_ENCODING_LENGTH])
-            .map_err(|_| MusigABIError::EncodingError)?;
-
-        signature
-            .s
-            .into_repr()
-            .write_le(&mut encoded_sig[crate::decoder::STANDARD_ENCODING_LENGTH..])
-            .map_err(|_| MusigABIError::EncodingError)?;
+        let encoded_sig = MusigSignature::from(signature).to_bytes();
 
         Ok(encoded_sig)
     }
 }
+
+#[wasm_bindgen]
+pub struct MusigBN256WasmSigner {
+    inner: WasmSigner<Bn256>,
+}
+
+#[wasm_bindgen]
+impl MusigBN256WasmSigner {
+    #[wasm_bindgen]
+    pub fn new(
+        input: &[u8], // concatenation of all pubkeys
+        position: usize,
+    ) -> Result<MusigBN256WasmSigner, JsValue> {
+        let inner = WasmSigner::new(input, position, DEFAULT_GENERATOR, DEFAULT_CHALLENGE_HASH)?;
+
+        Ok(MusigBN256WasmSigner { inner })
+    }
+
+    /// Same as `new` but uses generator with given index
+    /// instead of the default one.
+    #[wasm_bindgen]
+    pub fn new_with_generator(
+        input: &[u8], // concatenation of all pubkeys
+        position: usize,
+        generator: u8,
+    ) -> Result<MusigBN256WasmSigner, JsValue> {
+        let generator = generator_from_index(generator)?;
+        let inner = WasmSigner::new(input, position, generator, DEFAULT_CHALLENGE_HASH)?;
+
+        Ok(MusigBN256WasmSigner { inner })
+    }
+
+    /// Same as `new_with_generator` but also selects hash of the challenge.
+    /// Verifier must use the same one.
+    #[wasm_bindgen]
+    pub fn new_with_challenge_hash(
+        input: &[u8], // concatenation of all pubkeys
+        position: usize,
+        generator: u8,
+        challenge_hash: ChallengeHash,
+    ) -> Result<MusigBN256WasmSigner, JsValue> {
+        let generator = generator_from_index(generator)?;
+        let inner = WasmSigner::new(input, position, generator, challenge_hash)?;
+
+        Ok(MusigBN256WasmSigner { inner })
+    }
+
+    /// Encrypts the signer state with the password. Seed is used to
+    /// sample salt and IV, it must be as random as the one passed
+    /// to `compute_precommitment`.
+    #[wasm_bindgen]
+    pub fn export_state(&self, password: &str, seed: &[u32]) -> Result<Vec<u8>, JsValue> {
+        self.inner.export_state(password, seed)
+    }
+
+    /// Restores signer from the output of `export_state`. Restored
+    /// signer refuses to sign again if its nonce was already used,
+    /// either before the export or according to `used_nonces`, the
+    /// list returned by `sign`. An empty list is valid.
+    #[wasm_bindgen]
+    pub fn import_state(
+        encrypted_state: &[u8],
+        password: &str,
+        used_nonces: &[u8],
+    ) -> Result<MusigBN256WasmSigner, JsValue> {
+        let inner = WasmSigner::import_state(encrypted_state, password, used_nonces)?;
+
+        Ok(MusigBN256WasmSigner { inner })
+    }
+
+    #[wasm_bindgen]
+    pub fn compute_precommitment(&mut self, seed: &[u32]) -> Result<Vec<u8>, JsValue> {
+        self.inner.compute_precommitment(seed)
+    }
+
+    #[wasm_bindgen]
+    pub fn receive_precommitments(&mut self, input: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.inner.receive_precommitments(input)
+    }
+
+    #[wasm_bindgen]
+    pub fn receive_commitments(&mut self, input: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.inner.receive_commitments(input)
+    }
+
+    /// Produces signature share and adds nonce of this signer to
+    /// `used_nonces`, the encoded list of used nonce commitments (empty
+    /// for the first signature). The updated list must be stored
+    /// persistently before the share is sent, so a state exported
+    /// before signing can't sign another message with the same nonce.
+    #[wasm_bindgen]
+    pub fn sign(
+        &mut self,
+        private_key_bytes: &[u8],
+        message: &[u8],
+        used_nonces: &[u8],
+    ) -> Result<SignOutput, JsValue> {
+        let (signature_share, used_nonces) =
+            self.inner
+                .sign_and_record_nonce(private_key_bytes, message, used_nonces)?;
+        let signature_share = SignatureShare::new(self.inner.position(), &signature_share)?;
+
+        Ok(SignOutput::new(signature_share, used_nonces))
+    }
+
+    #[wasm_bindgen]
+    pub fn receive_signature_shares(&self, input: &[u8]) -> Result<Vec<u8>, JsValue> {
+        self.inner.receive_signature_shares(input)
+    }
+
+    /// Typed version of `compute_precommitment`
+    #[wasm_bindgen]
+    pub fn compute_typed_precommitment(&mut self, seed: &[u32]) -> Result<PreCommitment, JsValue> {
+        let pre_commitment = self.inner.compute_precommitment(seed)?;
+
+        PreCommitment::new(self.inner.position(), &pre_commitment)
+    }
+
+    /// Typed version of `receive_precommitments`, pre-commitments
+    /// may be pushed into the list in any order.
+    #[wasm_bindgen]
+    pub fn receive_typed_precommitments(
+        &mut self,
+        pre_commitments: &PreCommitmentList,
+    ) -> Result<Commitment, JsValue> {
+        let input = pre_commitments.concat_payloads(self.inner.number_of_parties())?;
+        let commitment = self.inner.receive_precommitments(&input)?;
+
+        Commitment::new(self.inner.position(), &commitment)
+    }
+
+    /// Typed version of `receive_commitments`, returns
+    /// encoded aggregated commitment.
+    #[wasm_bindgen]
+    pub fn receive_typed_commitments(
+        &mut self,
+        commitments: &CommitmentList,
+    ) -> Result<Vec<u8>, JsValue> {
+        let input = commitments.concat_payloads(self.inner.number_of_parties())?;
+
+        self.inner.receive_commitments(&input)
+    }
+
+    /// Typed version of `receive_signature_shares`
+    #[wasm_bindgen]
+    pub fn receive_typed_signature_shares(
+        &self,
+        signature_shares: &SignatureShareList,
+    ) -> Result<AggregatedSignature, JsValue> {
+        let input = signature_shares.concat_payloads(self.inner.number_of_parties())?;
+        let signature = self.inner.receive_signature_shares(&input)?;
+
+        AggregatedSignature::from_bytes(&signature)
+    }
+}
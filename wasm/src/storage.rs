@@ -0,0 +1,102 @@
+use crate::errors::MusigABIError;
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{Aead, NewAead, Payload};
+use aes_gcm::Aes256Gcm;
+use hmac::Hmac;
+use rand::{Rng, SeedableRng};
+use sha2::Sha256;
+
+const STORAGE_VERSION: u8 = 1;
+const SALT_LENGTH: usize = 16;
+const IV_LENGTH: usize = 12;
+const KEY_LENGTH: usize = 32;
+const PBKDF2_ROUNDS: usize = 100_000;
+
+/// Encrypts data with a key derived from the password. Salt and IV are
+/// sampled from rng which is seeded with given seed, so seed must be
+/// freshly generated by a cryptographically secure generator.
+/// Output layout is `version || salt || iv || ciphertext`.
+pub fn encrypt(data: &[u8], password: &str, seed: &[u32]) -> Result<Vec<u8>, MusigABIError> {
+    if seed.len() < 4 {
+        return Err(MusigABIError::InvalidSeed);
+    }
+    let mut rng = rand::ChaChaRng::from_seed(seed);
+
+    let mut salt = [0u8; SALT_LENGTH];
+    rng.fill_bytes(&mut salt);
+    let mut iv = [0u8; IV_LENGTH];
+    rng.fill_bytes(&mut iv);
+
+    let cipher = cipher(password, &salt);
+    let ciphertext = cipher
+        .encrypt(
+            GenericArray::from_slice(&iv),
+            Payload {
+                msg: data,
+                aad: &[STORAGE_VERSION],
+            },
+        )
+        .map_err(|_| MusigABIError::EncodingError)?;
+
+    let mut encrypted = vec![STORAGE_VERSION];
+    encrypted.extend_from_slice(&salt);
+    encrypted.extend_from_slice(&iv);
+    encrypted.extend_from_slice(&ciphertext);
+
+    Ok(encrypted)
+}
+
+/// Decrypts data produced by `encrypt`.
+pub fn decrypt(encrypted: &[u8], password: &str) -> Result<Vec<u8>, MusigABIError> {
+    if encrypted.len() < 1 + SALT_LENGTH + IV_LENGTH || encrypted[0] != STORAGE_VERSION {
+        return Err(MusigABIError::InvalidInputData);
+    }
+    let salt = &encrypted[1..(1 + SALT_LENGTH)];
+    let iv = &encrypted[(1 + SALT_LENGTH)..(1 + SALT_LENGTH + IV_LENGTH)];
+    let ciphertext = &encrypted[(1 + SALT_LENGTH + IV_LENGTH)..];
+
+    let cipher = cipher(password, salt);
+    cipher
+        .decrypt(
+            GenericArray::from_slice(iv),
+            Payload {
+                msg: ciphertext,
+                aad: &[STORAGE_VERSION],
+            },
+        )
+        .map_err(|_| MusigABIError::DecryptionError)
+}
+
+fn cipher(password: &str, salt: &[u8]) -> Aes256Gcm {
+    let mut key = [0u8; KEY_LENGTH];
+    pbkdf2::pbkdf2::<Hmac<Sha256>>(password.as_bytes(), salt, PBKDF2_ROUNDS, &mut key);
+
+    Aes256Gcm::new(GenericArray::from_slice(&key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decrypt, encrypt};
+    use crate::errors::MusigABIError;
+
+    #[test]
+    fn test_encrypt_decrypt() {
+        let data = vec![1u8, 2, 3, 4, 5];
+        let seed = [1u32, 2, 3, 4];
+
+        let encrypted = encrypt(&data, "password", &seed).unwrap();
+        assert_eq!(decrypt(&encrypted, "password").unwrap(), data);
+
+        assert_eq!(
+            decrypt(&encrypted, "wrong password").unwrap_err(),
+            MusigABIError::DecryptionError
+        );
+
+        let mut tampered = encrypted;
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            decrypt(&tampered, "password").unwrap_err(),
+            MusigABIError::DecryptionError
+        );
+    }
+}
@@ -1,7 +1,13 @@
 mod tests {
     use crate::decoder::STANDARD_ENCODING_LENGTH;
+    use crate::engine::{generator_from_index, MusigEngine};
     use crate::errors::MusigABIError;
-    use crate::signer::MusigBN256WasmSigner;
+    use crate::hash_suite::ChallengeHash;
+    use crate::messages::{
+        CommitmentList, PreCommitment, PreCommitmentList, SignatureShare, SignatureShareList,
+    };
+    use crate::session_manager::MusigBN256WasmSessionManager;
+    use crate::signer::{MusigBN256WasmSigner, WasmSigner};
     use crate::verifier::MusigBN256WasmVerifier;
     use bellman::pairing::bn256::Bn256;
     use bellman::{Field, PrimeField, PrimeFieldRepr};
@@ -38,14 +44,26 @@ mod tests {
         Ok((privkeys, pubkeys))
     }
 
-    fn musig_wasm_multiparty_full_round() {
+    fn encode_pubkeys(pubkeys: &[PublicKey<Bn256>]) -> Vec<u8> {
+        let mut encoded_pubkeys = vec![0u8; pubkeys.len() * STANDARD_ENCODING_LENGTH];
+        for (position, pubkey) in pubkeys.iter().enumerate() {
+            let offset = position * STANDARD_ENCODING_LENGTH;
+            pubkey
+                .write(&mut encoded_pubkeys[offset..(offset + STANDARD_ENCODING_LENGTH)])
+                .unwrap();
+        }
+
+        encoded_pubkeys
+    }
+
+    fn musig_wasm_multiparty_full_round(generator_index: u8, challenge_hash: ChallengeHash) {
         let number_of_parties = 2;
 
         let message = vec![1, 2, 3, 4, 5];
 
         let (privkeys, pubkeys) = musig_wasm_bn256_deterministic_setup(
             number_of_parties,
-            FixedGenerators::SpendingKeyGenerator,
+            generator_from_index(generator_index).unwrap(),
         )
         .unwrap();
 
@@ -63,7 +81,13 @@ mod tests {
 
         let mut wasm_signers = vec![];
         for position in 0..pubkeys.len() {
-            let signer = MusigBN256WasmSigner::new(&encoded_pubkeys, position).unwrap();
+            let signer = MusigBN256WasmSigner::new_with_challenge_hash(
+                &encoded_pubkeys,
+                position,
+                generator_index,
+                challenge_hash,
+            )
+            .unwrap();
             wasm_signers.push(signer);
         }
         assert!(wasm_signers.len() == number_of_parties);
@@ -127,9 +151,9 @@ mod tests {
                 .into_repr()
                 .write_be(&mut encoded_privkey[..])
                 .unwrap();
-            let sig_share = wasm_signer.sign(&encoded_privkey, &message).unwrap();
+            let sig_share = wasm_signer.sign(&encoded_privkey, &message, &[]).unwrap();
 
-            signature_shares.extend_from_slice(&sig_share);
+            signature_shares.extend_from_slice(&sig_share.signature_share());
         }
         assert!(signature_shares.len() == number_of_parties * pubkey_len);
 
@@ -152,20 +176,144 @@ mod tests {
             assert_eq!(first_agg_sig[..sig_len], sig[..]);
 
             // verify aggregated signature
-            let is_verified =
-                MusigBN256WasmVerifier::verify(&message, &encoded_pubkeys, &sig).unwrap();
+            let is_verified = MusigBN256WasmVerifier::verify_with_challenge_hash(
+                &message,
+                &encoded_pubkeys,
+                &sig,
+                generator_index,
+                challenge_hash,
+            )
+            .unwrap();
             assert!(is_verified);
         }
+
+        // signature is bound to the challenge hash
+        let other_challenge_hash = match challenge_hash {
+            ChallengeHash::Rescue => ChallengeHash::Sha256,
+            ChallengeHash::Sha256 => ChallengeHash::Rescue,
+        };
+        assert!(!MusigBN256WasmVerifier::verify_with_challenge_hash(
+            &message,
+            &encoded_pubkeys,
+            &first_agg_sig,
+            generator_index,
+            other_challenge_hash,
+        )
+        .unwrap());
     }
 
     #[test]
     fn test_musig_wasm_multiparty_full_round() {
-        musig_wasm_multiparty_full_round()
+        musig_wasm_multiparty_full_round(5, ChallengeHash::Rescue)
+    }
+
+    #[test]
+    fn test_musig_wasm_multiparty_full_round_with_custom_generator() {
+        musig_wasm_multiparty_full_round(0, ChallengeHash::Rescue)
+    }
+
+    #[test]
+    fn test_musig_wasm_multiparty_full_round_with_sha256_challenge() {
+        musig_wasm_multiparty_full_round(5, ChallengeHash::Sha256)
     }
 
     #[wasm_bindgen_test]
     fn test_musig_wasm() {
-        musig_wasm_multiparty_full_round();
+        musig_wasm_multiparty_full_round(5, ChallengeHash::Rescue);
+        musig_wasm_multiparty_full_round(5, ChallengeHash::Sha256);
+    }
+
+    fn musig_wasm_typed_full_round() {
+        let number_of_parties = 3;
+
+        let message = vec![1, 2, 3, 4, 5];
+
+        let (privkeys, pubkeys) = musig_wasm_bn256_deterministic_setup(
+            number_of_parties,
+            FixedGenerators::SpendingKeyGenerator,
+        )
+        .unwrap();
+
+        let encoded_pubkeys = encode_pubkeys(&pubkeys);
+
+        let mut wasm_signers = vec![];
+        for position in 0..number_of_parties {
+            wasm_signers.push(MusigBN256WasmSigner::new(&encoded_pubkeys, position).unwrap());
+        }
+
+        // messages are pushed in reverse order, positions attached
+        // to them define the order for the signer
+        let mut pre_commitments = PreCommitmentList::new();
+        for (i, wasm_signer) in wasm_signers.iter_mut().enumerate().rev() {
+            let seed = [i as u32 + 1, 2, 3, 4];
+            let pre_commitment = wasm_signer.compute_typed_precommitment(&seed).unwrap();
+            assert_eq!(pre_commitment.position(), i);
+
+            // messages survive round trip through their encoding
+            let pre_commitment = PreCommitment::from_bytes(&pre_commitment.to_bytes()).unwrap();
+            pre_commitments.push(&pre_commitment);
+        }
+
+        let mut commitments = CommitmentList::new();
+        for wasm_signer in wasm_signers.iter_mut().rev() {
+            let commitment = wasm_signer
+                .receive_typed_precommitments(&pre_commitments)
+                .unwrap();
+            commitments.push(&commitment);
+        }
+
+        for wasm_signer in wasm_signers.iter_mut() {
+            wasm_signer.receive_typed_commitments(&commitments).unwrap();
+        }
+
+        let mut signature_shares = SignatureShareList::new();
+        for (position, wasm_signer) in wasm_signers.iter_mut().enumerate().rev() {
+            let mut encoded_privkey = vec![0u8; STANDARD_ENCODING_LENGTH];
+            privkeys[position]
+                .0
+                .into_repr()
+                .write_be(&mut encoded_privkey[..])
+                .unwrap();
+            let signature_share = wasm_signer
+                .sign(&encoded_privkey, &message, &[])
+                .unwrap()
+                .typed_signature_share();
+            let signature_share = SignatureShare::from_bytes(&signature_share.to_bytes()).unwrap();
+            signature_shares.push(&signature_share);
+        }
+
+        for wasm_signer in wasm_signers.iter() {
+            let signature = wasm_signer
+                .receive_typed_signature_shares(&signature_shares)
+                .unwrap();
+            assert!(
+                MusigBN256WasmVerifier::verify_typed(&message, &encoded_pubkeys, &signature)
+                    .unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_musig_wasm_typed_full_round() {
+        musig_wasm_typed_full_round()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_invalid_generator() {
+        let number_of_parties = 2;
+
+        let (_, pubkeys) = musig_wasm_bn256_deterministic_setup(
+            number_of_parties,
+            FixedGenerators::SpendingKeyGenerator,
+        )
+        .unwrap();
+
+        let encoded_pubkeys = encode_pubkeys(&pubkeys);
+
+        match MusigBN256WasmSigner::new_with_generator(&encoded_pubkeys, 0, 6) {
+            Err(e) => assert_eq!(e, MusigABIError::InvalidGenerator.to_string()),
+            _ => unreachable!(),
+        }
     }
 
     #[wasm_bindgen_test]
@@ -198,4 +346,257 @@ mod tests {
             }
         }
     }
+
+    fn musig_wasm_concurrent_sessions() {
+        let number_of_parties = 2;
+        let session_ids = ["first", "second"];
+        let messages = [vec![1, 2, 3], vec![4, 5, 6]];
+
+        let (privkeys, pubkeys) = musig_wasm_bn256_deterministic_setup(
+            number_of_parties,
+            FixedGenerators::SpendingKeyGenerator,
+        )
+        .unwrap();
+
+        let encoded_pubkeys = encode_pubkeys(&pubkeys);
+
+        let mut managers = vec![];
+        for position in 0..number_of_parties {
+            let mut manager =
+                MusigBN256WasmSessionManager::new(&encoded_pubkeys, position).unwrap();
+            for session_id in session_ids.iter() {
+                manager.start_session(session_id).unwrap();
+            }
+            managers.push(manager);
+        }
+
+        // rounds of both sessions are interleaved
+        let mut pre_commitments = vec![vec![]; session_ids.len()];
+        for (i, manager) in managers.iter_mut().enumerate() {
+            for (j, session_id) in session_ids.iter().enumerate() {
+                let seed = [i as u32 + 1, j as u32 + 1, 3, 4];
+                let pre_commitment = manager.compute_precommitment(session_id, &seed).unwrap();
+                pre_commitments[j].extend_from_slice(&pre_commitment);
+            }
+        }
+
+        let mut commitments = vec![vec![]; session_ids.len()];
+        for manager in managers.iter_mut() {
+            for (j, session_id) in session_ids.iter().enumerate() {
+                let commitment = manager
+                    .receive_precommitments(session_id, &pre_commitments[j])
+                    .unwrap();
+                commitments[j].extend_from_slice(&commitment);
+            }
+        }
+
+        for manager in managers.iter_mut() {
+            for (j, session_id) in session_ids.iter().enumerate() {
+                manager
+                    .receive_commitments(session_id, &commitments[j])
+                    .unwrap();
+            }
+        }
+
+        let mut signature_shares = vec![vec![]; session_ids.len()];
+        for (position, manager) in managers.iter_mut().enumerate() {
+            let mut encoded_privkey = vec![0u8; STANDARD_ENCODING_LENGTH];
+            privkeys[position]
+                .0
+                .into_repr()
+                .write_be(&mut encoded_privkey[..])
+                .unwrap();
+            for (j, session_id) in session_ids.iter().enumerate() {
+                let share = manager
+                    .sign(session_id, &encoded_privkey, &messages[j])
+                    .unwrap();
+                signature_shares[j].extend_from_slice(&share);
+            }
+        }
+
+        for manager in managers.iter_mut() {
+            for (j, session_id) in session_ids.iter().enumerate() {
+                let signature = manager
+                    .receive_signature_shares(session_id, &signature_shares[j])
+                    .unwrap();
+                assert!(
+                    MusigBN256WasmVerifier::verify(&messages[j], &encoded_pubkeys, &signature)
+                        .unwrap()
+                );
+                manager.finish_session(session_id).unwrap();
+            }
+            assert_eq!(manager.number_of_sessions(), 0);
+        }
+    }
+
+    #[test]
+    fn test_musig_wasm_concurrent_sessions() {
+        musig_wasm_concurrent_sessions()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_session_id_reuse() {
+        let number_of_parties = 2;
+
+        let (_, pubkeys) = musig_wasm_bn256_deterministic_setup(
+            number_of_parties,
+            FixedGenerators::SpendingKeyGenerator,
+        )
+        .unwrap();
+
+        let encoded_pubkeys = encode_pubkeys(&pubkeys);
+
+        let mut manager = MusigBN256WasmSessionManager::new(&encoded_pubkeys, 0).unwrap();
+        manager.start_session("session").unwrap();
+        assert_eq!(
+            manager.start_session("session").unwrap_err(),
+            MusigABIError::SessionAlreadyExists.to_string()
+        );
+
+        // finished session id can't be reused either
+        manager.finish_session("session").unwrap();
+        assert_eq!(
+            manager.start_session("session").unwrap_err(),
+            MusigABIError::SessionAlreadyExists.to_string()
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_signers_share_cached_params() {
+        let number_of_parties = 2;
+
+        let (_, pubkeys) = musig_wasm_bn256_deterministic_setup(
+            number_of_parties,
+            FixedGenerators::SpendingKeyGenerator,
+        )
+        .unwrap();
+
+        let encoded_pubkeys = encode_pubkeys(&pubkeys);
+
+        let signers: Vec<_> = (0..number_of_parties)
+            .map(|position| {
+                WasmSigner::<Bn256>::new(
+                    &encoded_pubkeys,
+                    position,
+                    FixedGenerators::SpendingKeyGenerator,
+                    ChallengeHash::Rescue,
+                )
+                .unwrap()
+            })
+            .collect();
+
+        // parameters are built once and every signer borrows the same ones
+        assert!(std::ptr::eq(
+            signers[0].jubjub_params(),
+            signers[1].jubjub_params()
+        ));
+        assert!(std::ptr::eq(
+            signers[0].jubjub_params(),
+            Bn256::jubjub_params()
+        ));
+        assert!(std::ptr::eq(Bn256::rescue_params(), Bn256::rescue_params()));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_signer_state_export_import() {
+        let number_of_parties = 2;
+        let message = vec![1, 2, 3, 4, 5];
+        let password = "password";
+        let seed = [1u32, 2, 3, 4];
+
+        let (privkeys, pubkeys) = musig_wasm_bn256_deterministic_setup(
+            number_of_parties,
+            FixedGenerators::SpendingKeyGenerator,
+        )
+        .unwrap();
+
+        let encoded_pubkeys = encode_pubkeys(&pubkeys);
+
+        let encoded_privkeys: Vec<_> = privkeys
+            .iter()
+            .map(|privkey| {
+                let mut encoded_privkey = vec![0u8; STANDARD_ENCODING_LENGTH];
+                privkey
+                    .0
+                    .into_repr()
+                    .write_be(&mut encoded_privkey[..])
+                    .unwrap();
+                encoded_privkey
+            })
+            .collect();
+
+        let mut signers: Vec<_> = (0..number_of_parties)
+            .map(|position| MusigBN256WasmSigner::new(&encoded_pubkeys, position).unwrap())
+            .collect();
+        let mut used_nonces = vec![];
+
+        // first signer is restored after each round
+        let mut pre_commitments = vec![];
+        for (position, signer) in signers.iter_mut().enumerate() {
+            let seed = [position as u32 + 1; 4];
+            pre_commitments.extend_from_slice(&signer.compute_precommitment(&seed).unwrap());
+        }
+        let state = signers[0].export_state(password, &seed).unwrap();
+        signers[0] = MusigBN256WasmSigner::import_state(&state, password, &used_nonces).unwrap();
+
+        let mut commitments = vec![];
+        for signer in signers.iter_mut() {
+            commitments
+                .extend_from_slice(&signer.receive_precommitments(&pre_commitments).unwrap());
+        }
+        let state = signers[0].export_state(password, &seed).unwrap();
+        signers[0] = MusigBN256WasmSigner::import_state(&state, password, &used_nonces).unwrap();
+
+        for signer in signers.iter_mut() {
+            signer.receive_commitments(&commitments).unwrap();
+        }
+        let state_before_signing = signers[0].export_state(password, &seed).unwrap();
+        signers[0] =
+            MusigBN256WasmSigner::import_state(&state_before_signing, password, &used_nonces)
+                .unwrap();
+
+        let mut signature_shares = vec![];
+        for (position, (signer, encoded_privkey)) in
+            signers.iter_mut().zip(encoded_privkeys.iter()).enumerate()
+        {
+            let output = signer
+                .sign(encoded_privkey, &message, &used_nonces)
+                .unwrap();
+            signature_shares.extend_from_slice(&output.signature_share());
+            if position == 0 {
+                used_nonces = output.used_nonces();
+            }
+        }
+
+        let signature = signers[0]
+            .receive_signature_shares(&signature_shares)
+            .unwrap();
+        assert!(MusigBN256WasmVerifier::verify(&message, &encoded_pubkeys, &signature).unwrap());
+
+        // state exported before signing can't sign another message
+        let mut stale_signer =
+            MusigBN256WasmSigner::import_state(&state_before_signing, password, &used_nonces)
+                .unwrap();
+        match stale_signer.sign(&encoded_privkeys[0], &[6, 7, 8], &used_nonces) {
+            Err(e) => assert_eq!(e, MusigError::NonceAlreadyUsed.to_string()),
+            _ => unreachable!(),
+        }
+
+        // the same holds when the list is only passed at signing
+        let mut stale_signer =
+            MusigBN256WasmSigner::import_state(&state_before_signing, password, &[]).unwrap();
+        match stale_signer.sign(&encoded_privkeys[0], &[6, 7, 8], &used_nonces) {
+            Err(e) => assert_eq!(e, MusigError::NonceAlreadyUsed.to_string()),
+            _ => unreachable!(),
+        }
+
+        match MusigBN256WasmSigner::import_state(
+            &state_before_signing,
+            "wrong password",
+            &used_nonces,
+        ) {
+            Err(e) => assert_eq!(e, MusigABIError::DecryptionError.to_string()),
+            _ => unreachable!(),
+        }
+    }
 }
@@ -1,12 +1,44 @@
-use crate::decoder::{Decoder, STANDARD_ENCODING_LENGTH};
-use bellman::{PrimeField, PrimeFieldRepr};
-use franklin_crypto::alt_babyjubjub::{fs::Fs, fs::FsRepr, AltJubjubBn256};
+use crate::decoder::Decoder;
+use crate::engine::{generator_from_index, MusigEngine, DEFAULT_CHALLENGE_HASH, DEFAULT_GENERATOR};
+use crate::errors::MusigABIError;
+use crate::hash_suite::ChallengeHash;
+use crate::messages::AggregatedSignature;
+use bellman::pairing::bn256::Bn256;
 use franklin_crypto::eddsa::Signature;
-use franklin_crypto::jubjub::edwards::Point;
 use franklin_crypto::jubjub::FixedGenerators;
+use musig::signature::MusigSignature;
 use musig::verifier::MuSigVerifier;
 use wasm_bindgen::prelude::*;
 
+/// Engine-generic verification of an encoded aggregated signature.
+pub fn verify<E: MusigEngine>(
+    message: &[u8],
+    encoded_pubkeys: &[u8],
+    encoded_signature: &[u8],
+    generator: FixedGenerators,
+    challenge_hash: ChallengeHash,
+) -> Result<bool, JsValue> {
+    let jubjub_params = E::jubjub_params();
+
+    let pubkeys = Decoder::decode_pubkey_list::<E>(encoded_pubkeys, jubjub_params)?;
+
+    let signature: Signature<E> = MusigSignature::from_bytes(encoded_signature, jubjub_params)
+        .map_err(|_| MusigABIError::InvalidInputData)?
+        .into();
+
+    let is_valid = MuSigVerifier::verify(
+        message,
+        &pubkeys,
+        &signature,
+        jubjub_params,
+        generator,
+        &E::hash_suite(challenge_hash),
+    )
+    .unwrap();
+
+    Ok(is_valid)
+}
+
 #[wasm_bindgen]
 pub struct MusigBN256WasmVerifier;
 
@@ -18,36 +50,69 @@ impl MusigBN256WasmVerifier {
         encoded_pubkeys: &[u8],
         encoded_signature: &[u8],
     ) -> Result<bool, JsValue> {
-        let jubjub_params = AltJubjubBn256::new();
-        let generator = FixedGenerators::SpendingKeyGenerator;
-        let rescue_params =
-            franklin_crypto::rescue::bn256::Bn256RescueParams::new_checked_2_into_1();
+        verify::<Bn256>(
+            message,
+            encoded_pubkeys,
+            encoded_signature,
+            DEFAULT_GENERATOR,
+            DEFAULT_CHALLENGE_HASH,
+        )
+    }
 
-        let pubkeys = Decoder::decode_pubkey_list(encoded_pubkeys)?;
+    /// Same as `verify` but uses generator with given index
+    /// instead of the default one.
+    #[wasm_bindgen]
+    pub fn verify_with_generator(
+        message: &[u8],
+        encoded_pubkeys: &[u8],
+        encoded_signature: &[u8],
+        generator: u8,
+    ) -> Result<bool, JsValue> {
+        let generator = generator_from_index(generator)?;
 
-        let sig_r = Point::read(
-            &encoded_signature[..STANDARD_ENCODING_LENGTH],
-            &jubjub_params,
+        verify::<Bn256>(
+            message,
+            encoded_pubkeys,
+            encoded_signature,
+            generator,
+            DEFAULT_CHALLENGE_HASH,
         )
-        .unwrap();
-
-        let mut repr = FsRepr::default();
-        repr.read_le(&encoded_signature[STANDARD_ENCODING_LENGTH..])
-            .unwrap();
-        let sig_s = Fs::from_repr(repr).unwrap();
+    }
 
-        let signature = Signature { r: sig_r, s: sig_s };
+    /// Same as `verify_with_generator` for signatures produced
+    /// with the given challenge hash.
+    #[wasm_bindgen]
+    pub fn verify_with_challenge_hash(
+        message: &[u8],
+        encoded_pubkeys: &[u8],
+        encoded_signature: &[u8],
+        generator: u8,
+        challenge_hash: ChallengeHash,
+    ) -> Result<bool, JsValue> {
+        let generator = generator_from_index(generator)?;
 
-        let is_valid = MuSigVerifier::verify(
+        verify::<Bn256>(
             message,
-            &pubkeys,
-            &signature,
-            &jubjub_params,
+            encoded_pubkeys,
+            encoded_signature,
             generator,
-            &rescue_params,
+            challenge_hash,
         )
-        .unwrap();
+    }
 
-        Ok(is_valid)
+    /// Typed version of `verify`
+    #[wasm_bindgen]
+    pub fn verify_typed(
+        message: &[u8],
+        encoded_pubkeys: &[u8],
+        signature: &AggregatedSignature,
+    ) -> Result<bool, JsValue> {
+        verify::<Bn256>(
+            message,
+            encoded_pubkeys,
+            &signature.to_bytes(),
+            DEFAULT_GENERATOR,
+            DEFAULT_CHALLENGE_HASH,
+        )
     }
 }